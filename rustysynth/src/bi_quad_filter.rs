@@ -91,8 +91,11 @@ impl BiQuadFilter {
                 *input = output;
             }
         } else {
-            self.x2 = block[block_length - 2];
-            self.x1 = block[block_length - 1];
+            // The block can be shorter than two samples when it is split at an event.
+            for input in block.iter().skip(block_length.saturating_sub(2)) {
+                self.x2 = self.x1;
+                self.x1 = *input;
+            }
             self.y2 = self.x2;
             self.y1 = self.x1;
         }
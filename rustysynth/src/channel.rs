@@ -1,5 +1,8 @@
 #![allow(dead_code)]
 
+use crate::generator_type::GeneratorType;
+use crate::soundfont_math::SoundFontMath;
+
 #[non_exhaustive]
 pub(crate) struct Channel {
     pub(crate) is_percussion_channel: bool,
@@ -11,20 +14,52 @@ pub(crate) struct Channel {
     volume: i16,
     pan: i16,
     expression: i16,
+    brightness: i16,
     hold_pedal: bool,
+    sostenuto_pedal: bool,
+    soft_pedal: bool,
+
+    portamento: bool,
+    portamento_time: i16,
+    portamento_control: Option<i32>,
+    last_key: Option<i32>,
+    mono_mode: bool,
 
     reverb_send: u8,
     chorus_send: u8,
 
     rpn: i16,
+    nrpn: i16,
+    nrpn_selected: bool,
     pitch_bend_range: i16,
     coarse_tune: i16,
     fine_tune: i16,
 
     pitch_bend: f32,
+
+    channel_pressure: i16,
+    key_pressures: [i16; 128],
+
+    // The SoundFont 2.01 NRPN selects a generator by summing the LSBs of 100, 1000 and 10000,
+    // which precede the LSB of the last digits.
+    generator_select_base: i32,
+    selected_generator: Option<usize>,
+    generator_offsets: [i16; GeneratorType::COUNT],
+
+    // The gain controlled by the application, which is not affected by the MIDI messages.
+    pub(crate) output_gain: f32,
+
+    // The gain to freeze the notes, which changes by the step every sample.
+    // The notes are kept as they are while the channel is frozen.
+    freeze_gain: f32,
+    freeze_step: f32,
+    frozen: bool,
 }
 
 impl Channel {
+    // The NRPN MSB used by the SoundFont 2.01 specification to control the generators.
+    const SOUNDFONT_NRPN: i16 = 120;
+
     pub(crate) fn new(is_percussion_channel: bool) -> Self {
         let mut channel = Self {
             is_percussion_channel,
@@ -34,14 +69,33 @@ impl Channel {
             volume: 0,
             pan: 0,
             expression: 0,
+            brightness: 0,
             hold_pedal: false,
+            sostenuto_pedal: false,
+            soft_pedal: false,
+            portamento: false,
+            portamento_time: 0,
+            portamento_control: None,
+            last_key: None,
+            mono_mode: false,
             reverb_send: 0,
             chorus_send: 0,
             rpn: 0,
+            nrpn: 0,
+            nrpn_selected: false,
             pitch_bend_range: 0,
             coarse_tune: 0,
             fine_tune: 0,
             pitch_bend: 0_f32,
+            channel_pressure: 0,
+            key_pressures: [0; 128],
+            generator_select_base: 0,
+            selected_generator: None,
+            generator_offsets: [0; GeneratorType::COUNT],
+            output_gain: 1_f32,
+            freeze_gain: 1_f32,
+            freeze_step: 0_f32,
+            frozen: false,
         };
 
         channel.reset();
@@ -57,27 +111,61 @@ impl Channel {
         self.volume = 100 << 7;
         self.pan = 64 << 7;
         self.expression = 127 << 7;
+        self.brightness = 64 << 7;
         self.hold_pedal = false;
+        self.sostenuto_pedal = false;
+        self.soft_pedal = false;
+
+        self.portamento = false;
+        self.portamento_time = 0;
+        self.portamento_control = None;
+        self.last_key = None;
+        self.mono_mode = false;
 
         self.reverb_send = 40;
         self.chorus_send = 0;
 
         self.rpn = -1;
+        self.nrpn = -1;
+        self.nrpn_selected = false;
         self.pitch_bend_range = 2 << 7;
         self.coarse_tune = 0;
         self.fine_tune = 8192;
 
         self.pitch_bend = 0_f32;
+
+        self.channel_pressure = 0;
+        self.key_pressures = [0; 128];
+
+        self.reset_generator_offsets();
     }
 
     pub(crate) fn reset_all_controllers(&mut self) {
         self.modulation = 0;
         self.expression = 127 << 7;
         self.hold_pedal = false;
+        self.sostenuto_pedal = false;
+        self.soft_pedal = false;
+
+        self.portamento = false;
+        self.portamento_control = None;
 
         self.rpn = -1;
+        self.nrpn = -1;
+        self.nrpn_selected = false;
 
         self.pitch_bend = 0_f32;
+
+        self.channel_pressure = 0;
+        self.key_pressures = [0; 128];
+
+        self.reset_generator_offsets();
+    }
+
+    fn reset_generator_offsets(&mut self) {
+        self.generator_select_base = 0;
+        self.selected_generator = None;
+        self.generator_offsets = [0; GeneratorType::COUNT];
     }
 
     pub(crate) fn set_bank(&mut self, value: i32) {
@@ -88,6 +176,13 @@ impl Channel {
         }
     }
 
+    pub(crate) fn set_percussion_channel(&mut self, value: bool) {
+        if self.is_percussion_channel != value {
+            self.is_percussion_channel = value;
+            self.bank_number += if value { 128 } else { -128 };
+        }
+    }
+
     pub(crate) fn set_patch(&mut self, value: i32) {
         self.patch_number = value;
     }
@@ -124,10 +219,75 @@ impl Channel {
         self.expression = (((self.expression as i32) & 0xFF80) | value) as i16;
     }
 
+    pub(crate) fn set_modulation(&mut self, value: i32) {
+        self.modulation = value as i16;
+    }
+
+    pub(crate) fn set_volume(&mut self, value: i32) {
+        self.volume = value as i16;
+    }
+
+    pub(crate) fn set_pan(&mut self, value: i32) {
+        self.pan = value as i16;
+    }
+
+    pub(crate) fn set_expression(&mut self, value: i32) {
+        self.expression = value as i16;
+    }
+
+    pub(crate) fn set_brightness(&mut self, value: i32) {
+        self.brightness = value as i16;
+    }
+
     pub(crate) fn set_hold_pedal(&mut self, value: i32) {
         self.hold_pedal = value >= 64;
     }
 
+    pub(crate) fn set_sostenuto_pedal(&mut self, value: i32) {
+        self.sostenuto_pedal = value >= 64;
+    }
+
+    pub(crate) fn set_soft_pedal(&mut self, value: i32) {
+        self.soft_pedal = value >= 64;
+    }
+
+    pub(crate) fn set_portamento(&mut self, value: i32) {
+        self.portamento = value >= 64;
+    }
+
+    pub(crate) fn set_portamento_time_coarse(&mut self, value: i32) {
+        self.portamento_time = (self.portamento_time & 0x7F) | (value << 7) as i16;
+    }
+
+    pub(crate) fn set_portamento_time_fine(&mut self, value: i32) {
+        self.portamento_time = (((self.portamento_time as i32) & 0xFF80) | value) as i16;
+    }
+
+    pub(crate) fn set_portamento_time(&mut self, value: i32) {
+        self.portamento_time = value as i16;
+    }
+
+    // The portamento control gives the source key of the glide to the next note only.
+    pub(crate) fn set_portamento_control(&mut self, value: i32) {
+        self.portamento_control = Some(value);
+    }
+
+    pub(crate) fn set_mono_mode(&mut self, value: bool) {
+        self.mono_mode = value;
+    }
+
+    // Gets the source key of the glide to the new note, and records the new note
+    // as the source of the next glide.
+    pub(crate) fn start_portamento(&mut self, key: i32) -> Option<i32> {
+        let source = match self.portamento_control.take() {
+            Some(value) => Some(value),
+            None if self.portamento => self.last_key,
+            None => None,
+        };
+        self.last_key = Some(key);
+        source.filter(|&value| value != key)
+    }
+
     pub(crate) fn set_reverb_send(&mut self, value: i32) {
         self.reverb_send = value as u8;
     }
@@ -136,29 +296,194 @@ impl Channel {
         self.chorus_send = value as u8;
     }
 
+    pub(crate) fn set_channel_pressure(&mut self, value: i32) {
+        self.channel_pressure = value as i16;
+    }
+
+    pub(crate) fn set_key_pressure(&mut self, key: i32, value: i32) {
+        if let Some(pressure) = self.key_pressures.get_mut(key as usize) {
+            *pressure = value as i16;
+        }
+    }
+
     pub(crate) fn set_rpn_coarse(&mut self, value: i32) {
         self.rpn = (self.rpn & 0x7F) | (value << 7) as i16;
+        self.nrpn_selected = false;
     }
 
     pub(crate) fn set_rpn_fine(&mut self, value: i32) {
         self.rpn = (((self.rpn as i32) & 0xFF80) | value) as i16;
+        self.nrpn_selected = false;
+    }
+
+    pub(crate) fn set_nrpn_coarse(&mut self, value: i32) {
+        self.nrpn = (self.nrpn & 0x7F) | (value << 7) as i16;
+        self.nrpn_selected = true;
+    }
+
+    pub(crate) fn set_nrpn_fine(&mut self, value: i32) {
+        self.nrpn = (((self.nrpn as i32) & 0xFF80) | value) as i16;
+        self.nrpn_selected = true;
+
+        if (self.nrpn >> 7) == Channel::SOUNDFONT_NRPN {
+            match value {
+                100 => self.generator_select_base += 100,
+                101 => self.generator_select_base += 1000,
+                102 => self.generator_select_base += 10000,
+                _ => {
+                    let generator = (self.generator_select_base + value) as usize;
+                    self.selected_generator = Some(generator)
+                        .filter(|&generator| Channel::get_generator_scale(generator).is_some());
+                    self.generator_select_base = 0;
+                }
+            }
+        }
     }
 
     pub(crate) fn data_entry_coarse(&mut self, value: i32) {
-        if self.rpn == 0 {
-            self.pitch_bend_range = (self.pitch_bend_range & 0x7F) | (value << 7) as i16;
-        } else if self.rpn == 1 {
-            self.fine_tune = (self.fine_tune & 0x7F) | (value << 7) as i16;
-        } else if self.rpn == 2 {
-            self.coarse_tune = (value - 64) as i16;
+        if let Some(data) = self.get_parameter_data() {
+            self.set_parameter_data((data & 0x7F) | (value << 7));
         }
     }
 
     pub(crate) fn data_entry_fine(&mut self, value: i32) {
-        if self.rpn == 0 {
-            self.pitch_bend_range = (((self.pitch_bend_range as i32) & 0xFF80) | value) as i16;
-        } else if self.rpn == 1 {
-            self.fine_tune = (((self.fine_tune as i32) & 0xFF80) | value) as i16;
+        if let Some(data) = self.get_parameter_data() {
+            self.set_parameter_data((data & 0xFF80) | value);
+        }
+    }
+
+    pub(crate) fn data_entry(&mut self, value: i32) {
+        if self.get_parameter_data().is_some() {
+            self.set_parameter_data(value);
+        }
+    }
+
+    pub(crate) fn data_increment(&mut self) {
+        if let Some(data) = self.get_parameter_data() {
+            self.set_parameter_data(data + self.get_parameter_step());
+        }
+    }
+
+    pub(crate) fn data_decrement(&mut self) {
+        if let Some(data) = self.get_parameter_data() {
+            self.set_parameter_data(data - self.get_parameter_step());
+        }
+    }
+
+    // The notes are faded out in the length before they are frozen, so that they make no click.
+    pub(crate) fn freeze(&mut self, length: usize) {
+        if length == 0 {
+            self.freeze_gain = 0_f32;
+            self.freeze_step = 0_f32;
+        } else {
+            self.freeze_step = -1_f32 / length as f32;
+        }
+    }
+
+    pub(crate) fn unfreeze(&mut self, length: usize) {
+        if length == 0 {
+            self.freeze_gain = 1_f32;
+            self.freeze_step = 0_f32;
+        } else {
+            self.freeze_step = 1_f32 / length as f32;
+        }
+    }
+
+    // The channel is frozen from the block after the one in which the gain reaches zero.
+    pub(crate) fn process_freeze(&mut self, block_length: usize) {
+        self.frozen = self.freeze_gain == 0_f32 && self.freeze_step == 0_f32;
+
+        if self.freeze_step != 0_f32 {
+            let freeze_gain = self.freeze_gain + self.freeze_step * block_length as f32;
+            self.freeze_gain = SoundFontMath::clamp(freeze_gain, 0_f32, 1_f32);
+            if self.freeze_gain == 0_f32 || self.freeze_gain == 1_f32 {
+                self.freeze_step = 0_f32;
+            }
+        }
+    }
+
+    // Gets the 14-bit value of the parameter selected by the RPN or the NRPN.
+    fn get_parameter_data(&self) -> Option<i32> {
+        if self.nrpn_selected {
+            if (self.nrpn >> 7) != Channel::SOUNDFONT_NRPN {
+                return None;
+            }
+
+            // The data is centered at 8192, which corresponds to no offset.
+            let generator = self.selected_generator?;
+            let scale = Channel::get_generator_scale(generator)?;
+            return Some(self.generator_offsets[generator] as i32 / scale + 8192);
+        }
+
+        match self.rpn {
+            0 => Some(self.pitch_bend_range as i32),
+            1 => Some(self.fine_tune as i32),
+            2 => Some((self.coarse_tune as i32 + 64) << 7),
+            _ => None,
+        }
+    }
+
+    fn set_parameter_data(&mut self, value: i32) {
+        let value = value.clamp(0, 16383);
+
+        if self.nrpn_selected {
+            if let Some(generator) = self.selected_generator {
+                if let Some(scale) = Channel::get_generator_scale(generator) {
+                    self.generator_offsets[generator] = (scale * (value - 8192)) as i16;
+                }
+            }
+            return;
+        }
+
+        match self.rpn {
+            0 => self.pitch_bend_range = value as i16,
+            1 => self.fine_tune = value as i16,
+            2 => self.coarse_tune = ((value >> 7) - 64) as i16,
+            _ => (),
+        }
+    }
+
+    // The coarse tuning has no fine part, so it is changed by one semitone.
+    fn get_parameter_step(&self) -> i32 {
+        if !self.nrpn_selected && self.rpn == 2 {
+            1 << 7
+        } else {
+            1
+        }
+    }
+
+    // Gets the multiplier of the NRPN data for the generator, or `None` if the generator
+    // cannot be changed in real time.
+    // According to the SoundFont 2.01 specification, the data is doubled for the generators
+    // whose ranges exceed the range of the data.
+    fn get_generator_scale(generator: usize) -> Option<i32> {
+        match generator as u16 {
+            GeneratorType::MODULATION_LFO_TO_PITCH
+            | GeneratorType::VIBRATO_LFO_TO_PITCH
+            | GeneratorType::MODULATION_ENVELOPE_TO_PITCH
+            | GeneratorType::INITIAL_FILTER_CUTOFF_FREQUENCY
+            | GeneratorType::MODULATION_LFO_TO_FILTER_CUTOFF_FREQUENCY
+            | GeneratorType::MODULATION_ENVELOPE_TO_FILTER_CUTOFF_FREQUENCY
+            | GeneratorType::DELAY_MODULATION_LFO..=GeneratorType::DECAY_MODULATION_ENVELOPE
+            | GeneratorType::RELEASE_MODULATION_ENVELOPE
+            | GeneratorType::DELAY_VOLUME_ENVELOPE..=GeneratorType::DECAY_VOLUME_ENVELOPE
+            | GeneratorType::RELEASE_VOLUME_ENVELOPE => Some(2),
+            GeneratorType::INITIAL_FILTER_Q
+            | GeneratorType::MODULATION_LFO_TO_VOLUME
+            | GeneratorType::CHORUS_EFFECTS_SEND
+            | GeneratorType::REVERB_EFFECTS_SEND
+            | GeneratorType::PAN
+            | GeneratorType::SUSTAIN_MODULATION_ENVELOPE
+            | GeneratorType::KEY_NUMBER_TO_MODULATION_ENVELOPE_HOLD
+            | GeneratorType::KEY_NUMBER_TO_MODULATION_ENVELOPE_DECAY
+            | GeneratorType::SUSTAIN_VOLUME_ENVELOPE
+            | GeneratorType::KEY_NUMBER_TO_VOLUME_ENVELOPE_HOLD
+            | GeneratorType::KEY_NUMBER_TO_VOLUME_ENVELOPE_DECAY
+            | GeneratorType::INITIAL_ATTENUATION
+            | GeneratorType::COARSE_TUNE
+            | GeneratorType::FINE_TUNE
+            | GeneratorType::SCALE_TUNING => Some(1),
+            _ => None,
         }
     }
 
@@ -166,6 +491,40 @@ impl Channel {
         self.pitch_bend = (1_f32 / 8192_f32) * ((value1 | (value2 << 7)) - 8192) as f32;
     }
 
+    pub(crate) fn set_pitch_bend_normalized(&mut self, value: f32) {
+        self.pitch_bend = value;
+    }
+
+    pub(crate) fn set_registered_parameter(&mut self, rpn: i32, value: i32) {
+        match rpn {
+            0 => self.pitch_bend_range = value as i16,
+            1 => self.fine_tune = value as i16,
+            2 => self.coarse_tune = ((value >> 7) - 64) as i16,
+            _ => (),
+        }
+    }
+
+    // The MIDI 2.0 assignable controller gives the NRPN and its data at once,
+    // and does not change the parameter selected for the data entry.
+    pub(crate) fn set_assignable_parameter(&mut self, nrpn: i32, value: i32) {
+        if (nrpn >> 7) as i16 != Channel::SOUNDFONT_NRPN {
+            return;
+        }
+
+        let generator = (nrpn & 0x7F) as usize;
+        if let Some(scale) = Channel::get_generator_scale(generator) {
+            self.generator_offsets[generator] = (scale * (value.clamp(0, 16383) - 8192)) as i16;
+        }
+    }
+
+    pub(crate) fn get_selected_rpn(&self) -> Option<i32> {
+        if self.nrpn_selected {
+            None
+        } else {
+            Some(self.rpn as i32)
+        }
+    }
+
     pub(crate) fn get_bank_number(&self) -> i32 {
         self.bank_number
     }
@@ -190,10 +549,40 @@ impl Channel {
         (1_f32 / 16383_f32) * self.expression as f32
     }
 
+    // The brightness changes the filter cutoff frequency by up to two octaves in cents.
+    pub(crate) fn get_brightness(&self) -> i32 {
+        (2400 * (self.brightness as i32 - 8192)) / 8192
+    }
+
     pub(crate) fn get_hold_pedal(&self) -> bool {
         self.hold_pedal
     }
 
+    pub(crate) fn get_sostenuto_pedal(&self) -> bool {
+        self.sostenuto_pedal
+    }
+
+    pub(crate) fn get_soft_pedal(&self) -> bool {
+        self.soft_pedal
+    }
+
+    pub(crate) fn get_freeze_gain(&self) -> f32 {
+        self.freeze_gain
+    }
+
+    pub(crate) fn is_frozen(&self) -> bool {
+        self.frozen
+    }
+
+    // The portamento time is given in milliseconds.
+    pub(crate) fn get_portamento_time(&self) -> f32 {
+        0.001_f32 * self.portamento_time as f32
+    }
+
+    pub(crate) fn get_mono_mode(&self) -> bool {
+        self.mono_mode
+    }
+
     pub(crate) fn get_reverb_send(&self) -> f32 {
         (1_f32 / 127_f32) * self.reverb_send as f32
     }
@@ -210,6 +599,21 @@ impl Channel {
         self.coarse_tune as f32 + (1_f32 / 8192_f32) * (self.fine_tune - 8192) as f32
     }
 
+    pub(crate) fn get_channel_pressure(&self) -> f32 {
+        (1_f32 / 16383_f32) * self.channel_pressure as f32
+    }
+
+    pub(crate) fn get_key_pressure(&self, key: i32) -> f32 {
+        match self.key_pressures.get(key as usize) {
+            Some(value) => (1_f32 / 16383_f32) * *value as f32,
+            None => 0_f32,
+        }
+    }
+
+    pub(crate) fn get_generator_offsets(&self) -> &[i16; GeneratorType::COUNT] {
+        &self.generator_offsets
+    }
+
     pub(crate) fn get_pitch_bend(&self) -> f32 {
         self.get_pitch_bend_range() * self.pitch_bend
     }
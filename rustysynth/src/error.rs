@@ -14,6 +14,8 @@ pub enum SynthesizerError {
     BlockSizeOutOfRange(usize),
     #[error("the maximum number of polyphony must be between 8 and 256, but was {0}")]
     MaximumPolyphonyOutOfRange(usize),
+    #[error("the number of channels must be between 1 and 240, but was {0}")]
+    ChannelCountOutOfRange(usize),
 }
 
 /// Represents an error when loading a SoundFont.
@@ -84,10 +86,16 @@ pub enum MidiFileError {
     IoError(#[from] io::Error),
     #[error("the chunk type must be '{expected}', but was '{actual}'")]
     InvalidChunkType { expected: FourCC, actual: FourCC },
+    #[error("the '{0}' chunk was not found")]
+    ChunkNotFound(FourCC),
     #[error("the '{0}' chunk has invalid data")]
     InvalidChunkData(FourCC),
     #[error("the format {0} is not supported")]
     UnsupportedFormat(i16),
     #[error("failed to read the tempo value")]
     InvalidTempoValue,
+    #[error("the time division {0} is invalid")]
+    InvalidTimeDivision(i16),
+    #[error("failed to load the embedded SoundFont")]
+    InvalidSoundFont(#[source] ParseError),
 }
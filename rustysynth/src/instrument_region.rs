@@ -4,6 +4,7 @@ use crate::error::ParseError;
 use crate::generator::Generator;
 use crate::generator_type::GeneratorType;
 use crate::loop_mode::LoopMode;
+use crate::modulator::Modulator;
 use crate::sample_header::SampleHeader;
 use crate::soundfont_math::SoundFontMath;
 use crate::zone::Zone;
@@ -29,6 +30,7 @@ pub struct InstrumentRegion {
     pub(crate) sample_sample_rate: i32,
     pub(crate) sample_original_pitch: i32,
     pub(crate) sample_pitch_correction: i32,
+    pub(crate) modulators: Vec<Modulator>,
 }
 
 impl InstrumentRegion {
@@ -85,6 +87,7 @@ impl InstrumentRegion {
             sample_sample_rate: sample.sample_rate,
             sample_original_pitch: sample.original_pitch as i32,
             sample_pitch_correction: sample.pitch_correction as i32,
+            modulators: Modulator::merge(&global.modulators, &local.modulators),
         })
     }
 
@@ -5,7 +5,6 @@ use crate::synthesizer_settings::SynthesizerSettings;
 #[non_exhaustive]
 pub(crate) struct Lfo {
     sample_rate: i32,
-    block_size: usize,
 
     active: bool,
 
@@ -20,7 +19,6 @@ impl Lfo {
     pub(crate) fn new(settings: &SynthesizerSettings) -> Self {
         Self {
             sample_rate: settings.sample_rate,
-            block_size: settings.block_size,
             active: false,
             delay: 0_f64,
             period: 0_f64,
@@ -44,12 +42,12 @@ impl Lfo {
         }
     }
 
-    pub(crate) fn process(&mut self) {
+    pub(crate) fn process(&mut self, sample_count: usize) {
         if !self.active {
             return;
         }
 
-        self.processed_sample_count += self.block_size;
+        self.processed_sample_count += sample_count;
 
         let current_time = self.processed_sample_count as f64 / self.sample_rate as f64;
 
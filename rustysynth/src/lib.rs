@@ -11,6 +11,7 @@ mod instrument;
 mod instrument_info;
 mod instrument_region;
 mod loop_mode;
+mod modulator;
 mod preset;
 mod preset_info;
 mod preset_region;
@@ -30,17 +31,34 @@ mod envelope_stage;
 mod lfo;
 mod modulation_envelope;
 mod oscillator;
+mod pressure_mapping;
 mod region_ex;
 mod region_pair;
 mod synthesizer;
+mod synthesizer_handle;
+mod synthesizer_mpe;
 mod synthesizer_settings;
+mod synthesizer_sysex;
+mod synthesizer_ump;
 mod voice;
 mod voice_collection;
 mod volume_envelope;
 
 mod midifile;
+mod midifile_clip;
+mod midifile_hmi;
 mod midifile_looptype;
+mod midifile_lyric;
+mod midifile_mus;
+mod midifile_player;
+mod midifile_repair;
+mod midifile_sequence;
 mod midifile_sequencer;
+mod midifile_transform;
+mod midifile_transition;
+mod midifile_xmi;
+mod sequencer_event;
+mod tempo_map;
 
 mod chorus;
 mod reverb;
@@ -52,12 +70,22 @@ pub use self::instrument::Instrument;
 pub use self::instrument_region::InstrumentRegion;
 pub use self::midifile::MidiFile;
 pub use self::midifile_looptype::MidiFileLoopType;
+pub use self::midifile_lyric::Lyric;
+pub use self::midifile_player::MidiFilePlayer;
+pub use self::midifile_repair::MidiFileRepair;
 pub use self::midifile_sequencer::MidiFileSequencer;
+pub use self::midifile_transition::MidiFileTransition;
 pub use self::preset::Preset;
 pub use self::preset_region::PresetRegion;
+pub use self::pressure_mapping::PressureMapping;
 pub use self::sample_header::SampleHeader;
+pub use self::sequencer_event::SequencerEvent;
 pub use self::soundfont::SoundFont;
 pub use self::soundfont_info::SoundFontInfo;
 pub use self::soundfont_version::SoundFontVersion;
 pub use self::synthesizer::Synthesizer;
+pub use self::synthesizer_handle::SynthesizerHandle;
 pub use self::synthesizer_settings::SynthesizerSettings;
+pub use self::tempo_map::TempoChange;
+pub use self::tempo_map::TempoMap;
+pub use self::tempo_map::TimeSignature;
@@ -1,12 +1,19 @@
 #![allow(dead_code)]
 
 use std::io::Read;
+use std::sync::Arc;
 
 use crate::binary_reader::BinaryReader;
 use crate::four_cc::FourCC;
+use crate::midifile_lyric::MetaText;
 use crate::read_counter::ReadCounter;
+use crate::soundfont::SoundFont;
+use crate::synthesizer::Synthesizer;
+use crate::tempo_map::TempoMap;
+use crate::Lyric;
 use crate::MidiFileError;
 use crate::MidiFileLoopType;
+use crate::MidiFileRepair;
 
 #[derive(Clone, Copy)]
 #[non_exhaustive]
@@ -19,11 +26,17 @@ pub(crate) struct Message {
 
 impl Message {
     pub(crate) const NORMAL: u8 = 0;
+    pub(crate) const UMP: u8 = 250;
+    pub(crate) const TIME_SIGNATURE: u8 = 251;
     pub(crate) const TEMPO_CHANGE: u8 = 252;
     pub(crate) const LOOP_START: u8 = 253;
     pub(crate) const LOOP_END: u8 = 254;
     pub(crate) const END_OF_TRACK: u8 = 255;
 
+    // The channels of the ports must not overlap the special message types.
+    pub(crate) const PORT_COUNT: usize =
+        Synthesizer::MAXIMUM_CHANNEL_COUNT / Synthesizer::CHANNEL_COUNT;
+
     pub(crate) fn common1(status: u8, data1: u8) -> Self {
         Self {
             channel: status & 0x0F,
@@ -38,31 +51,20 @@ impl Message {
         let command = status & 0xF0;
 
         if command == 0xB0 {
-            match loop_type {
-                MidiFileLoopType::RpgMaker => {
-                    if data1 == 111 {
-                        return Message::loop_start();
-                    }
-                }
-
-                MidiFileLoopType::IncredibleMachine => {
-                    if data1 == 110 {
-                        return Message::loop_start();
-                    }
-                    if data1 == 111 {
-                        return Message::loop_end();
-                    }
+            match (loop_type, data1) {
+                (MidiFileLoopType::RpgMaker, 111) => return Message::loop_start(),
+                (MidiFileLoopType::IncredibleMachine, 110) => return Message::loop_start(),
+                (MidiFileLoopType::IncredibleMachine, 111) => return Message::loop_end(),
+                (MidiFileLoopType::FinalFantasy, 116) => return Message::loop_start(),
+                (MidiFileLoopType::FinalFantasy, 117) => return Message::loop_end(),
+                (MidiFileLoopType::Emidi, 116 | 118) => return Message::loop_start(),
+                (MidiFileLoopType::Emidi, 117 | 119) => return Message::loop_end(),
+                (MidiFileLoopType::Controller(start, _), _) if data1 == start => {
+                    return Message::loop_start()
                 }
-
-                MidiFileLoopType::FinalFantasy => {
-                    if data1 == 116 {
-                        return Message::loop_start();
-                    }
-                    if data1 == 117 {
-                        return Message::loop_end();
-                    }
+                (MidiFileLoopType::Controller(_, end), _) if data1 == end => {
+                    return Message::loop_end()
                 }
-
                 _ => (),
             }
         }
@@ -75,6 +77,17 @@ impl Message {
         }
     }
 
+    pub(crate) fn with_port(self, port: u8) -> Self {
+        if self.get_message_type() != Message::NORMAL {
+            return self;
+        }
+
+        Self {
+            channel: self.channel + Synthesizer::CHANNEL_COUNT as u8 * port,
+            ..self
+        }
+    }
+
     pub(crate) fn tempo_change(tempo: i32) -> Self {
         Self {
             channel: Message::TEMPO_CHANGE,
@@ -84,6 +97,24 @@ impl Message {
         }
     }
 
+    pub(crate) fn time_signature(numerator: u8, denominator: u8) -> Self {
+        Self {
+            channel: Message::TIME_SIGNATURE,
+            command: numerator,
+            data1: denominator,
+            data2: 0,
+        }
+    }
+
+    pub(crate) fn ump(index: usize) -> Self {
+        Self {
+            channel: Message::UMP,
+            command: (index >> 16) as u8,
+            data1: (index >> 8) as u8,
+            data2: index as u8,
+        }
+    }
+
     pub(crate) fn loop_start() -> Self {
         Self {
             channel: Message::LOOP_START,
@@ -113,6 +144,8 @@ impl Message {
 
     pub(crate) fn get_message_type(&self) -> u8 {
         match self.channel {
+            Message::UMP => Message::UMP,
+            Message::TIME_SIGNATURE => Message::TIME_SIGNATURE,
             Message::TEMPO_CHANGE => Message::TEMPO_CHANGE,
             Message::LOOP_START => Message::LOOP_START,
             Message::LOOP_END => Message::LOOP_END,
@@ -121,6 +154,10 @@ impl Message {
         }
     }
 
+    pub(crate) fn get_ump_index(&self) -> usize {
+        ((self.command as usize) << 16) | ((self.data1 as usize) << 8) | (self.data2 as usize)
+    }
+
     pub(crate) fn get_tempo(&self) -> f64 {
         60000000.0
             / (((self.command as i32) << 16) | ((self.data1 as i32) << 8) | (self.data2 as i32))
@@ -129,10 +166,22 @@ impl Message {
 }
 
 /// Represents a standard MIDI file.
+#[derive(Clone)]
 #[non_exhaustive]
 pub struct MidiFile {
     pub(crate) messages: Vec<Message>,
     pub(crate) times: Vec<f64>,
+    pub(crate) ticks: Vec<i32>,
+    pub(crate) tracks: Vec<usize>,
+    pub(crate) track_count: usize,
+    pub(crate) tempo_map: TempoMap,
+    pub(crate) sound_font: Option<Arc<SoundFont>>,
+    pub(crate) bank_offset: i32,
+    pub(crate) repairs: Vec<MidiFileRepair>,
+    pub(crate) ump_packets: Vec<[u32; 2]>,
+    pub(crate) lyrics: Vec<Lyric>,
+    pub(crate) lyric_headers: Vec<String>,
+    pub(crate) marker_ticks: Vec<i32>,
 }
 
 impl MidiFile {
@@ -141,6 +190,24 @@ impl MidiFile {
     /// # Arguments
     ///
     /// * `reader` - The data stream used to load the MIDI file.
+    ///
+    /// # Remarks
+    ///
+    /// Both the standard MIDI file and the RIFF-wrapped MIDI file (RMID) are supported.
+    /// If the RMID file contains an embedded SoundFont, it can be obtained by `get_sound_font`.
+    ///
+    /// The following formats used by old games are also supported, and are converted to the same representation:
+    /// * MUS - The format used by id Software.
+    /// * XMIDI - The format used by the Miles Sound System. Only the first sequence is loaded.
+    /// * HMP and HMI - The formats used by the Human Machine Interfaces Sound Operating System.
+    ///
+    /// The MIDI Clip File (SMF2CLIP) containing Universal MIDI Packets is also supported.
+    /// The MIDI 2.0 channel voice messages are kept at their higher resolution,
+    /// but the transformations such as `transpose` only apply to the MIDI 1.0 messages.
+    ///
+    /// The MIDI port meta events (0x21) are supported, and the channel `c` after the port `p`
+    /// in a track is sent to the channel `16 * p + c` of the synthesizer.
+    /// The synthesizer must be created with enough channels to play such a MIDI file.
     pub fn new<R: Read>(reader: &mut R) -> Result<Self, MidiFileError> {
         MidiFile::new_with_loop_type(reader, MidiFileLoopType::LoopPoint(0))
     }
@@ -156,17 +223,82 @@ impl MidiFile {
     ///
     /// `MidiFileLoopType` has the following variants:
     /// * `LoopPoint(usize)` - Specifies the loop start point by a tick value.
+    /// * `LoopRange(usize, usize)` - Specifies the loop start and end points by tick values.
     /// * `RpgMaker` - The RPG Maker style loop.
     ///   CC #111 will be the loop start point.
     /// * `IncredibleMachine` - The Incredible Machine style loop.
     ///   CC #110 and #111 will be the start and end points of the loop.
     /// * `FinalFantasy` - The Final Fantasy style loop.
     ///   CC #116 and #117 will be the start and end points of the loop.
+    /// * `Emidi` - The EMIDI style loop.
+    ///   CC #116 and #118 will be the start points, and CC #117 and #119 will be the end points of the loop.
+    /// * `Marker` - The marker style loop.
+    ///   The markers named `loopStart` and `loopEnd` will be the start and end points of the loop.
+    /// * `Controller(u8, u8)` - Specifies the controllers for the start and end points of the loop.
+    ///
+    /// XMIDI files use CC #116 and #117, and HMP and HMI files use CC #110 and #111 as the loop points,
+    /// unless another loop type based on controllers is specified.
     pub fn new_with_loop_type<R: Read>(
         reader: &mut R,
         loop_type: MidiFileLoopType,
     ) -> Result<Self, MidiFileError> {
-        let chunk_type = BinaryReader::read_four_cc(reader)?;
+        MidiFile::read(reader, loop_type, false)
+    }
+
+    /// Loads a MIDI file from the stream, repairing the common defects of the file.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - The data stream used to load the MIDI file.
+    /// * `loop_type` - The type of the loop extension to be used.
+    ///
+    /// # Remarks
+    ///
+    /// The following defects, which are often found in the MIDI files from old games, are tolerated:
+    /// * A track chunk with a wrong length.
+    /// * A track without the End of Track event.
+    /// * A track count that does not match the track chunks.
+    /// * Unknown chunks between the track chunks.
+    /// * Garbage after the last track, or the stream ending in the middle of a track.
+    ///
+    /// The repairs made while loading can be obtained by `get_repairs`.
+    pub fn new_lenient<R: Read>(
+        reader: &mut R,
+        loop_type: MidiFileLoopType,
+    ) -> Result<Self, MidiFileError> {
+        MidiFile::read(reader, loop_type, true)
+    }
+
+    fn read<R: Read>(
+        reader: &mut R,
+        loop_type: MidiFileLoopType,
+        lenient: bool,
+    ) -> Result<Self, MidiFileError> {
+        // The raw bytes are needed since the MUS signature contains a non-ASCII character.
+        let mut header: [u8; 4] = [0; 4];
+        reader.read_exact(&mut header)?;
+
+        let chunk_type = FourCC::from_bytes(header);
+        if chunk_type == b"RIFF" {
+            return MidiFile::read_rmid(reader, loop_type, lenient);
+        }
+
+        // The sequence formats of old game engines are converted from the whole data.
+        if matches!(
+            &header,
+            b"MUS\x1A" | b"FORM" | b"CAT " | b"HMIM" | b"HMI-" | b"SMF2"
+        ) {
+            let mut data: Vec<u8> = header.to_vec();
+            reader.read_to_end(&mut data)?;
+            return match &header {
+                b"SMF2" => MidiFile::read_clip(&data, loop_type),
+                b"MUS\x1A" => MidiFile::read_mus(&data, loop_type),
+                b"HMIM" => MidiFile::read_hmp(&data, loop_type),
+                b"HMI-" => MidiFile::read_hmi(&data, loop_type),
+                _ => MidiFile::read_xmi(&data, loop_type),
+            };
+        }
+
         if chunk_type != b"MThd" {
             return Err(MidiFileError::InvalidChunkType {
                 expected: FourCC::from_bytes(*b"MThd"),
@@ -174,8 +306,154 @@ impl MidiFile {
             });
         }
 
+        MidiFile::read_standard_midi_file(reader, loop_type, lenient)
+    }
+
+    fn read_rmid<R: Read>(
+        reader: &mut R,
+        loop_type: MidiFileLoopType,
+        lenient: bool,
+    ) -> Result<Self, MidiFileError> {
+        let end = BinaryReader::read_u32(reader)? as usize;
+        let reader = &mut ReadCounter::new(reader);
+
+        let form_type = BinaryReader::read_four_cc(reader)?;
+        if form_type != b"RMID" {
+            return Err(MidiFileError::InvalidChunkType {
+                expected: FourCC::from_bytes(*b"RMID"),
+                actual: form_type,
+            });
+        }
+
+        let mut midi_file: Option<MidiFile> = None;
+        let mut sound_font: Option<SoundFont> = None;
+
+        // According to the RMIDI specification, the bank offset is 1 if the DBNK chunk is absent.
+        let mut bank_offset: i32 = 1;
+
+        while reader.bytes_read() < end {
+            let header = BinaryReader::read_four_cc(reader)
+                .and_then(|id| Ok((id, BinaryReader::read_u32(reader)? as usize)));
+            let (id, size) = match header {
+                Ok(value) => value,
+                // The RIFF size of a truncated file exceeds the actual data.
+                Err(_) if lenient => break,
+                Err(err) => return Err(MidiFileError::IoError(err)),
+            };
+            let chunk = &mut ReadCounter::new(&mut *reader);
+
+            match id.as_bytes() {
+                b"data" => {
+                    let chunk_type = BinaryReader::read_four_cc(chunk)?;
+                    if chunk_type != b"MThd" {
+                        return Err(MidiFileError::InvalidChunkType {
+                            expected: FourCC::from_bytes(*b"MThd"),
+                            actual: chunk_type,
+                        });
+                    }
+                    midi_file = Some(MidiFile::read_standard_midi_file(
+                        &mut (&mut *chunk).take(size.saturating_sub(4) as u64),
+                        loop_type,
+                        lenient,
+                    )?);
+                }
+                b"DBNK" if size >= 2 => bank_offset = BinaryReader::read_u16(chunk)? as i32,
+                b"LIST" if size >= 4 => {
+                    // The RMIDI specification places the DBNK chunk in the INFO list.
+                    let list_type = BinaryReader::read_four_cc(chunk)?;
+                    if list_type == b"INFO" {
+                        if let Some(value) = MidiFile::read_rmid_info(chunk, size - 4)? {
+                            bank_offset = value;
+                        }
+                    }
+                }
+                b"RIFF" if size >= 4 => {
+                    // The embedded DLS bank is not supported and will be skipped.
+                    let form_type = BinaryReader::read_four_cc(chunk)?;
+                    if form_type == b"sfbk" {
+                        // SoundFont::new expects the RIFF header, so it is restored here.
+                        let mut header: Vec<u8> = b"RIFF".to_vec();
+                        header.extend_from_slice(&(size as u32).to_le_bytes());
+                        header.extend_from_slice(b"sfbk");
+                        let mut sf2 = header[..].chain((&mut *chunk).take((size - 4) as u64));
+                        match SoundFont::new(&mut sf2) {
+                            Ok(value) => sound_font = Some(value),
+                            Err(err) => return Err(MidiFileError::InvalidSoundFont(err)),
+                        }
+                    }
+                }
+                _ => (),
+            }
+
+            if chunk.bytes_read() < size {
+                let result = BinaryReader::discard_data(chunk, size - chunk.bytes_read());
+                match result {
+                    Ok(()) => (),
+                    Err(_) if lenient => break,
+                    Err(err) => return Err(MidiFileError::IoError(err)),
+                }
+            }
+
+            // RIFF chunks are aligned to even offsets.
+            if size % 2 == 1 && reader.bytes_read() < end {
+                match BinaryReader::read_u8(reader) {
+                    Ok(_) => (),
+                    Err(_) if lenient => break,
+                    Err(err) => return Err(MidiFileError::IoError(err)),
+                }
+            }
+        }
+
+        let mut midi_file = match midi_file {
+            Some(value) => value,
+            None => return Err(MidiFileError::ChunkNotFound(FourCC::from_bytes(*b"data"))),
+        };
+
+        if let Some(sound_font) = sound_font {
+            midi_file.sound_font = Some(Arc::new(sound_font));
+            midi_file.bank_offset = bank_offset;
+        }
+
+        Ok(midi_file)
+    }
+
+    // Reads the bank offset from the DBNK chunk in the INFO list of an RMIDI file.
+    fn read_rmid_info<R: Read>(reader: &mut R, size: usize) -> Result<Option<i32>, MidiFileError> {
+        let mut bank_offset: Option<i32> = None;
+
+        let mut position: usize = 0;
+        while position + 8 <= size {
+            let id = BinaryReader::read_four_cc(reader)?;
+            let chunk_size = BinaryReader::read_u32(reader)? as usize;
+            position += 8;
+
+            // The sub-chunk running past the end of the list is cut at the end.
+            let chunk_size = chunk_size.min(size - position);
+            if id == b"DBNK" && chunk_size >= 2 {
+                bank_offset = Some(BinaryReader::read_u16(reader)? as i32);
+                BinaryReader::discard_data(reader, chunk_size - 2)?;
+            } else {
+                BinaryReader::discard_data(reader, chunk_size)?;
+            }
+            position += chunk_size;
+
+            // RIFF chunks are aligned to even offsets.
+            if chunk_size % 2 == 1 && position < size {
+                BinaryReader::read_u8(reader)?;
+                position += 1;
+            }
+        }
+
+        Ok(bank_offset)
+    }
+
+    fn read_standard_midi_file<R: Read>(
+        reader: &mut R,
+        loop_type: MidiFileLoopType,
+        lenient: bool,
+    ) -> Result<Self, MidiFileError> {
         let size = BinaryReader::read_i32_big_endian(reader)?;
-        if size != 6 {
+        if size != 6 && !(lenient && size > 6) {
             return Err(MidiFileError::InvalidChunkData(FourCC::from_bytes(
                 *b"MThd",
             )));
@@ -187,45 +465,202 @@ impl MidiFile {
         }
 
         let track_count = BinaryReader::read_i16_big_endian(reader)? as i32;
-        let resolution = BinaryReader::read_i16_big_endian(reader)? as i32;
+        let division = BinaryReader::read_i16_big_endian(reader)?;
+        BinaryReader::discard_data(reader, size as usize - 6)?;
 
         let mut message_lists: Vec<Vec<Message>> = Vec::new();
         let mut tick_lists: Vec<Vec<i32>> = Vec::new();
+        let mut repairs: Vec<MidiFileRepair> = Vec::new();
+        let mut texts: Vec<MetaText> = Vec::new();
+
+        if lenient {
+            let mut data: Vec<u8> = Vec::new();
+            reader.read_to_end(&mut data)?;
 
-        for _i in 0..track_count {
-            let (message_list, tick_list) = MidiFile::read_track(reader, loop_type)?;
-            message_lists.push(message_list);
-            tick_lists.push(tick_list);
+            let mut position = 0;
+            while position < data.len() {
+                let id = &data[position..(position + 4).min(data.len())];
+                let size = match data.get(position + 4..position + 8) {
+                    Some(value) => u32::from_be_bytes(value.try_into().unwrap()) as usize,
+                    None => usize::MAX,
+                };
+
+                if id == b"MTrk" && size != usize::MAX {
+                    let (message_list, tick_list, end) = MidiFile::read_track_lenient(
+                        &data,
+                        position + 8,
+                        size,
+                        message_lists.len(),
+                        loop_type,
+                        &mut repairs,
+                        &mut texts,
+                    )?;
+                    message_lists.push(message_list);
+                    tick_lists.push(tick_list);
+                    position = end;
+                } else if MidiFile::is_chunk_id(id)
+                    && data
+                        .len()
+                        .checked_sub(position + 8)
+                        .is_some_and(|rest| size <= rest)
+                {
+                    let id = FourCC::from_bytes(id.try_into().unwrap());
+                    repairs.push(MidiFileRepair::UnknownChunkSkipped(id));
+                    position += 8 + size;
+                } else {
+                    // The garbage is skipped until the next track chunk is found.
+                    let skipped = data[position + 1..]
+                        .windows(4)
+                        .position(|window| window == b"MTrk")
+                        .map_or(data.len() - position, |offset| offset + 1);
+                    repairs.push(MidiFileRepair::UnknownDataSkipped(skipped));
+                    position += skipped;
+                }
+            }
+
+            if message_lists.len() != track_count as usize {
+                repairs.push(MidiFileRepair::TrackCountMismatch {
+                    expected: track_count,
+                    actual: message_lists.len() as i32,
+                });
+            }
+        } else {
+            for _i in 0..track_count {
+                let (message_list, tick_list) =
+                    MidiFile::read_track(reader, loop_type, &mut texts)?;
+                message_lists.push(message_list);
+                tick_lists.push(tick_list);
+            }
+        }
+
+        if message_lists.is_empty() {
+            return Err(MidiFileError::ChunkNotFound(FourCC::from_bytes(*b"MTrk")));
         }
 
+        MidiFile::from_tracks(
+            message_lists,
+            tick_lists,
+            division,
+            loop_type,
+            repairs,
+            texts,
+        )
+    }
+
+    pub(crate) fn from_tracks(
+        mut message_lists: Vec<Vec<Message>>,
+        mut tick_lists: Vec<Vec<i32>>,
+        division: i16,
+        loop_type: MidiFileLoopType,
+        repairs: Vec<MidiFileRepair>,
+        texts: Vec<MetaText>,
+    ) -> Result<Self, MidiFileError> {
         match loop_type {
             MidiFileLoopType::LoopPoint(loop_point) if loop_point != 0 => {
-                let loop_point = loop_point as i32;
-                let tick_list = &mut tick_lists[0];
-                let message_list = &mut message_lists[0];
-
-                if loop_point <= *tick_list.last().unwrap() {
-                    for i in 0..tick_list.len() {
-                        if tick_list[i] >= loop_point {
-                            tick_list.insert(i, loop_point);
-                            message_list.insert(i, Message::loop_start());
-                            break;
-                        }
-                    }
-                } else {
-                    tick_list.push(loop_point);
-                    message_list.push(Message::loop_start());
-                }
+                MidiFile::insert_message(
+                    &mut message_lists[0],
+                    &mut tick_lists[0],
+                    Message::loop_start(),
+                    loop_point as i32,
+                );
+            }
+            MidiFileLoopType::LoopRange(loop_start, loop_end) => {
+                MidiFile::insert_message(
+                    &mut message_lists[0],
+                    &mut tick_lists[0],
+                    Message::loop_start(),
+                    loop_start as i32,
+                );
+                MidiFile::insert_message(
+                    &mut message_lists[0],
+                    &mut tick_lists[0],
+                    Message::loop_end(),
+                    loop_end as i32,
+                );
             }
             _ => (),
         }
 
-        let (messages, times) = MidiFile::merge_tracks(&message_lists, &tick_lists, resolution);
+        let (messages, ticks, tracks) = MidiFile::merge_tracks(&message_lists, &tick_lists);
+        let tempo_map = TempoMap::new(division, &messages, &ticks)?;
+
+        // The tempo changes and the time signatures are no longer needed
+        // since they are included in the tempo map.
+        let mut merged_messages: Vec<Message> = Vec::new();
+        let mut merged_times: Vec<f64> = Vec::new();
+        let mut merged_ticks: Vec<i32> = Vec::new();
+        let mut merged_tracks: Vec<usize> = Vec::new();
+        for ((message, tick), track) in messages.into_iter().zip(ticks).zip(tracks) {
+            match message.get_message_type() {
+                Message::TEMPO_CHANGE | Message::TIME_SIGNATURE => (),
+                _ => {
+                    merged_messages.push(message);
+                    merged_times.push(tempo_map.ticks_to_seconds(tick as f64));
+                    merged_ticks.push(tick);
+                    merged_tracks.push(track);
+                }
+            }
+        }
+
+        // The markers are used as the transition points between MIDI files.
+        let mut marker_ticks: Vec<i32> = texts
+            .iter()
+            .filter(|text| text.meta_type == Lyric::MARKER)
+            .map(|text| text.tick)
+            .collect();
+        marker_ticks.sort();
+
+        let (lyrics, lyric_headers) = Lyric::from_texts(texts, &tempo_map);
+
+        Ok(Self {
+            messages: merged_messages,
+            times: merged_times,
+            ticks: merged_ticks,
+            tracks: merged_tracks,
+            track_count: message_lists.len(),
+            tempo_map,
+            sound_font: None,
+            bank_offset: 0,
+            repairs,
+            ump_packets: Vec::new(),
+            lyrics,
+            lyric_headers,
+            marker_ticks,
+        })
+    }
+
+    fn insert_message(
+        message_list: &mut Vec<Message>,
+        tick_list: &mut Vec<i32>,
+        message: Message,
+        tick: i32,
+    ) {
+        // The message is inserted before the other messages at the same tick,
+        // so that they are processed after jumping to the loop start point.
+        let i = tick_list.partition_point(|value| *value < tick);
+        tick_list.insert(i, tick);
+        message_list.insert(i, message);
+    }
+
+    pub(crate) fn sort_messages(message_list: &mut Vec<Message>, tick_list: &mut Vec<i32>) {
+        let mut end: i32 = 0;
+        let mut pairs: Vec<(i32, Message)> = Vec::new();
+        for (message, tick) in message_list.iter().zip(tick_list.iter()) {
+            end = end.max(*tick);
+            if message.get_message_type() != Message::END_OF_TRACK {
+                pairs.push((*tick, *message));
+            }
+        }
+
+        // The sort must be stable to keep the order of the messages at the same tick.
+        pairs.sort_by_key(|(tick, _)| *tick);
+        pairs.push((end, Message::end_of_track()));
 
-        Ok(Self { messages, times })
+        *tick_list = pairs.iter().map(|(tick, _)| *tick).collect();
+        *message_list = pairs.iter().map(|(_, message)| *message).collect();
     }
 
-    fn discard_data<R: Read>(reader: &mut R) -> Result<(), MidiFileError> {
+    pub(crate) fn discard_data<R: Read>(reader: &mut R) -> Result<(), MidiFileError> {
         let size = BinaryReader::read_i32_variable_length(reader)? as usize;
         BinaryReader::discard_data(reader, size)?;
         Ok(())
@@ -244,9 +679,24 @@ impl MidiFile {
         Ok((b1 << 16) | (b2 << 8) | b3)
     }
 
+    fn read_time_signature<R: Read>(reader: &mut R) -> Result<Option<Message>, MidiFileError> {
+        let size = BinaryReader::read_i32_variable_length(reader)? as usize;
+        if size < 2 {
+            BinaryReader::discard_data(reader, size)?;
+            return Ok(None);
+        }
+
+        let numerator = BinaryReader::read_u8(reader)?;
+        let denominator = BinaryReader::read_u8(reader)?;
+        BinaryReader::discard_data(reader, size - 2)?;
+
+        Ok(Some(Message::time_signature(numerator, denominator)))
+    }
+
     fn read_track<R: Read>(
         reader: &mut R,
         loop_type: MidiFileLoopType,
+        texts: &mut Vec<MetaText>,
     ) -> Result<(Vec<Message>, Vec<i32>), MidiFileError> {
         let chunk_type = BinaryReader::read_four_cc(reader)?;
         if chunk_type != b"MTrk" {
@@ -264,84 +714,271 @@ impl MidiFile {
 
         let mut tick: i32 = 0;
         let mut last_status: u8 = 0;
+        let mut port: u8 = 0;
 
         loop {
-            let delta = BinaryReader::read_i32_variable_length(reader)?;
-            let first = BinaryReader::read_u8(reader)?;
+            let end_of_track = MidiFile::read_event(
+                reader,
+                loop_type,
+                &mut tick,
+                &mut last_status,
+                &mut port,
+                &mut messages,
+                &mut ticks,
+                texts,
+            )?;
 
-            tick += delta;
+            if end_of_track {
+                // Some MIDI files may have events inserted after the EOT.
+                // Such events should be ignored.
+                if reader.bytes_read() < size {
+                    BinaryReader::discard_data(reader, size - reader.bytes_read())?;
+                }
 
-            if (first & 128) == 0 {
-                let command = last_status & 0xF0;
-                if command == 0xC0 || command == 0xD0 {
-                    messages.push(Message::common1(last_status, first));
-                    ticks.push(tick);
-                } else {
-                    let data2 = BinaryReader::read_u8(reader)?;
-                    messages.push(Message::common2(last_status, first, data2, loop_type));
-                    ticks.push(tick);
+                return Ok((messages, ticks));
+            }
+        }
+    }
+
+    fn read_track_lenient(
+        data: &[u8],
+        start: usize,
+        size: usize,
+        track: usize,
+        loop_type: MidiFileLoopType,
+        repairs: &mut Vec<MidiFileRepair>,
+        texts: &mut Vec<MetaText>,
+    ) -> Result<(Vec<Message>, Vec<i32>, usize), MidiFileError> {
+        let reader = &mut &data[start..];
+        let declared_end = start.saturating_add(size);
+
+        let mut messages: Vec<Message> = Vec::new();
+        let mut ticks: Vec<i32> = Vec::new();
+
+        let mut tick: i32 = 0;
+        let mut last_status: u8 = 0;
+        let mut port: u8 = 0;
+
+        let end = loop {
+            let position = data.len() - reader.len();
+
+            // The track is regarded as ended if the next track chunk begins without the EOT.
+            if reader.is_empty() || reader.starts_with(b"MTrk") {
+                repairs.push(MidiFileRepair::MissingEndOfTrack(track));
+                break position;
+            }
+
+            let result = MidiFile::read_event(
+                reader,
+                loop_type,
+                &mut tick,
+                &mut last_status,
+                &mut port,
+                &mut messages,
+                &mut ticks,
+                texts,
+            );
+
+            match result {
+                Ok(false) => (),
+                Ok(true) => {
+                    let position = data.len() - reader.len();
+
+                    // The events after the EOT are ignored as long as the chunk length is plausible.
+                    let next = data.get(declared_end..declared_end.saturating_add(4));
+                    let plausible =
+                        declared_end == data.len() || next.is_some_and(MidiFile::is_chunk_id);
+                    if declared_end >= position && plausible {
+                        return Ok((messages, ticks, declared_end));
+                    }
+
+                    break position;
+                }
+                Err(MidiFileError::IoError(_)) => {
+                    // The delta time of the incomplete event should not extend the track.
+                    tick = ticks.last().copied().unwrap_or(0);
+                    repairs.push(MidiFileRepair::TruncatedTrack(track));
+                    break data.len();
                 }
+                Err(err) => return Err(err),
+            }
+        };
+
+        if messages.last().map(|message| message.get_message_type()) != Some(Message::END_OF_TRACK)
+        {
+            messages.push(Message::end_of_track());
+            ticks.push(tick);
+        }
+
+        if end - start != size {
+            repairs.push(MidiFileRepair::TrackLengthMismatch {
+                track,
+                expected: size,
+                actual: end - start,
+            });
+        }
+
+        Ok((messages, ticks, end))
+    }
+
+    fn is_chunk_id(id: &[u8]) -> bool {
+        id.len() == 4
+            && id
+                .iter()
+                .all(|byte| byte.is_ascii_alphanumeric() || *byte == b' ')
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn read_event<R: Read>(
+        reader: &mut R,
+        loop_type: MidiFileLoopType,
+        tick: &mut i32,
+        last_status: &mut u8,
+        port: &mut u8,
+        messages: &mut Vec<Message>,
+        ticks: &mut Vec<i32>,
+        texts: &mut Vec<MetaText>,
+    ) -> Result<bool, MidiFileError> {
+        let delta = BinaryReader::read_i32_variable_length(reader)?;
+        let first = BinaryReader::read_u8(reader)?;
+
+        *tick += delta;
 
-                continue;
+        MidiFile::read_message(
+            reader,
+            first,
+            loop_type,
+            *tick,
+            last_status,
+            port,
+            messages,
+            ticks,
+            texts,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn read_message<R: Read>(
+        reader: &mut R,
+        first: u8,
+        loop_type: MidiFileLoopType,
+        tick: i32,
+        last_status: &mut u8,
+        port: &mut u8,
+        messages: &mut Vec<Message>,
+        ticks: &mut Vec<i32>,
+        texts: &mut Vec<MetaText>,
+    ) -> Result<bool, MidiFileError> {
+        if (first & 128) == 0 {
+            let command = *last_status & 0xF0;
+            if command == 0xC0 || command == 0xD0 {
+                let message = Message::common1(*last_status, first);
+                messages.push(message.with_port(*port));
+                ticks.push(tick);
+            } else {
+                let data2 = BinaryReader::read_u8(reader)?;
+                let message = Message::common2(*last_status, first, data2, loop_type);
+                messages.push(message.with_port(*port));
+                ticks.push(tick);
             }
 
-            match first {
-                0xF0 => MidiFile::discard_data(reader)?,
-                0xF7 => MidiFile::discard_data(reader)?,
-                0xFF => match BinaryReader::read_u8(reader)? {
-                    0x2F => {
-                        BinaryReader::read_u8(reader)?;
-                        messages.push(Message::end_of_track());
-                        ticks.push(tick);
+            return Ok(false);
+        }
 
-                        // Some MIDI files may have events inserted after the EOT.
-                        // Such events should be ignored.
-                        if reader.bytes_read() < size {
-                            BinaryReader::discard_data(reader, size - reader.bytes_read())?;
+        match first {
+            0xF0 => MidiFile::discard_data(reader)?,
+            0xF7 => MidiFile::discard_data(reader)?,
+            0xFF => match BinaryReader::read_u8(reader)? {
+                0x2F => {
+                    BinaryReader::read_u8(reader)?;
+                    messages.push(Message::end_of_track());
+                    ticks.push(tick);
+                    return Ok(true);
+                }
+                0x51 => {
+                    messages.push(Message::tempo_change(MidiFile::read_tempo(reader)?));
+                    ticks.push(tick);
+                }
+                0x21 => {
+                    // The MIDI port applies to the following channel messages in the track.
+                    // The ports beyond the maximum number of channels are ignored.
+                    let size = BinaryReader::read_i32_variable_length(reader)? as usize;
+                    let mut data: Vec<u8> = vec![0; size];
+                    reader.read_exact(&mut data)?;
+                    if let Some(&value) = data.first() {
+                        if (value as usize) < Message::PORT_COUNT {
+                            *port = value;
                         }
-
-                        return Ok((messages, ticks));
                     }
-                    0x51 => {
-                        messages.push(Message::tempo_change(MidiFile::read_tempo(reader)?));
+                }
+                meta_type @ (Lyric::TEXT | Lyric::LYRIC) => {
+                    let size = BinaryReader::read_i32_variable_length(reader)? as usize;
+                    let mut data: Vec<u8> = vec![0; size];
+                    reader.read_exact(&mut data)?;
+                    texts.push(MetaText {
+                        tick,
+                        meta_type,
+                        text: Lyric::decode(&data),
+                    });
+                }
+                Lyric::MARKER => {
+                    let size = BinaryReader::read_i32_variable_length(reader)? as usize;
+                    let text = BinaryReader::read_fixed_length_string(reader, size)?;
+                    let marker_loop = matches!(loop_type, MidiFileLoopType::Marker);
+                    if marker_loop && text.trim().eq_ignore_ascii_case("loopStart") {
+                        messages.push(Message::loop_start());
                         ticks.push(tick);
-                    }
-                    _ => MidiFile::discard_data(reader)?,
-                },
-                _ => {
-                    let command = first & 0xF0;
-                    if command == 0xC0 || command == 0xD0 {
-                        let data1 = BinaryReader::read_u8(reader)?;
-                        messages.push(Message::common1(first, data1));
+                    } else if marker_loop && text.trim().eq_ignore_ascii_case("loopEnd") {
+                        messages.push(Message::loop_end());
                         ticks.push(tick);
                     } else {
-                        let data1 = BinaryReader::read_u8(reader)?;
-                        let data2 = BinaryReader::read_u8(reader)?;
-                        messages.push(Message::common2(first, data1, data2, loop_type));
+                        texts.push(MetaText {
+                            tick,
+                            meta_type: Lyric::MARKER,
+                            text,
+                        });
+                    }
+                }
+                0x58 => {
+                    if let Some(message) = MidiFile::read_time_signature(reader)? {
+                        messages.push(message);
                         ticks.push(tick);
                     }
                 }
+                _ => MidiFile::discard_data(reader)?,
+            },
+            _ => {
+                let command = first & 0xF0;
+                if command == 0xC0 || command == 0xD0 {
+                    let data1 = BinaryReader::read_u8(reader)?;
+                    let message = Message::common1(first, data1);
+                    messages.push(message.with_port(*port));
+                    ticks.push(tick);
+                } else {
+                    let data1 = BinaryReader::read_u8(reader)?;
+                    let data2 = BinaryReader::read_u8(reader)?;
+                    let message = Message::common2(first, data1, data2, loop_type);
+                    messages.push(message.with_port(*port));
+                    ticks.push(tick);
+                }
             }
-
-            last_status = first
         }
+
+        *last_status = first;
+
+        Ok(false)
     }
 
     fn merge_tracks(
         message_lists: &[Vec<Message>],
         tick_lists: &[Vec<i32>],
-        resolution: i32,
-    ) -> (Vec<Message>, Vec<f64>) {
+    ) -> (Vec<Message>, Vec<i32>, Vec<usize>) {
         let mut merged_messages: Vec<Message> = Vec::new();
-        let mut merged_times: Vec<f64> = Vec::new();
+        let mut merged_ticks: Vec<i32> = Vec::new();
+        let mut merged_tracks: Vec<usize> = Vec::new();
 
         let mut indices: Vec<usize> = vec![0; message_lists.len()];
 
-        let mut current_tick: i32 = 0;
-        let mut current_time: f64 = 0.0;
-
-        let mut tempo: f64 = 120.0;
-
         loop {
             let mut min_tick = i32::MAX;
             let mut min_index: i32 = -1;
@@ -360,29 +997,80 @@ impl MidiFile {
                 break;
             }
 
-            let next_tick = tick_lists[min_index as usize][indices[min_index as usize]];
-            let delta_tick = next_tick - current_tick;
-            let delta_time = 60.0 / (resolution as f64 * tempo) * delta_tick as f64;
-
-            current_tick += delta_tick;
-            current_time += delta_time;
-
-            let message = message_lists[min_index as usize][indices[min_index as usize]];
-            if message.get_message_type() == Message::TEMPO_CHANGE {
-                tempo = message.get_tempo();
-            } else {
-                merged_messages.push(message);
-                merged_times.push(current_time);
-            }
+            merged_messages.push(message_lists[min_index as usize][indices[min_index as usize]]);
+            merged_ticks.push(min_tick);
+            merged_tracks.push(min_index as usize);
 
             indices[min_index as usize] += 1;
         }
 
-        (merged_messages, merged_times)
+        (merged_messages, merged_ticks, merged_tracks)
     }
 
     /// Get the length of the MIDI file in seconds.
     pub fn get_length(&self) -> f64 {
         *self.times.last().unwrap()
     }
+
+    /// Gets the number of the tracks in the MIDI file.
+    pub fn get_track_count(&self) -> usize {
+        self.track_count
+    }
+
+    /// Gets the tempo map of the MIDI file.
+    pub fn get_tempo_map(&self) -> &TempoMap {
+        &self.tempo_map
+    }
+
+    /// Gets the SoundFont embedded in the RMID file.
+    ///
+    /// # Remarks
+    ///
+    /// This value will be `None` if the MIDI file does not contain an embedded SoundFont.
+    pub fn get_sound_font(&self) -> Option<&Arc<SoundFont>> {
+        self.sound_font.as_ref()
+    }
+
+    /// Gets the bank offset of the embedded SoundFont.
+    ///
+    /// # Remarks
+    ///
+    /// According to the RMIDI specification, the bank numbers of the embedded SoundFont
+    /// should be shifted by this value when they are selected by the MIDI file.
+    /// If no SoundFont is embedded, this value will be 0.
+    pub fn get_bank_offset(&self) -> i32 {
+        self.bank_offset
+    }
+
+    /// Gets the repairs made while loading the MIDI file.
+    ///
+    /// # Remarks
+    ///
+    /// This will be empty unless the MIDI file was loaded by `new_lenient`
+    /// and some defects were found.
+    pub fn get_repairs(&self) -> &[MidiFileRepair] {
+        &self.repairs[..]
+    }
+
+    /// Gets the lyrics of the MIDI file.
+    ///
+    /// # Remarks
+    ///
+    /// If the MIDI file is in the KAR format, the text events are used as the lyrics.
+    /// Otherwise, the lyric meta events are used.
+    /// The syllables are sorted by time.
+    pub fn get_lyrics(&self) -> &[Lyric] {
+        &self.lyrics[..]
+    }
+
+    /// Gets the headers of the KAR file.
+    ///
+    /// # Remarks
+    ///
+    /// The headers are the text events beginning with `@`, which are returned without the `@`.
+    /// The first character indicates the type of the header,
+    /// such as `T` for the title and `L` for the language.
+    pub fn get_lyric_headers(&self) -> &[String] {
+        &self.lyric_headers[..]
+    }
 }
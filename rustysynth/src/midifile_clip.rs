@@ -0,0 +1,135 @@
+use crate::binary_reader::BinaryReader;
+use crate::four_cc::FourCC;
+use crate::midifile::Message;
+use crate::midifile::MidiFile;
+use crate::MidiFileError;
+use crate::MidiFileLoopType;
+
+impl MidiFile {
+    pub(crate) fn read_clip(
+        data: &[u8],
+        loop_type: MidiFileLoopType,
+    ) -> Result<Self, MidiFileError> {
+        if !data.starts_with(b"SMF2CLIP") {
+            return Err(MidiFileError::InvalidChunkData(FourCC::from_bytes(
+                *b"SMF2",
+            )));
+        }
+
+        let reader = &mut &data[8..];
+
+        let mut messages: Vec<Message> = Vec::new();
+        let mut ticks: Vec<i32> = Vec::new();
+        let mut packets: Vec<[u32; 2]> = Vec::new();
+
+        let mut division: Option<i16> = None;
+        let mut tick: i32 = 0;
+
+        while !reader.is_empty() {
+            let mut words: [u32; 4] = [0; 4];
+            words[0] = BinaryReader::read_i32_big_endian(reader)? as u32;
+            for i in 1..MidiFile::get_ump_word_count(words[0]) {
+                words[i] = BinaryReader::read_i32_big_endian(reader)? as u32;
+            }
+
+            let status = (words[0] >> 20) & 0x0F;
+            match words[0] >> 28 {
+                // Utility messages.
+                0x0 if status == 0x3 => {
+                    let value = (words[0] & 0xFFFF) as i32;
+                    if value == 0 || value > i16::MAX as i32 {
+                        return Err(MidiFileError::InvalidTimeDivision(value as i16));
+                    }
+                    division = Some(value as i16);
+                }
+                0x0 if status == 0x4 => tick += (words[0] & 0xFFFFF) as i32,
+                // MIDI 1.0 channel voice messages.
+                0x2 => {
+                    let status = (words[0] >> 16) as u8;
+                    let data1 = ((words[0] >> 8) & 0x7F) as u8;
+                    let data2 = (words[0] & 0x7F) as u8;
+                    let message = match status & 0xF0 {
+                        0xC0 | 0xD0 => Message::common1(status, data1),
+                        _ => Message::common2(status, data1, data2, loop_type),
+                    };
+                    messages.push(message);
+                    ticks.push(tick);
+                }
+                // MIDI 2.0 channel voice messages.
+                0x4 => {
+                    // The loop controllers are detected by the controller number.
+                    let loop_message = if status == 0xB {
+                        let channel = ((words[0] >> 16) & 0x0F) as u8;
+                        let controller = ((words[0] >> 8) & 0x7F) as u8;
+                        let message = Message::common2(0xB0 | channel, controller, 0, loop_type);
+                        Some(message)
+                            .filter(|message| message.get_message_type() != Message::NORMAL)
+                    } else {
+                        None
+                    };
+
+                    match loop_message {
+                        Some(message) => messages.push(message),
+                        None => {
+                            messages.push(Message::ump(packets.len()));
+                            packets.push([words[0], words[1]]);
+                        }
+                    }
+                    ticks.push(tick);
+                }
+                // Flex data messages.
+                0xD if (words[0] >> 8) & 0xFF == 0 => match words[0] & 0xFF {
+                    0x00 if words[1] > 0 => {
+                        // The tempo is given in units of 10 nanoseconds per quarter note.
+                        messages.push(Message::tempo_change((words[1] / 100) as i32));
+                        ticks.push(tick);
+                    }
+                    0x01 => {
+                        let numerator = (words[1] >> 24) as u8;
+                        let denominator = (words[1] >> 16) as u8;
+                        messages.push(Message::time_signature(numerator, denominator));
+                        ticks.push(tick);
+                    }
+                    _ => (),
+                },
+                // UMP stream messages.
+                0xF => match (words[0] >> 16) & 0x3FF {
+                    // The clip sequence starts from the Start of Clip.
+                    0x20 => tick = 0,
+                    0x21 => break,
+                    _ => (),
+                },
+                _ => (),
+            }
+        }
+
+        messages.push(Message::end_of_track());
+        ticks.push(tick);
+
+        let division = match division {
+            Some(value) => value,
+            None => return Err(MidiFileError::InvalidTimeDivision(0)),
+        };
+
+        let mut midi_file = MidiFile::from_tracks(
+            vec![messages],
+            vec![ticks],
+            division,
+            loop_type,
+            Vec::new(),
+            Vec::new(),
+        )?;
+        midi_file.ump_packets = packets;
+
+        Ok(midi_file)
+    }
+
+    fn get_ump_word_count(word: u32) -> usize {
+        match word >> 28 {
+            0x0 | 0x1 | 0x2 | 0x6 | 0x7 => 1,
+            0x3 | 0x4 | 0x8 | 0x9 | 0xA => 2,
+            0xB | 0xC => 3,
+            _ => 4,
+        }
+    }
+}
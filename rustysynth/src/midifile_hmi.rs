@@ -0,0 +1,252 @@
+use std::io;
+use std::io::ErrorKind;
+use std::io::Read;
+
+use crate::binary_reader::BinaryReader;
+use crate::four_cc::FourCC;
+use crate::midifile::Message;
+use crate::midifile::MidiFile;
+use crate::midifile_lyric::MetaText;
+use crate::MidiFileError;
+use crate::MidiFileLoopType;
+
+impl MidiFile {
+    const HMP_TRACK_COUNT_OFFSET: usize = 0x30;
+    const HMP_DIVISION_OFFSET: usize = 0x38;
+    const HMP_TRACK_OFFSET: usize = 0x308;
+    const HMP_TRACK_OFFSET_NEW: usize = 0x388;
+    const HMP_TRACK_HEADER_SIZE: usize = 12;
+
+    const HMI_DIVISION_OFFSET: usize = 0xD4;
+    const HMI_TRACK_COUNT_OFFSET: usize = 0xE4;
+    const HMI_TRACK_DIRECTORY_OFFSET: usize = 0xE8;
+    const HMI_TRACK_DATA_OFFSET: usize = 0x57;
+
+    pub(crate) fn read_hmp(
+        data: &[u8],
+        loop_type: MidiFileLoopType,
+    ) -> Result<Self, MidiFileError> {
+        if !data.starts_with(b"HMIMIDIP") {
+            return Err(MidiFileError::InvalidChunkData(FourCC::from_bytes(
+                *b"HMIM",
+            )));
+        }
+
+        // The revision dated 1995-01-31 has a larger header.
+        let mut position = if data[8..].starts_with(b"013195") {
+            MidiFile::HMP_TRACK_OFFSET_NEW
+        } else {
+            MidiFile::HMP_TRACK_OFFSET
+        };
+
+        let track_count = MidiFile::read_u32_at(data, MidiFile::HMP_TRACK_COUNT_OFFSET)?;
+        let division = MidiFile::read_u32_at(data, MidiFile::HMP_DIVISION_OFFSET)?;
+
+        let mut tracks: Vec<&[u8]> = Vec::new();
+        for _i in 0..track_count {
+            let size = MidiFile::read_u32_at(data, position + 4)? as usize;
+            if size < MidiFile::HMP_TRACK_HEADER_SIZE {
+                return Err(MidiFileError::InvalidChunkData(FourCC::from_bytes(
+                    *b"HMIM",
+                )));
+            }
+
+            let start = position + MidiFile::HMP_TRACK_HEADER_SIZE;
+            let end = (position + size).min(data.len());
+            tracks.push(&data[start.min(end)..end]);
+            position += size;
+        }
+
+        MidiFile::read_hmi_tracks(&tracks, division, loop_type, false)
+    }
+
+    pub(crate) fn read_hmi(
+        data: &[u8],
+        loop_type: MidiFileLoopType,
+    ) -> Result<Self, MidiFileError> {
+        if !data.starts_with(b"HMI-MIDISONG") {
+            return Err(MidiFileError::InvalidChunkData(FourCC::from_bytes(
+                *b"HMI-",
+            )));
+        }
+
+        let division = MidiFile::read_u32_at(data, MidiFile::HMI_DIVISION_OFFSET)? & 0xFFFF;
+        let track_count = MidiFile::read_u32_at(data, MidiFile::HMI_TRACK_COUNT_OFFSET)? as usize;
+        let directory = MidiFile::read_u32_at(data, MidiFile::HMI_TRACK_DIRECTORY_OFFSET)? as usize;
+
+        let mut offsets: Vec<usize> = Vec::new();
+        for i in 0..track_count {
+            offsets.push(MidiFile::read_u32_at(data, directory + 4 * i)? as usize);
+        }
+
+        // Each track continues up to the next track or the end of the file.
+        let mut ends: Vec<usize> = offsets.clone();
+        ends.push(data.len());
+        ends.sort();
+
+        let mut tracks: Vec<&[u8]> = Vec::new();
+        for offset in offsets {
+            if !data[offset.min(data.len())..].starts_with(b"HMI-MIDITRACK") {
+                return Err(MidiFileError::InvalidChunkData(FourCC::from_bytes(
+                    *b"HMI-",
+                )));
+            }
+
+            let start = offset
+                + MidiFile::read_u32_at(data, offset + MidiFile::HMI_TRACK_DATA_OFFSET)? as usize;
+            let end = ends[ends.partition_point(|value| *value <= offset)];
+            tracks.push(&data[start.min(end)..end]);
+        }
+
+        MidiFile::read_hmi_tracks(&tracks, division, loop_type, true)
+    }
+
+    fn read_hmi_tracks(
+        tracks: &[&[u8]],
+        division: u32,
+        loop_type: MidiFileLoopType,
+        has_note_durations: bool,
+    ) -> Result<Self, MidiFileError> {
+        // The division is the number of ticks per second.
+        // With the tempo of 60 BPM, a quarter note is one second long.
+        if division == 0 || division > i16::MAX as u32 {
+            return Err(MidiFileError::InvalidTimeDivision(division as i16));
+        }
+
+        // CC #110 and #111 are used as the loop points by the HMI driver.
+        let native_loop_type = loop_type.or_native(110, 111);
+
+        let mut message_lists: Vec<Vec<Message>> = Vec::new();
+        let mut tick_lists: Vec<Vec<i32>> = Vec::new();
+        let mut texts: Vec<MetaText> = Vec::new();
+
+        for track in tracks {
+            let reader = &mut &track[..];
+
+            let mut messages: Vec<Message> = Vec::new();
+            let mut ticks: Vec<i32> = Vec::new();
+
+            let mut tick: i32 = 0;
+            let mut last_status: u8 = 0;
+            let mut port: u8 = 0;
+
+            while !reader.is_empty() {
+                tick += if has_note_durations {
+                    BinaryReader::read_i32_variable_length(reader)?
+                } else {
+                    MidiFile::read_hmp_variable_length(reader)?
+                };
+
+                let first = BinaryReader::read_u8(reader)?;
+                if first == 0xFE {
+                    MidiFile::discard_hmi_event(reader)?;
+                    continue;
+                }
+
+                let count = messages.len();
+                let end_of_track = MidiFile::read_message(
+                    reader,
+                    first,
+                    native_loop_type,
+                    tick,
+                    &mut last_status,
+                    &mut port,
+                    &mut messages,
+                    &mut ticks,
+                    &mut texts,
+                )?;
+                if end_of_track {
+                    break;
+                }
+
+                // In the HMI format, a note-on has its duration instead of the corresponding note-off.
+                let message = messages.get(count).copied();
+                if let Some(note_on) = message.filter(|message| {
+                    has_note_durations
+                        && message.command == 0x90
+                        && message.get_message_type() == Message::NORMAL
+                }) {
+                    let duration = BinaryReader::read_i32_variable_length(reader)?;
+                    messages.push(Message::common2(
+                        0x80 | note_on.channel,
+                        note_on.data1,
+                        0,
+                        native_loop_type,
+                    ));
+                    ticks.push(tick + duration);
+                }
+            }
+
+            MidiFile::sort_messages(&mut messages, &mut ticks);
+            message_lists.push(messages);
+            tick_lists.push(ticks);
+        }
+
+        if message_lists.is_empty() {
+            return Err(MidiFileError::InvalidChunkData(FourCC::from_bytes(
+                *b"HMI-",
+            )));
+        }
+
+        message_lists[0].insert(0, Message::tempo_change(1000000));
+        tick_lists[0].insert(0, 0);
+
+        MidiFile::from_tracks(
+            message_lists,
+            tick_lists,
+            division as i16,
+            loop_type,
+            Vec::new(),
+            texts,
+        )
+    }
+
+    fn discard_hmi_event<R: Read>(reader: &mut R) -> Result<(), MidiFileError> {
+        // The driver-specific events have fixed lengths except for 0x10.
+        match BinaryReader::read_u8(reader)? {
+            0x10 => {
+                BinaryReader::discard_data(reader, 2)?;
+                let size = BinaryReader::read_u8(reader)? as usize;
+                BinaryReader::discard_data(reader, size + 4)?;
+            }
+            0x12 | 0x14 => BinaryReader::discard_data(reader, 2)?,
+            0x13 | 0x15 => BinaryReader::discard_data(reader, 6)?,
+            _ => {
+                return Err(MidiFileError::InvalidChunkData(FourCC::from_bytes(
+                    *b"HMI-",
+                )))
+            }
+        }
+
+        Ok(())
+    }
+
+    fn read_hmp_variable_length<R: Read>(reader: &mut R) -> Result<i32, io::Error> {
+        // Unlike the standard MIDI file, the lower 7 bits come first
+        // and the MSB marks the last byte.
+        let mut acc: i32 = 0;
+        let mut shift: i32 = 0;
+
+        loop {
+            let value = BinaryReader::read_u8(reader)? as i32;
+            acc |= (value & 127) << shift;
+            if (value & 128) != 0 {
+                break;
+            }
+            shift += 7;
+            if shift == 28 {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidData,
+                    "the length of the value must be equal to or less than 4",
+                ));
+            }
+        }
+
+        Ok(acc)
+    }
+
+    fn read_u32_at(data: &[u8], position: usize) -> Result<u32, io::Error> {
+        let reader = &mut data.get(position..).unwrap_or(&[]);
+        BinaryReader::read_u32(reader)
+    }
+}
@@ -5,6 +5,9 @@ pub enum MidiFileLoopType {
     /// Specifies the loop start point by a tick value.
     LoopPoint(usize),
 
+    /// Specifies the loop start and end points by tick values.
+    LoopRange(usize, usize),
+
     /// The RPG Maker style loop.
     /// CC #111 will be the loop start point.
     RpgMaker,
@@ -16,4 +19,32 @@ pub enum MidiFileLoopType {
     /// The Final Fantasy style loop.
     /// CC #116 and #117 will be the start and end points of the loop.
     FinalFantasy,
+
+    /// The EMIDI style loop used by the Apogee Sound System.
+    /// CC #116 (track loop) and #118 (global loop) will be the loop start point.
+    /// CC #117 (track loop) and #119 (global loop) will be the loop end point.
+    Emidi,
+
+    /// The marker style loop used by many game engines.
+    /// The marker meta events named `loopStart` and `loopEnd` will be the start and end points of the loop.
+    /// The names are case-insensitive.
+    Marker,
+
+    /// Specifies the controllers for the start and end points of the loop.
+    /// The first value is the CC number for the loop start point,
+    /// and the second value is the CC number for the loop end point.
+    Controller(u8, u8),
+}
+
+impl MidiFileLoopType {
+    pub(crate) fn or_native(self, start: u8, end: u8) -> Self {
+        // The loop controllers defined by the format are used
+        // unless a controller-based loop type is specified explicitly.
+        match self {
+            MidiFileLoopType::LoopPoint(_) | MidiFileLoopType::LoopRange(_, _) => {
+                MidiFileLoopType::Controller(start, end)
+            }
+            _ => self,
+        }
+    }
 }
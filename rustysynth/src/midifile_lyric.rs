@@ -0,0 +1,135 @@
+use crate::tempo_map::TempoMap;
+
+/// Represents a syllable of the lyrics in a MIDI file.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct Lyric {
+    pub(crate) tick: i32,
+    pub(crate) time: f64,
+    pub(crate) text: String,
+    pub(crate) new_line: bool,
+    pub(crate) new_paragraph: bool,
+}
+
+// A text or lyric meta event collected while reading the tracks.
+#[derive(Clone)]
+pub(crate) struct MetaText {
+    pub(crate) tick: i32,
+    pub(crate) meta_type: u8,
+    pub(crate) text: String,
+}
+
+impl Lyric {
+    pub(crate) const TEXT: u8 = 0x01;
+    pub(crate) const LYRIC: u8 = 0x05;
+    pub(crate) const MARKER: u8 = 0x06;
+
+    // Decodes the text of a meta event.
+    // The text is regarded as Latin-1 if it is not valid UTF-8.
+    pub(crate) fn decode(data: &[u8]) -> String {
+        match std::str::from_utf8(data) {
+            Ok(value) => value.to_string(),
+            Err(_) => data.iter().map(|value| *value as char).collect(),
+        }
+    }
+
+    pub(crate) fn from_texts(
+        mut texts: Vec<MetaText>,
+        tempo_map: &TempoMap,
+    ) -> (Vec<Lyric>, Vec<String>) {
+        // The sort must be stable to keep the order of the syllables at the same tick.
+        texts.sort_by_key(|text| text.tick);
+
+        // In the KAR format, the lyrics are stored in the text events instead of the lyric events.
+        let is_kar = texts
+            .iter()
+            .any(|text| text.meta_type == Lyric::TEXT && text.text.starts_with("@K"));
+        let meta_type = if is_kar { Lyric::TEXT } else { Lyric::LYRIC };
+
+        let mut lyrics: Vec<Lyric> = Vec::new();
+        let mut headers: Vec<String> = Vec::new();
+
+        let mut new_line = false;
+        let mut new_paragraph = false;
+
+        for text in texts {
+            if text.meta_type != meta_type {
+                continue;
+            }
+
+            let mut value = text.text.as_str();
+
+            if is_kar {
+                if let Some(header) = value.strip_prefix('@') {
+                    headers.push(header.to_string());
+                    continue;
+                }
+
+                if let Some(rest) = value.strip_prefix('\\') {
+                    new_paragraph = true;
+                    value = rest;
+                } else if let Some(rest) = value.strip_prefix('/') {
+                    new_line = true;
+                    value = rest;
+                }
+            }
+
+            // Many files put the carriage returns and the line feeds before or after the syllables.
+            if value.starts_with(['\r', '\n']) {
+                new_line = true;
+                value = value.trim_start_matches(['\r', '\n']);
+            }
+            let line_ends = value.ends_with(['\r', '\n']);
+            value = value.trim_end_matches(['\r', '\n']);
+
+            if !value.is_empty() {
+                lyrics.push(Lyric {
+                    tick: text.tick,
+                    time: tempo_map.ticks_to_seconds(text.tick as f64),
+                    text: value.to_string(),
+                    new_line: new_line || new_paragraph,
+                    new_paragraph,
+                });
+                new_line = false;
+                new_paragraph = false;
+            }
+
+            new_line |= line_ends;
+        }
+
+        (lyrics, headers)
+    }
+
+    /// Gets the position of the syllable in ticks.
+    pub fn get_tick(&self) -> i32 {
+        self.tick
+    }
+
+    /// Gets the position of the syllable in seconds.
+    pub fn get_time(&self) -> f64 {
+        self.time
+    }
+
+    /// Gets the text of the syllable.
+    ///
+    /// # Remarks
+    ///
+    /// The line and paragraph break marks are not included.
+    pub fn get_text(&self) -> &str {
+        &self.text
+    }
+
+    /// Gets a value that indicates whether the syllable begins a new line.
+    pub fn is_new_line(&self) -> bool {
+        self.new_line
+    }
+
+    /// Gets a value that indicates whether the syllable begins a new paragraph.
+    ///
+    /// # Remarks
+    ///
+    /// A syllable which begins a new paragraph also begins a new line.
+    pub fn is_new_paragraph(&self) -> bool {
+        self.new_paragraph
+    }
+}
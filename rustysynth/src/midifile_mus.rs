@@ -0,0 +1,144 @@
+use crate::binary_reader::BinaryReader;
+use crate::four_cc::FourCC;
+use crate::midifile::Message;
+use crate::midifile::MidiFile;
+use crate::MidiFileError;
+use crate::MidiFileLoopType;
+
+impl MidiFile {
+    // The MUS format ticks at 140 Hz.
+    // With the default tempo of 120 BPM, 70 ticks per quarter note gives the same rate.
+    const MUS_DIVISION: i16 = 70;
+
+    const MUS_PERCUSSION_CHANNEL: usize = 15;
+
+    // Bank select, modulation, volume, pan, expression, reverb, chorus, sustain and soft pedal.
+    const MUS_CONTROLLERS: [u8; 9] = [0, 1, 7, 10, 11, 91, 93, 64, 67];
+
+    pub(crate) fn read_mus(
+        data: &[u8],
+        loop_type: MidiFileLoopType,
+    ) -> Result<Self, MidiFileError> {
+        let reader = &mut &data[4..];
+        let _score_length = BinaryReader::read_u16(reader)?;
+        let score_start = BinaryReader::read_u16(reader)? as usize;
+        if score_start >= data.len() {
+            return Err(MidiFileError::InvalidChunkData(FourCC::from_bytes(
+                *b"MUS\x1A",
+            )));
+        }
+
+        let reader = &mut &data[score_start..];
+
+        let mut messages: Vec<Message> = Vec::new();
+        let mut ticks: Vec<i32> = Vec::new();
+
+        let mut tick: i32 = 0;
+        let mut velocities: [u8; 16] = [127; 16];
+        let mut used: [bool; 16] = [false; 16];
+
+        loop {
+            let descriptor = BinaryReader::read_u8(reader)?;
+            let mus_channel = (descriptor & 0x0F) as usize;
+
+            // The percussion is always on the MUS channel 15,
+            // and the other channels are mapped around the MIDI percussion channel.
+            let channel = match mus_channel {
+                MidiFile::MUS_PERCUSSION_CHANNEL => 9,
+                0..=8 => mus_channel as u8,
+                _ => mus_channel as u8 + 1,
+            };
+
+            // MUS channels start at the full volume, unlike MIDI channels.
+            if !used[mus_channel] {
+                used[mus_channel] = true;
+                messages.push(Message::common2(0xB0 | channel, 7, 127, loop_type));
+                ticks.push(tick);
+            }
+
+            let message = match (descriptor >> 4) & 0x07 {
+                0 => {
+                    let key = BinaryReader::read_u8(reader)? & 0x7F;
+                    Some(Message::common2(0x80 | channel, key, 0, loop_type))
+                }
+                1 => {
+                    let key = BinaryReader::read_u8(reader)?;
+                    if (key & 0x80) != 0 {
+                        velocities[mus_channel] = BinaryReader::read_u8(reader)? & 0x7F;
+                    }
+                    let velocity = velocities[mus_channel];
+                    Some(Message::common2(
+                        0x90 | channel,
+                        key & 0x7F,
+                        velocity,
+                        loop_type,
+                    ))
+                }
+                2 => {
+                    // The 8-bit value is centered at 128.
+                    let value = BinaryReader::read_u8(reader)? as i32 * 64;
+                    let lsb = (value & 0x7F) as u8;
+                    let msb = (value >> 7) as u8;
+                    Some(Message::common2(0xE0 | channel, lsb, msb, loop_type))
+                }
+                3 => {
+                    let controller = match BinaryReader::read_u8(reader)? {
+                        10 => Some(120), // All sounds off
+                        11 => Some(123), // All notes off
+                        12 => Some(126), // Mono
+                        13 => Some(127), // Poly
+                        14 => Some(121), // Reset all controllers
+                        _ => None,
+                    };
+                    controller.map(|value| Message::common2(0xB0 | channel, value, 0, loop_type))
+                }
+                4 => {
+                    let controller = BinaryReader::read_u8(reader)?;
+                    let value = BinaryReader::read_u8(reader)?.min(127);
+                    match controller as usize {
+                        0 => Some(Message::common1(0xC0 | channel, value)),
+                        number @ 1..=9 => {
+                            let controller = MidiFile::MUS_CONTROLLERS[number - 1];
+                            Some(Message::common2(
+                                0xB0 | channel,
+                                controller,
+                                value,
+                                loop_type,
+                            ))
+                        }
+                        _ => None,
+                    }
+                }
+                5 => None, // End of measure
+                6 => {
+                    messages.push(Message::end_of_track());
+                    ticks.push(tick);
+                    break;
+                }
+                _ => {
+                    return Err(MidiFileError::InvalidChunkData(FourCC::from_bytes(
+                        *b"MUS\x1A",
+                    )))
+                }
+            };
+
+            if let Some(message) = message {
+                messages.push(message);
+                ticks.push(tick);
+            }
+
+            if (descriptor & 0x80) != 0 {
+                tick += BinaryReader::read_i32_variable_length(reader)?;
+            }
+        }
+
+        MidiFile::from_tracks(
+            vec![messages],
+            vec![ticks],
+            MidiFile::MUS_DIVISION,
+            loop_type,
+            Vec::new(),
+            Vec::new(),
+        )
+    }
+}
@@ -0,0 +1,333 @@
+use std::borrow::BorrowMut;
+use std::cmp;
+use std::sync::Arc;
+
+use crate::midifile::MidiFile;
+use crate::midifile_sequence::Sequence;
+use crate::synthesizer::Synthesizer;
+
+/// An instance of the player which plays several MIDI files simultaneously on one synthesizer.
+///
+/// # Remarks
+///
+/// Each MIDI file is played as a sequence with its own channel mapping, volume and loop settings.
+/// All the sequences share the clock of the player, so the sequences started at the same time
+/// stay in sync.
+/// The player owns the synthesizer by default.
+/// A borrowed synthesizer such as `&mut Synthesizer` can also be used.
+#[non_exhaustive]
+pub struct MidiFilePlayer<S: BorrowMut<Synthesizer> = Synthesizer> {
+    synthesizer: S,
+
+    block_wrote: usize,
+
+    sequences: Vec<SequenceEntry>,
+    next_id: usize,
+}
+
+struct SequenceEntry {
+    id: usize,
+    sequence: Sequence,
+    volume: f32,
+}
+
+impl<S: BorrowMut<Synthesizer>> MidiFilePlayer<S> {
+    /// Initializes a new instance of the player.
+    ///
+    /// # Arguments
+    ///
+    /// * `synthesizer` - The synthesizer to be handled by the player.
+    pub fn new(synthesizer: S) -> Self {
+        let block_size = synthesizer.borrow().block_size;
+        Self {
+            synthesizer,
+            block_wrote: block_size,
+            sequences: Vec::new(),
+            next_id: 0,
+        }
+    }
+
+    /// Starts playing a MIDI file along with the other sequences.
+    ///
+    /// # Arguments
+    ///
+    /// * `midi_file` - The MIDI file to be played.
+    /// * `channel_map` - The channels of the synthesizer to which the channels of the MIDI file are sent.
+    ///   The channel `i` of the MIDI file is sent to the channel `channel_map[i]`.
+    /// * `play_loop` - If `true`, the MIDI file loops after reaching the end.
+    ///
+    /// # Remarks
+    ///
+    /// The return value is the ID of the sequence, which is used to control the sequence.
+    /// The messages on the channels beyond the length of the channel map are ignored.
+    /// The target channels are reset when the sequence starts and stops,
+    /// so the sequences should not share the target channels.
+    /// The percussion channels are set in the synthesizer,
+    /// so the percussion part should be mapped to one of them, such as the channel 9.
+    /// The playback starts at the beginning of the next synthesis block.
+    pub fn play(
+        &mut self,
+        midi_file: &Arc<MidiFile>,
+        channel_map: &[i32],
+        play_loop: bool,
+    ) -> usize {
+        let synthesizer = self.synthesizer.borrow_mut();
+
+        let channel_count = synthesizer.channels.len();
+        for &channel in channel_map {
+            if channel < 0 || channel >= channel_count as i32 {
+                panic!(
+                    "The channel number must be between 0 and {}.",
+                    channel_count - 1
+                );
+            }
+        }
+
+        let channel_map: Vec<u8> = channel_map.iter().map(|&channel| channel as u8).collect();
+        let mut sequence = Sequence::new(channel_count, Some(channel_map));
+        sequence.play(synthesizer, midi_file, play_loop);
+
+        let id = self.next_id;
+        self.next_id += 1;
+        self.sequences.push(SequenceEntry {
+            id,
+            sequence,
+            volume: 1_f32,
+        });
+        id
+    }
+
+    /// Stops a sequence.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the sequence to be stopped.
+    ///
+    /// # Remarks
+    ///
+    /// The notes of the sequence stop immediately.
+    /// If the sequence has already ended, this method does nothing.
+    pub fn stop(&mut self, id: usize) {
+        if let Some(index) = self.sequences.iter().position(|entry| entry.id == id) {
+            let entry = self.sequences.remove(index);
+            MidiFilePlayer::<S>::remove_sequence(self.synthesizer.borrow_mut(), entry);
+        }
+    }
+
+    /// Stops all the sequences.
+    pub fn stop_all(&mut self) {
+        let synthesizer = self.synthesizer.borrow_mut();
+        for entry in self.sequences.drain(..) {
+            MidiFilePlayer::<S>::remove_sequence(synthesizer, entry);
+        }
+    }
+
+    /// Renders the waveform.
+    ///
+    /// # Arguments
+    ///
+    /// * `left` - The buffer of the left channel to store the rendered waveform.
+    /// * `right` - The buffer of the right channel to store the rendered waveform.
+    ///
+    /// # Remarks
+    ///
+    /// The output buffers for the left and right must be the same length.
+    pub fn render(&mut self, left: &mut [f32], right: &mut [f32]) {
+        if left.len() != right.len() {
+            panic!("The output buffers for the left and right must be the same length.");
+        }
+
+        let synthesizer = self.synthesizer.borrow_mut();
+
+        let left_length = left.len();
+        let mut wrote: usize = 0;
+        while wrote < left_length {
+            if self.block_wrote == synthesizer.block_size {
+                // The ended sequences are removed before the next block,
+                // leaving the release of their notes.
+                // The gain of their channels is restored for the notes played afterwards.
+                self.sequences.retain(|entry| {
+                    let ended = entry.sequence.end_of_sequence();
+                    if ended {
+                        MidiFilePlayer::<S>::reset_output_gain(synthesizer, entry);
+                    }
+                    !ended
+                });
+
+                for entry in &mut self.sequences {
+                    entry.sequence.process_block(synthesizer);
+                }
+                self.block_wrote = 0;
+            }
+
+            let src_rem = synthesizer.block_size - self.block_wrote;
+            let dst_rem = left_length - wrote;
+            let rem = cmp::min(src_rem, dst_rem);
+
+            for entry in &self.sequences {
+                let gain = entry.volume * entry.sequence.get_fade_out_gain(synthesizer);
+                for &channel in entry.sequence.get_target_channels() {
+                    synthesizer.channels[channel as usize].output_gain = gain;
+                }
+            }
+
+            synthesizer.render(
+                &mut left[wrote..wrote + rem],
+                &mut right[wrote..wrote + rem],
+            );
+
+            for entry in &mut self.sequences {
+                entry
+                    .sequence
+                    .process_fade_out(synthesizer, rem, wrote, None);
+            }
+
+            self.block_wrote += rem;
+            wrote += rem;
+        }
+    }
+
+    fn remove_sequence(synthesizer: &mut Synthesizer, mut entry: SequenceEntry) {
+        entry.sequence.stop(synthesizer);
+        MidiFilePlayer::<S>::reset_output_gain(synthesizer, &entry);
+    }
+
+    fn reset_output_gain(synthesizer: &mut Synthesizer, entry: &SequenceEntry) {
+        for &channel in entry.sequence.get_target_channels() {
+            synthesizer.channels[channel as usize].output_gain = 1_f32;
+        }
+    }
+
+    fn get_entry(&self, id: usize) -> Option<&SequenceEntry> {
+        self.sequences.iter().find(|entry| entry.id == id)
+    }
+
+    fn get_entry_mut(&mut self, id: usize) -> Option<&mut SequenceEntry> {
+        self.sequences.iter_mut().find(|entry| entry.id == id)
+    }
+
+    /// Gets the IDs of the sequences being played.
+    pub fn get_sequence_ids(&self) -> Vec<usize> {
+        self.sequences.iter().map(|entry| entry.id).collect()
+    }
+
+    /// Gets the volume of a sequence.
+    ///
+    /// # Remarks
+    ///
+    /// If the sequence is not being played, this value will be `None`.
+    pub fn get_volume(&self, id: usize) -> Option<f32> {
+        self.get_entry(id).map(|entry| entry.volume)
+    }
+
+    /// Sets the volume of a sequence.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the sequence.
+    /// * `value` - The volume of the sequence, where 1 is the original volume.
+    ///
+    /// # Remarks
+    ///
+    /// The volume is applied to the target channels of the sequence,
+    /// and is changed at the beginning of the next synthesis block.
+    /// If the sequence is not being played, this method does nothing.
+    /// The value must be non-negative.
+    pub fn set_volume(&mut self, id: usize, value: f32) {
+        if value < 0_f32 {
+            panic!("The volume must be a non-negative value.");
+        }
+
+        if let Some(entry) = self.get_entry_mut(id) {
+            entry.volume = value;
+        }
+    }
+
+    /// Sets the number of loops to be played before a sequence ends.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the sequence.
+    /// * `value` - The number of times the loop section is played to its end,
+    ///   or `None` to loop forever.
+    ///
+    /// # Remarks
+    ///
+    /// See `MidiFileSequencer::set_loop_count` for the details.
+    /// If the sequence is not being played, this method does nothing.
+    /// The value must be positive.
+    pub fn set_loop_count(&mut self, id: usize, value: Option<usize>) {
+        if value == Some(0) {
+            panic!("The loop count must be a positive value.");
+        }
+
+        if let Some(entry) = self.get_entry_mut(id) {
+            entry.sequence.loop_count = value;
+        }
+    }
+
+    /// Sets the duration of the fade-out after the last loop of a sequence.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the sequence.
+    /// * `value` - The duration of the fade-out in seconds.
+    ///
+    /// # Remarks
+    ///
+    /// The gain of the fade-out is updated at the beginning of each synthesis block.
+    /// If the sequence is not being played, this method does nothing.
+    /// The value must be non-negative.
+    pub fn set_fade_out_duration(&mut self, id: usize, value: f64) {
+        if value < 0.0 {
+            panic!("The fade-out duration must be a non-negative value.");
+        }
+
+        if let Some(entry) = self.get_entry_mut(id) {
+            entry.sequence.fade_out_duration = value;
+        }
+    }
+
+    /// Gets the current playback position of a sequence in seconds.
+    ///
+    /// # Remarks
+    ///
+    /// If the sequence is not being played, this value will be `None`.
+    pub fn get_position(&self, id: usize) -> Option<f64> {
+        self.get_entry(id).map(|entry| entry.sequence.current_time)
+    }
+
+    /// Gets a value that indicates whether a sequence has reached its end.
+    ///
+    /// # Remarks
+    ///
+    /// The ended sequences are removed from the player,
+    /// and this value will be `true` for the IDs of the removed sequences.
+    pub fn end_of_sequence(&self, id: usize) -> bool {
+        match self.get_entry(id) {
+            None => true,
+            Some(entry) => entry.sequence.end_of_sequence(),
+        }
+    }
+
+    /// Gets the synthesizer handled by the player.
+    pub fn get_synthesizer(&self) -> &Synthesizer {
+        self.synthesizer.borrow()
+    }
+
+    /// Gets the synthesizer handled by the player for modification.
+    ///
+    /// # Remarks
+    ///
+    /// The channels not used by the sequences can be used to play live notes.
+    /// Resetting the synthesizer or rendering the waveform directly with it
+    /// disturbs the timing of the playback.
+    pub fn get_synthesizer_mut(&mut self) -> &mut Synthesizer {
+        self.synthesizer.borrow_mut()
+    }
+
+    /// Consumes the player and returns the synthesizer.
+    pub fn into_synthesizer(self) -> S {
+        self.synthesizer
+    }
+}
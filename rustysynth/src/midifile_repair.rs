@@ -0,0 +1,33 @@
+use crate::four_cc::FourCC;
+
+/// Represents a problem in a MIDI file that was repaired by the lenient parser.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum MidiFileRepair {
+    /// The number of the tracks in the header did not match the number of the track chunks.
+    /// The track chunks actually found were used.
+    TrackCountMismatch { expected: i32, actual: i32 },
+
+    /// The length of the track chunk did not match the data.
+    /// The end of the track was derived from the events.
+    TrackLengthMismatch {
+        track: usize,
+        expected: usize,
+        actual: usize,
+    },
+
+    /// The track had no End of Track event.
+    /// The track was closed at the last event.
+    MissingEndOfTrack(usize),
+
+    /// The stream ended in the middle of the track.
+    /// The events before the end of the stream were used.
+    TruncatedTrack(usize),
+
+    /// An unknown chunk was found between the track chunks and was skipped.
+    UnknownChunkSkipped(FourCC),
+
+    /// Bytes that do not form a chunk were found and were skipped.
+    /// The value is the number of the skipped bytes.
+    UnknownDataSkipped(usize),
+}
@@ -0,0 +1,1077 @@
+use std::cmp;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use crate::midifile::Message;
+use crate::midifile::MidiFile;
+use crate::midifile_transition::MidiFileTransition;
+use crate::sequencer_event::SequencerEvent;
+use crate::synthesizer::ScheduledMessage;
+use crate::synthesizer::Synthesizer;
+
+type EventCallback = Box<dyn FnMut(SequencerEvent, usize) + Send>;
+
+// The playback state of a MIDI file.
+// The synthesizer is given to each method, so that several sequences can share a synthesizer.
+// If the channel map is given, the sequence only touches the channels in the map.
+// Otherwise, the sequence owns the whole synthesizer.
+pub(crate) struct Sequence {
+    // Identifies the messages scheduled by the sequence on the synthesizer.
+    id: usize,
+
+    pub(crate) speed: f64,
+
+    pub(crate) midi_file: Option<Arc<MidiFile>>,
+    play_loop: bool,
+    pub(crate) paused: bool,
+    frozen: bool,
+
+    pub(crate) loop_count: Option<usize>,
+    pub(crate) fade_out_duration: f64,
+    played_loop_count: usize,
+    fade_position: Option<i64>,
+
+    pub(crate) current_time: f64,
+    msg_index: usize,
+    loop_index: usize,
+
+    pub(crate) loop_range: Option<(f64, f64)>,
+    pub(crate) keep_loop_tails: bool,
+
+    pub(crate) crossfade_duration: f64,
+    pub(crate) release_tail: bool,
+    queued: Option<(Arc<MidiFile>, bool, MidiFileTransition)>,
+
+    channel_map: Option<Vec<u8>>,
+
+    pub(crate) channel_mutes: Vec<bool>,
+    pub(crate) channel_solos: Vec<bool>,
+    pub(crate) track_mutes: Vec<bool>,
+    pub(crate) track_solos: Vec<bool>,
+    active_notes: Vec<(u8, u8, usize)>,
+    carried_notes: Vec<(u8, u8, usize, f64)>,
+
+    event_callback: Option<EventCallback>,
+    pending_events: Vec<(usize, SequencerEvent)>,
+}
+
+// The IDs of the sequences start from 1, since 0 is for the messages without an owner.
+static NEXT_ID: AtomicUsize = AtomicUsize::new(1);
+
+impl Sequence {
+    // The duration of the gain ramp by which the notes are frozen and unfrozen without clicks.
+    const FREEZE_RAMP_DURATION: f64 = 0.01;
+
+    pub(crate) fn new(channel_count: usize, channel_map: Option<Vec<u8>>) -> Self {
+        Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            speed: 1.0,
+            midi_file: None,
+            play_loop: false,
+            paused: false,
+            frozen: false,
+            loop_count: None,
+            fade_out_duration: 0.0,
+            played_loop_count: 0,
+            fade_position: None,
+            current_time: 0.0,
+            msg_index: 0,
+            loop_index: 0,
+            loop_range: None,
+            keep_loop_tails: false,
+            crossfade_duration: 0.0,
+            release_tail: true,
+            queued: None,
+            channel_map,
+            channel_mutes: vec![false; channel_count],
+            channel_solos: vec![false; channel_count],
+            track_mutes: Vec::new(),
+            track_solos: Vec::new(),
+            active_notes: Vec::new(),
+            carried_notes: Vec::new(),
+            event_callback: None,
+            pending_events: Vec::new(),
+        }
+    }
+
+    pub(crate) fn play(
+        &mut self,
+        synthesizer: &mut Synthesizer,
+        midi_file: &Arc<MidiFile>,
+        play_loop: bool,
+    ) {
+        self.midi_file = Some(Arc::clone(midi_file));
+        self.play_loop = play_loop;
+        self.paused = false;
+        self.frozen = false;
+
+        self.current_time = 0.0;
+        self.msg_index = 0;
+        self.loop_index = 0;
+
+        self.played_loop_count = 0;
+        self.fade_position = None;
+        self.queued = None;
+        self.loop_range = None;
+
+        self.active_notes.clear();
+        self.carried_notes.clear();
+        self.pending_events.clear();
+        self.reset_synthesizer(synthesizer);
+    }
+
+    // The transition is only used by the sequence which owns the whole synthesizer,
+    // since the channels are reset at the transition.
+    pub(crate) fn queue(
+        &mut self,
+        midi_file: &Arc<MidiFile>,
+        play_loop: bool,
+        transition: MidiFileTransition,
+    ) {
+        self.queued = Some((Arc::clone(midi_file), play_loop, transition));
+    }
+
+    pub(crate) fn clear_queue(&mut self) {
+        self.queued = None;
+    }
+
+    pub(crate) fn stop(&mut self, synthesizer: &mut Synthesizer) {
+        self.midi_file = None;
+        self.queued = None;
+        self.paused = false;
+        self.frozen = false;
+        self.fade_position = None;
+        self.active_notes.clear();
+        self.carried_notes.clear();
+        self.pending_events.clear();
+        self.reset_synthesizer(synthesizer);
+    }
+
+    // Processes the events in the next block and advances the time.
+    pub(crate) fn process_block(&mut self, synthesizer: &mut Synthesizer) {
+        if !self.paused {
+            self.process_events(synthesizer);
+            self.current_time += self.get_block_duration(synthesizer);
+        }
+    }
+
+    // Schedules the events in the block at their exact sample offsets.
+    fn process_events(&mut self, synthesizer: &mut Synthesizer) {
+        let midi_file = match self.midi_file.as_ref() {
+            Some(value) => Arc::clone(value),
+            None => return,
+        };
+
+        let mut end_time = self.current_time + self.get_block_duration(synthesizer);
+        let mut previous_offset: usize = 0;
+
+        // The events from the transition point are not played.
+        let transition_time = self.get_transition_time(&midi_file);
+        let transition_time = transition_time.filter(|time| *time < end_time);
+
+        // The loop range overrides the loop points in the MIDI file.
+        let loop_range = self.get_loop_range(&midi_file);
+        let mut loop_count: usize = 0;
+
+        while self.msg_index < midi_file.messages.len() {
+            let time = midi_file.times[self.msg_index];
+            let msg = midi_file.messages[self.msg_index];
+
+            if transition_time.is_some_and(|value| time >= value) {
+                break;
+            }
+
+            if let Some((_, loop_end)) = loop_range {
+                if time >= loop_end && loop_end < end_time {
+                    let offset = cmp::max(self.get_offset(synthesizer, loop_end), previous_offset);
+                    previous_offset = offset;
+                    self.release_carried_notes(synthesizer, loop_end);
+                    if !self.jump_to_loop_start(synthesizer, &midi_file, loop_end, offset) {
+                        break;
+                    }
+                    end_time = self.current_time + self.get_block_duration(synthesizer);
+
+                    // A loop shorter than a block is processed at most twice per block.
+                    loop_count += 1;
+                    if loop_count == 2 {
+                        break;
+                    }
+                    continue;
+                }
+            }
+
+            if time > self.current_time && time >= end_time {
+                break;
+            }
+
+            // The offsets must not go backward due to the rounding errors after a loop.
+            let offset = cmp::max(self.get_offset(synthesizer, time), previous_offset);
+            previous_offset = offset;
+
+            self.release_carried_notes(synthesizer, time);
+
+            if matches!(msg.get_message_type(), Message::NORMAL | Message::UMP) {
+                self.send_message(synthesizer, &midi_file, self.msg_index, offset);
+                self.notify_message(&midi_file, self.msg_index, offset);
+            } else if self.play_loop && loop_range.is_none() {
+                if msg.get_message_type() == Message::LOOP_START {
+                    self.loop_index = self.msg_index;
+                    self.notify(SequencerEvent::LoopStart, offset);
+                } else if msg.get_message_type() == Message::LOOP_END {
+                    if !self.jump_to_loop_start(synthesizer, &midi_file, time, offset) {
+                        break;
+                    }
+                    end_time = self.current_time + self.get_block_duration(synthesizer);
+
+                    // An empty loop is processed only once per block.
+                    if midi_file.times[self.loop_index] >= time {
+                        self.msg_index += 1;
+                        break;
+                    }
+                }
+            }
+            self.msg_index += 1;
+
+            if self.msg_index == midi_file.messages.len() {
+                if self.play_loop {
+                    if !self.jump_to_loop_start(synthesizer, &midi_file, time, offset) {
+                        break;
+                    }
+                    end_time = self.current_time + self.get_block_duration(synthesizer);
+
+                    if midi_file.times[self.loop_index] >= time {
+                        break;
+                    }
+                } else {
+                    self.notify(SequencerEvent::EndOfSequence, offset);
+                }
+            }
+        }
+
+        self.release_carried_notes(synthesizer, end_time);
+
+        if let Some(time) = transition_time {
+            let offset = cmp::max(self.get_offset(synthesizer, time), previous_offset);
+            self.transition(synthesizer, offset);
+
+            // The events of the next MIDI file in the rest of the block.
+            self.process_events(synthesizer);
+        }
+    }
+
+    // Gets the time of the transition to the queued MIDI file, if any.
+    fn get_transition_time(&self, midi_file: &MidiFile) -> Option<f64> {
+        let transition = self.queued.as_ref()?.2;
+
+        // The transition occurs at the end of the MIDI file or the loop at the latest.
+        let mut end_time = midi_file.times.last().copied().unwrap_or(0.0);
+        if self.play_loop {
+            let rest = &midi_file.messages[self.msg_index..];
+            if let Some(index) = rest
+                .iter()
+                .position(|msg| msg.get_message_type() == Message::LOOP_END)
+            {
+                end_time = midi_file.times[self.msg_index + index];
+            }
+        }
+        if let Some((_, loop_end)) = self.get_loop_range(midi_file) {
+            end_time = loop_end;
+        }
+
+        let tempo_map = &midi_file.tempo_map;
+        let ticks = tempo_map.seconds_to_ticks(self.current_time);
+        let time = match transition {
+            MidiFileTransition::NextBeat => {
+                tempo_map.ticks_to_seconds(tempo_map.get_next_beat(ticks))
+            }
+            MidiFileTransition::NextBar => {
+                tempo_map.ticks_to_seconds(tempo_map.get_next_bar(ticks))
+            }
+            MidiFileTransition::NextMarker => {
+                let index = midi_file
+                    .marker_ticks
+                    .partition_point(|tick| (*tick as f64) < ticks - 1.0E-6);
+                match midi_file.marker_ticks.get(index) {
+                    Some(tick) => tempo_map.ticks_to_seconds(*tick as f64),
+                    None => end_time,
+                }
+            }
+            _ => self.current_time,
+        };
+
+        Some(time.min(end_time).max(self.current_time))
+    }
+
+    // Switches to the queued MIDI file at the offset in the block.
+    fn transition(&mut self, synthesizer: &mut Synthesizer, offset: usize) {
+        let (midi_file, play_loop, _) = match self.queued.take() {
+            Some(value) => value,
+            None => return,
+        };
+
+        let sample_rate = synthesizer.sample_rate as f64;
+        let crossfade_length = (self.crossfade_duration * sample_rate).round() as usize;
+
+        // The notes of the previous MIDI file are released or faded out.
+        // Without both, the notes stop immediately.
+        if self.release_tail {
+            synthesizer.schedule_from(self.id, offset, ScheduledMessage::NoteOffAll(false));
+        }
+        if crossfade_length > 0 || !self.release_tail {
+            synthesizer.schedule_from(
+                self.id,
+                offset,
+                ScheduledMessage::FadeOutAll(crossfade_length),
+            );
+        }
+        synthesizer.schedule_from(self.id, offset, ScheduledMessage::ResetChannels);
+        if crossfade_length > 0 {
+            synthesizer.schedule_from(self.id, offset, ScheduledMessage::FadeIn(crossfade_length));
+        }
+
+        self.midi_file = Some(midi_file);
+        self.play_loop = play_loop;
+        self.msg_index = 0;
+        self.loop_index = 0;
+        self.played_loop_count = 0;
+        self.fade_position = None;
+        self.loop_range = None;
+        self.active_notes.clear();
+        self.carried_notes.clear();
+
+        // The time is adjusted so that the start of the MIDI file falls on the offset.
+        self.current_time = -self.speed * offset as f64 / sample_rate;
+        self.notify(SequencerEvent::Transition, offset);
+    }
+
+    // Returns false if the playback ends instead of looping.
+    fn jump_to_loop_start(
+        &mut self,
+        synthesizer: &mut Synthesizer,
+        midi_file: &MidiFile,
+        loop_end: f64,
+        offset: usize,
+    ) -> bool {
+        self.played_loop_count += 1;
+        let last_loop = self.loop_count == Some(self.played_loop_count);
+
+        if last_loop && self.fade_out_duration == 0.0 {
+            self.release_notes(synthesizer, offset);
+            self.msg_index = midi_file.messages.len();
+            self.notify(SequencerEvent::EndOfSequence, offset);
+            return false;
+        }
+
+        // The fade-out starts at the offset in the block to be rendered.
+        if last_loop {
+            self.fade_position = Some(-(offset as i64));
+        }
+
+        let loop_range = self.get_loop_range(midi_file);
+        let (loop_start, loop_index) = match loop_range {
+            Some((start, _)) => (start, midi_file.times.partition_point(|time| *time < start)),
+            None => (midi_file.times[self.loop_index], self.loop_index),
+        };
+
+        if self.keep_loop_tails {
+            self.carry_notes(synthesizer, midi_file, loop_start, loop_end, offset);
+        } else {
+            self.release_notes(synthesizer, offset);
+        }
+
+        // The time is adjusted so that the loop start falls on the offset of the loop end.
+        self.current_time =
+            loop_start - self.speed * offset as f64 / synthesizer.sample_rate as f64;
+        self.msg_index = loop_index;
+
+        if loop_range.is_some() {
+            self.chase_controllers(synthesizer, midi_file, loop_index, offset);
+        }
+
+        self.notify(SequencerEvent::LoopEnd, offset);
+        true
+    }
+
+    // Gets the loop range within the MIDI file.
+    fn get_loop_range(&self, midi_file: &MidiFile) -> Option<(f64, f64)> {
+        let (start, end) = self.loop_range?;
+        let end = end.min(midi_file.get_length());
+        if start < end {
+            Some((start, end))
+        } else {
+            None
+        }
+    }
+
+    // Restores the state of the channels at the loop start,
+    // by replaying the messages before the loop start other than the notes.
+    // The channels are reset at the same offset, which does not affect the sounding notes.
+    fn chase_controllers(
+        &mut self,
+        synthesizer: &mut Synthesizer,
+        midi_file: &MidiFile,
+        loop_index: usize,
+        offset: usize,
+    ) {
+        synthesizer.schedule_from(self.id, offset, ScheduledMessage::ResetChannels);
+
+        for index in 0..loop_index {
+            let msg = midi_file.messages[index];
+            let chased = match msg.get_message_type() {
+                Message::NORMAL => match msg.command {
+                    0x80 | 0x90 => false,
+                    // All Sound Off, Local Control and All Notes Off are not chased,
+                    // since they would stop the notes kept across the loop.
+                    // The mono and poly mode messages are chased to restore the mode.
+                    0xB0 => !matches!(msg.data1, 0x78 | 0x7A | 0x7B | 0x7C | 0x7D),
+                    _ => true,
+                },
+                Message::UMP => Sequence::get_note(midi_file, &msg).is_none(),
+                _ => false,
+            };
+
+            if chased {
+                self.send_message(synthesizer, midi_file, index, offset);
+            }
+        }
+    }
+
+    // Keeps the held notes sounding across the loop for the rest of their lengths.
+    fn carry_notes(
+        &mut self,
+        synthesizer: &mut Synthesizer,
+        midi_file: &MidiFile,
+        loop_start: f64,
+        loop_end: f64,
+        offset: usize,
+    ) {
+        let mut notes: Vec<(u8, u8, usize, f64)> = Vec::new();
+        for (channel, key, track, release_time) in self.carried_notes.drain(..) {
+            notes.push((channel, key, track, release_time - loop_end));
+        }
+        for (channel, key, track) in self.active_notes.drain(..) {
+            // The note is released at the next note event of the same key in the same track.
+            let rest = self.msg_index..midi_file.messages.len();
+            let release_time = rest
+                .filter(|index| {
+                    let note = Sequence::get_note(midi_file, &midi_file.messages[*index]);
+                    let same_track = midi_file.tracks[*index] == track;
+                    same_track && note.is_some_and(|note| note.0 == channel && note.1 == key)
+                })
+                .map(|index| midi_file.times[index])
+                .next()
+                .unwrap_or(midi_file.get_length());
+            notes.push((channel, key, track, release_time - loop_end));
+        }
+
+        for (channel, key, track, length) in notes {
+            if length > 0.0 {
+                self.carried_notes
+                    .push((channel, key, track, loop_start + length));
+            } else {
+                self.send_note_off(synthesizer, channel, key, offset);
+            }
+        }
+    }
+
+    // Releases the notes kept across the loop, which end before the time.
+    fn release_carried_notes(&mut self, synthesizer: &mut Synthesizer, time: f64) {
+        let mut i = 0;
+        while i < self.carried_notes.len() {
+            let (channel, key, _, release_time) = self.carried_notes[i];
+            if release_time < time {
+                let offset = self.get_offset(synthesizer, release_time);
+                self.send_note_off(synthesizer, channel, key, offset);
+                self.carried_notes.remove(i);
+            } else {
+                i += 1;
+            }
+        }
+    }
+
+    fn send_note_off(&self, synthesizer: &mut Synthesizer, channel: u8, key: u8, offset: usize) {
+        if let Some(target) = self.map_channel(channel) {
+            let message = ScheduledMessage::Midi {
+                channel: target as i32,
+                command: 0x80,
+                data1: key as i32,
+                data2: 0,
+            };
+            synthesizer.schedule_from(self.id, offset, message);
+        }
+    }
+
+    // Gets the gain of the fade-out at the current position.
+    pub(crate) fn get_fade_out_gain(&self, synthesizer: &Synthesizer) -> f32 {
+        match self.fade_position {
+            Some(position) if position >= 0 => {
+                let fade_out_length = self.fade_out_duration * synthesizer.sample_rate as f64;
+                (1.0 - position as f64 / fade_out_length).max(0.0) as f32
+            }
+            _ => 1_f32,
+        }
+    }
+
+    // Advances the fade-out by the rendered length, and applies it to the output if given.
+    pub(crate) fn process_fade_out(
+        &mut self,
+        synthesizer: &mut Synthesizer,
+        length: usize,
+        offset: usize,
+        mut output: Option<(&mut [f32], &mut [f32])>,
+    ) {
+        let mut position = match self.fade_position {
+            Some(value) => value,
+            None => return,
+        };
+
+        let fade_out_length = self.fade_out_duration * synthesizer.sample_rate as f64;
+        let fade_out_end = fade_out_length.ceil() as i64;
+        let mut end: Option<usize> = None;
+        for t in 0..length {
+            if position >= 0 {
+                if let Some((left, right)) = output.as_mut() {
+                    let gain = (1.0 - position as f64 / fade_out_length).max(0.0) as f32;
+                    left[t] *= gain;
+                    right[t] *= gain;
+                }
+            }
+
+            if !self.paused {
+                if position == fade_out_end {
+                    end = Some(t);
+                }
+                position += 1;
+            }
+        }
+        self.fade_position = Some(position);
+
+        // The playback ends when the fade-out completes.
+        // The fade-out is cleared, so that the synthesizer can be played again.
+        if let Some(t) = end {
+            self.fade_position = None;
+            if let Some(midi_file) = self.midi_file.as_ref() {
+                self.msg_index = midi_file.messages.len();
+            }
+            self.flush_scheduled_messages(synthesizer);
+            self.stop_notes(synthesizer, true);
+            self.pending_events.clear();
+            if let Some(callback) = self.event_callback.as_mut() {
+                callback(SequencerEvent::EndOfSequence, offset + t);
+            }
+        }
+    }
+
+    fn get_block_duration(&self, synthesizer: &Synthesizer) -> f64 {
+        self.speed * synthesizer.block_size as f64 / synthesizer.sample_rate as f64
+    }
+
+    fn get_offset(&self, synthesizer: &Synthesizer, time: f64) -> usize {
+        if time <= self.current_time {
+            return 0;
+        }
+
+        let offset = (time - self.current_time) * synthesizer.sample_rate as f64 / self.speed;
+        cmp::min(offset.round() as usize, synthesizer.block_size - 1)
+    }
+
+    fn notify_message(&mut self, midi_file: &MidiFile, index: usize, offset: usize) {
+        if self.event_callback.is_none() {
+            return;
+        }
+
+        let msg = midi_file.messages[index];
+        let track = midi_file.tracks[index];
+        let event = if msg.get_message_type() == Message::UMP {
+            SequencerEvent::Ump {
+                track,
+                packet: midi_file.ump_packets[msg.get_ump_index()],
+            }
+        } else {
+            SequencerEvent::Message {
+                track,
+                channel: msg.channel as i32,
+                command: msg.command as i32,
+                data1: msg.data1 as i32,
+                data2: msg.data2 as i32,
+            }
+        };
+        self.notify(event, offset);
+    }
+
+    // The offset is relative to the beginning of the block,
+    // and the event is reported when the block is rendered.
+    fn notify(&mut self, event: SequencerEvent, offset: usize) {
+        if self.event_callback.is_some() {
+            self.pending_events.push((offset, event));
+        }
+    }
+
+    pub(crate) fn notify_pending_events(&mut self, block_wrote: usize, wrote: usize, rem: usize) {
+        let callback = match self.event_callback.as_mut() {
+            Some(value) => value,
+            None => {
+                self.pending_events.clear();
+                return;
+            }
+        };
+
+        let end = block_wrote + rem;
+        let count = self
+            .pending_events
+            .partition_point(|(offset, _)| *offset < end);
+        for (offset, event) in self.pending_events.drain(..count) {
+            callback(event, wrote + offset - block_wrote);
+        }
+    }
+
+    pub(crate) fn set_event_callback(&mut self, callback: EventCallback) {
+        self.event_callback = Some(callback);
+    }
+
+    pub(crate) fn clear_event_callback(&mut self) {
+        self.event_callback = None;
+    }
+
+    pub(crate) fn pause(&mut self, synthesizer: &mut Synthesizer, freeze_notes: bool) {
+        if self.midi_file.is_none() || self.paused {
+            return;
+        }
+
+        self.paused = true;
+
+        // The scheduled messages would take effect during the pause.
+        self.flush_scheduled_messages(synthesizer);
+
+        if freeze_notes {
+            let length = Sequence::get_freeze_ramp_length(synthesizer);
+            self.freeze_channels(synthesizer, true, length);
+        } else {
+            self.stop_notes(synthesizer, false);
+        }
+    }
+
+    pub(crate) fn resume(&mut self, synthesizer: &mut Synthesizer, restart_held_notes: bool) {
+        if !self.paused {
+            return;
+        }
+
+        self.paused = false;
+
+        if self.frozen {
+            let length = Sequence::get_freeze_ramp_length(synthesizer);
+            self.freeze_channels(synthesizer, false, length);
+        } else if restart_held_notes {
+            self.restart_held_notes(synthesizer);
+        }
+    }
+
+    pub(crate) fn seek(
+        &mut self,
+        synthesizer: &mut Synthesizer,
+        position: f64,
+        restart_held_notes: bool,
+    ) {
+        let midi_file = match self.midi_file.as_ref() {
+            Some(value) => Arc::clone(value),
+            None => return,
+        };
+
+        self.reset_synthesizer(synthesizer);
+        self.active_notes.clear();
+        self.carried_notes.clear();
+        self.pending_events.clear();
+        self.current_time = position;
+
+        // The restarted notes should not sound until the playback is resumed.
+        if self.paused {
+            self.freeze_channels(synthesizer, true, 0);
+        }
+        self.loop_index = 0;
+        self.played_loop_count = 0;
+        self.fade_position = None;
+
+        let mut msg_index = 0;
+        while msg_index < midi_file.messages.len() && midi_file.times[msg_index] < position {
+            let msg = midi_file.messages[msg_index];
+            match msg.get_message_type() {
+                Message::LOOP_START if self.play_loop => self.loop_index = msg_index,
+                Message::NORMAL | Message::UMP
+                    if Sequence::get_note(&midi_file, &msg).is_none() =>
+                {
+                    self.send_message(synthesizer, &midi_file, msg_index, 0);
+                }
+                _ => (),
+            }
+            msg_index += 1;
+        }
+        self.msg_index = msg_index;
+
+        if restart_held_notes {
+            self.restart_held_notes(synthesizer);
+        }
+    }
+
+    fn restart_held_notes(&mut self, synthesizer: &mut Synthesizer) {
+        let midi_file = match self.midi_file.as_ref() {
+            Some(value) => Arc::clone(value),
+            None => return,
+        };
+
+        // The held notes are tracked as the channel, the key, the index of the note-on,
+        // and whether the note-off has been sent while the hold pedal is pressed.
+        let mut held_notes: Vec<(u8, u8, usize, bool)> = Vec::new();
+        let mut hold_pedals = [false; 256];
+
+        for msg_index in 0..self.msg_index {
+            let msg = midi_file.messages[msg_index];
+
+            if let Some((channel, key, note_on)) = Sequence::get_note(&midi_file, &msg) {
+                if note_on {
+                    held_notes.retain(|note| note.0 != channel || note.1 != key);
+                    held_notes.push((channel, key, msg_index, false));
+                } else if hold_pedals[channel as usize] {
+                    for note in held_notes.iter_mut() {
+                        if note.0 == channel && note.1 == key {
+                            note.3 = true;
+                        }
+                    }
+                } else {
+                    held_notes.retain(|note| note.0 != channel || note.1 != key);
+                }
+            } else if msg.get_message_type() == Message::NORMAL && msg.command == 0xB0 {
+                let channel = msg.channel;
+                match msg.data1 {
+                    // All Sound Off and All Note Off.
+                    0x78 | 0x7B => held_notes.retain(|note| note.0 != channel),
+                    // Reset All Controllers.
+                    0x79 => {
+                        hold_pedals[channel as usize] = false;
+                        held_notes.retain(|note| note.0 != channel || !note.3);
+                    }
+                    // Hold Pedal.
+                    0x40 => {
+                        hold_pedals[channel as usize] = msg.data2 >= 64;
+                        if msg.data2 < 64 {
+                            held_notes.retain(|note| note.0 != channel || !note.3);
+                        }
+                    }
+                    _ => (),
+                }
+            }
+        }
+
+        for (channel, key, index, released) in held_notes {
+            self.send_message(synthesizer, &midi_file, index, 0);
+
+            // The released note is kept sounding by the hold pedal.
+            if released {
+                if let Some(target) = self.map_channel(channel) {
+                    let message = ScheduledMessage::Midi {
+                        channel: target as i32,
+                        command: 0x80,
+                        data1: key as i32,
+                        data2: 0,
+                    };
+                    synthesizer.schedule_from(self.id, 0, message);
+                }
+                self.active_notes
+                    .retain(|note| note.0 != channel || note.1 != key);
+            }
+        }
+    }
+
+    fn send_message(
+        &mut self,
+        synthesizer: &mut Synthesizer,
+        midi_file: &MidiFile,
+        index: usize,
+        offset: usize,
+    ) {
+        let msg = midi_file.messages[index];
+        let track = midi_file.tracks[index];
+
+        if let Some((channel, key, note_on)) = Sequence::get_note(midi_file, &msg) {
+            // The note kept across the loop ends when the same track plays the same key again.
+            // The notes are told apart by the track, so that the tracks playing the same key
+            // on the same channel do not interfere under the mute and the solo.
+            if let Some(index) = self
+                .carried_notes
+                .iter()
+                .position(|note| note.0 == channel && note.1 == key && note.2 == track)
+            {
+                self.carried_notes.remove(index);
+                if note_on {
+                    self.send_note_off(synthesizer, channel, key, offset);
+                }
+            }
+
+            if note_on {
+                // The note-ons of the muted parts are dropped,
+                // while the other messages are sent to keep the state of the channels.
+                if !self.is_audible(channel, track) {
+                    return;
+                }
+                self.active_notes
+                    .retain(|note| note.0 != channel || note.1 != key || note.2 != track);
+                self.active_notes.push((channel, key, track));
+            } else {
+                self.active_notes
+                    .retain(|note| note.0 != channel || note.1 != key || note.2 != track);
+            }
+        } else if msg.get_message_type() == Message::NORMAL
+            && msg.command == 0xB0
+            && matches!(msg.data1, 0x78 | 0x7B | 0x7E | 0x7F)
+        {
+            self.active_notes.retain(|note| note.0 != msg.channel);
+            self.carried_notes.retain(|note| note.0 != msg.channel);
+        }
+
+        let message = if msg.get_message_type() == Message::UMP {
+            let mut packet = midi_file.ump_packets[msg.get_ump_index()];
+            if self.channel_map.is_some() {
+                let channel = ((packet[0] >> 16) & 0x0F) as u8;
+                let target = match self.map_channel(channel) {
+                    Some(value) => value as u32,
+                    None => return,
+                };
+                // The channels beyond 15 are addressed by the group, in the same way as the ports.
+                let group = target / Synthesizer::CHANNEL_COUNT as u32;
+                let channel = target % Synthesizer::CHANNEL_COUNT as u32;
+                packet[0] = (packet[0] & !0x0F0F_0000) | (group << 24) | (channel << 16);
+            }
+            ScheduledMessage::Ump(packet)
+        } else {
+            let target = match self.map_channel(msg.channel) {
+                Some(value) => value,
+                None => return,
+            };
+            ScheduledMessage::Midi {
+                channel: target as i32,
+                command: msg.command as i32,
+                data1: msg.data1 as i32,
+                data2: msg.data2 as i32,
+            }
+        };
+        synthesizer.schedule_from(self.id, offset, message);
+    }
+
+    fn is_audible(&self, channel: u8, track: usize) -> bool {
+        let channel = channel as usize;
+        let channel_mute = self.channel_mutes.get(channel).copied().unwrap_or(false);
+        let track_mute = self.track_mutes.get(track).copied().unwrap_or(false);
+        if channel_mute || track_mute {
+            return false;
+        }
+
+        // If any part is soloed, only the soloed parts are audible.
+        let has_solo = self.channel_solos.contains(&true) || self.track_solos.contains(&true);
+        if !has_solo {
+            return true;
+        }
+
+        let channel_solo = self.channel_solos.get(channel).copied().unwrap_or(false);
+        let track_solo = self.track_solos.get(track).copied().unwrap_or(false);
+        channel_solo || track_solo
+    }
+
+    pub(crate) fn release_inaudible_notes(&mut self, synthesizer: &mut Synthesizer) {
+        // The scheduled note-ons must not be left after the note-offs.
+        self.flush_scheduled_messages(synthesizer);
+
+        let mut i = 0;
+        while i < self.active_notes.len() {
+            let (channel, key, track) = self.active_notes[i];
+            if self.is_audible(channel, track) {
+                i += 1;
+            } else {
+                self.active_notes.remove(i);
+                self.release_key(synthesizer, channel, key);
+            }
+        }
+
+        let mut i = 0;
+        while i < self.carried_notes.len() {
+            let (channel, key, track, _) = self.carried_notes[i];
+            if self.is_audible(channel, track) {
+                i += 1;
+            } else {
+                self.carried_notes.remove(i);
+                self.release_key(synthesizer, channel, key);
+            }
+        }
+    }
+
+    // Releases the key unless another track still holds it,
+    // since the note-off stops all the notes of the key on the channel.
+    fn release_key(&self, synthesizer: &mut Synthesizer, channel: u8, key: u8) {
+        let active = self.active_notes.iter().map(|note| (note.0, note.1));
+        let carried = self.carried_notes.iter().map(|note| (note.0, note.1));
+        if active.chain(carried).any(|note| note == (channel, key)) {
+            return;
+        }
+
+        if let Some(target) = self.map_channel(channel) {
+            synthesizer.note_off(target as i32, key as i32);
+        }
+    }
+
+    pub(crate) fn check_channel(&self, channel: i32) {
+        if channel < 0 || channel >= self.channel_mutes.len() as i32 {
+            panic!(
+                "The channel number must be between 0 and {}.",
+                self.channel_mutes.len() - 1
+            );
+        }
+    }
+
+    // Returns the channel, the key, and whether the message is a note-on.
+    fn get_note(midi_file: &MidiFile, msg: &Message) -> Option<(u8, u8, bool)> {
+        match msg.get_message_type() {
+            Message::NORMAL => match msg.command {
+                0x80 => Some((msg.channel, msg.data1, false)),
+                0x90 => Some((msg.channel, msg.data1, msg.data2 > 0)),
+                _ => None,
+            },
+            Message::UMP => {
+                let word0 = midi_file.ump_packets[msg.get_ump_index()][0];
+                let channel = ((word0 >> 16) & 0x0F) as u8;
+                let key = ((word0 >> 8) & 0x7F) as u8;
+                match (word0 >> 20) & 0x0F {
+                    0x8 => Some((channel, key, false)),
+                    0x9 => Some((channel, key, true)),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    // Gets the channel of the synthesizer to which the channel of the MIDI file is sent.
+    fn map_channel(&self, channel: u8) -> Option<u8> {
+        match self.channel_map.as_ref() {
+            None => Some(channel),
+            Some(map) => map.get(channel as usize).copied(),
+        }
+    }
+
+    // Gets the channels of the synthesizer used by the sequence.
+    pub(crate) fn get_target_channels(&self) -> &[u8] {
+        match self.channel_map.as_ref() {
+            None => &[],
+            Some(map) => map,
+        }
+    }
+
+    fn reset_synthesizer(&self, synthesizer: &mut Synthesizer) {
+        match self.channel_map.as_ref() {
+            None => synthesizer.reset(),
+            Some(map) => {
+                synthesizer.cancel_channel_messages(map);
+                for &channel in map {
+                    synthesizer.note_off_all_channel(channel as i32, true);
+                    synthesizer.channels[channel as usize].reset();
+                    synthesizer.channels[channel as usize].unfreeze(0);
+                }
+            }
+        }
+    }
+
+    fn get_freeze_ramp_length(synthesizer: &Synthesizer) -> usize {
+        (Sequence::FREEZE_RAMP_DURATION * synthesizer.sample_rate as f64).round() as usize
+    }
+
+    // Freezes or unfreezes the channels to which the MIDI file sends the messages,
+    // so that the live input and the other sequences on the synthesizer are not affected.
+    fn freeze_channels(&mut self, synthesizer: &mut Synthesizer, frozen: bool, length: usize) {
+        let midi_file = match self.midi_file.as_ref() {
+            Some(value) => Arc::clone(value),
+            None => return,
+        };
+
+        let mut channels: Vec<u8> = Vec::new();
+        for msg in &midi_file.messages {
+            let channel = match msg.get_message_type() {
+                Message::NORMAL => msg.channel,
+                Message::UMP => {
+                    let packet = midi_file.ump_packets[msg.get_ump_index()];
+                    ((packet[0] >> 16) & 0x0F) as u8
+                }
+                _ => continue,
+            };
+            if let Some(target) = self.map_channel(channel) {
+                if !channels.contains(&target) {
+                    channels.push(target);
+                }
+            }
+        }
+
+        for channel in channels {
+            if let Some(channel_info) = synthesizer.channels.get_mut(channel as usize) {
+                if frozen {
+                    channel_info.freeze(length);
+                } else {
+                    channel_info.unfreeze(length);
+                }
+            }
+        }
+        self.frozen = frozen;
+    }
+
+    // Only the messages scheduled by this sequence are flushed,
+    // leaving the ones given to the synthesizer directly or by the other sequences.
+    fn flush_scheduled_messages(&self, synthesizer: &mut Synthesizer) {
+        synthesizer.flush_messages_from(self.id);
+    }
+
+    // Releases the notes at the offset in the block.
+    fn release_notes(&mut self, synthesizer: &mut Synthesizer, offset: usize) {
+        match self.channel_map.as_ref() {
+            None => synthesizer.schedule_from(self.id, offset, ScheduledMessage::NoteOffAll(false)),
+            Some(map) => {
+                for &channel in map {
+                    // All Note Off.
+                    let message = ScheduledMessage::Midi {
+                        channel: channel as i32,
+                        command: 0xB0,
+                        data1: 0x7B,
+                        data2: 0,
+                    };
+                    synthesizer.schedule_from(self.id, offset, message);
+                }
+            }
+        }
+        self.active_notes.clear();
+        self.carried_notes.clear();
+    }
+
+    // Stops the notes immediately.
+    fn stop_notes(&mut self, synthesizer: &mut Synthesizer, immediate: bool) {
+        match self.channel_map.as_ref() {
+            None => synthesizer.note_off_all(immediate),
+            Some(map) => {
+                for &channel in map {
+                    synthesizer.note_off_all_channel(channel as i32, immediate);
+                }
+            }
+        }
+        self.active_notes.clear();
+        self.carried_notes.clear();
+    }
+
+    pub(crate) fn get_lyric_index(&self) -> Option<usize> {
+        let midi_file = self.midi_file.as_ref()?;
+        let count = midi_file
+            .lyrics
+            .partition_point(|lyric| lyric.time <= self.current_time);
+        count.checked_sub(1)
+    }
+
+    pub(crate) fn end_of_sequence(&self) -> bool {
+        match &self.midi_file {
+            None => true,
+            Some(value) => self.queued.is_none() && self.msg_index == value.messages.len(),
+        }
+    }
+}
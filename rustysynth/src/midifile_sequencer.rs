@@ -1,45 +1,43 @@
 #![allow(dead_code)]
 
+use std::borrow::BorrowMut;
 use std::cmp;
 use std::sync::Arc;
 
-use crate::midifile::Message;
 use crate::midifile::MidiFile;
+use crate::midifile_sequence::Sequence;
+use crate::midifile_transition::MidiFileTransition;
+use crate::sequencer_event::SequencerEvent;
 use crate::synthesizer::Synthesizer;
 
 /// An instance of the MIDI file sequencer.
+///
+/// # Remarks
+///
+/// The sequencer owns the synthesizer by default.
+/// A borrowed synthesizer such as `&mut Synthesizer` can also be used,
+/// so that the synthesizer can be shared with other sources of input.
 #[non_exhaustive]
-pub struct MidiFileSequencer {
-    synthesizer: Synthesizer,
-
-    speed: f64,
-
-    midi_file: Option<Arc<MidiFile>>,
-    play_loop: bool,
+pub struct MidiFileSequencer<S: BorrowMut<Synthesizer> = Synthesizer> {
+    synthesizer: S,
 
     block_wrote: usize,
 
-    current_time: f64,
-    msg_index: usize,
-    loop_index: usize,
+    sequence: Sequence,
 }
 
-impl MidiFileSequencer {
+impl<S: BorrowMut<Synthesizer>> MidiFileSequencer<S> {
     /// Initializes a new instance of the sequencer.
     ///
     /// # Arguments
     ///
     /// * `synthesizer` - The synthesizer to be handled by the sequencer.
-    pub fn new(synthesizer: Synthesizer) -> Self {
+    pub fn new(synthesizer: S) -> Self {
+        let channel_count = synthesizer.borrow().channels.len();
         Self {
             synthesizer,
-            speed: 1.0,
-            midi_file: None,
-            play_loop: false,
             block_wrote: 0,
-            current_time: 0.0,
-            msg_index: 0,
-            loop_index: 0,
+            sequence: Sequence::new(channel_count, None),
         }
     }
 
@@ -50,22 +48,50 @@ impl MidiFileSequencer {
     /// * `midi_file` - The MIDI file to be played.
     /// * `play_loop` - If `true`, the MIDI file loops after reaching the end.
     pub fn play(&mut self, midi_file: &Arc<MidiFile>, play_loop: bool) {
-        self.midi_file = Some(Arc::clone(midi_file));
-        self.play_loop = play_loop;
-
-        self.block_wrote = self.synthesizer.block_size;
-
-        self.current_time = 0.0;
-        self.msg_index = 0;
-        self.loop_index = 0;
-
-        self.synthesizer.reset()
+        let synthesizer = self.synthesizer.borrow_mut();
+        self.block_wrote = synthesizer.block_size;
+        self.sequence.play(synthesizer, midi_file, play_loop);
     }
 
     /// Stops playing.
     pub fn stop(&mut self) {
-        self.midi_file = None;
-        self.synthesizer.reset();
+        self.sequence.stop(self.synthesizer.borrow_mut());
+    }
+
+    /// Queues a MIDI file to be played after the current one.
+    ///
+    /// # Arguments
+    ///
+    /// * `midi_file` - The MIDI file to be played.
+    /// * `play_loop` - If `true`, the queued MIDI file loops after reaching the end.
+    /// * `transition` - The point where the queued MIDI file starts.
+    ///
+    /// # Remarks
+    ///
+    /// The current MIDI file is played up to the transition point, and the queued MIDI file
+    /// starts exactly at that point.
+    /// If the current MIDI file reaches its end or its loop end before the transition point,
+    /// the transition occurs there instead.
+    /// At the transition, the notes of the current MIDI file are handled as set by
+    /// `set_crossfade_duration` and `set_release_tail`, and the channels are reset.
+    /// If nothing is being played, the queued MIDI file starts immediately as with `play`.
+    /// Queuing another MIDI file before the transition replaces the queued one.
+    pub fn queue(
+        &mut self,
+        midi_file: &Arc<MidiFile>,
+        play_loop: bool,
+        transition: MidiFileTransition,
+    ) {
+        if self.sequence.midi_file.is_none() {
+            self.play(midi_file, play_loop);
+        } else {
+            self.sequence.queue(midi_file, play_loop, transition);
+        }
+    }
+
+    /// Cancels the MIDI file queued by `queue`.
+    pub fn clear_queue(&mut self) {
+        self.sequence.clear_queue();
     }
 
     /// Renders the waveform.
@@ -83,86 +109,289 @@ impl MidiFileSequencer {
             panic!("The output buffers for the left and right must be the same length.");
         }
 
+        let synthesizer = self.synthesizer.borrow_mut();
+
         let left_length = left.len();
         let mut wrote: usize = 0;
         while wrote < left_length {
-            if self.block_wrote == self.synthesizer.block_size {
-                self.process_events();
+            if self.block_wrote == synthesizer.block_size {
+                self.sequence.process_block(synthesizer);
                 self.block_wrote = 0;
-                self.current_time += self.speed * self.synthesizer.block_size as f64
-                    / self.synthesizer.sample_rate as f64;
             }
 
-            let src_rem = self.synthesizer.block_size - self.block_wrote;
+            let src_rem = synthesizer.block_size - self.block_wrote;
             let dst_rem = left_length - wrote;
             let rem = cmp::min(src_rem, dst_rem);
 
-            self.synthesizer.render(
+            self.sequence
+                .notify_pending_events(self.block_wrote, wrote, rem);
+            synthesizer.render(
                 &mut left[wrote..wrote + rem],
                 &mut right[wrote..wrote + rem],
             );
+            self.sequence.process_fade_out(
+                synthesizer,
+                rem,
+                wrote,
+                Some((
+                    &mut left[wrote..wrote + rem],
+                    &mut right[wrote..wrote + rem],
+                )),
+            );
 
             self.block_wrote += rem;
             wrote += rem;
         }
     }
 
-    fn process_events(&mut self) {
-        let midi_file = match self.midi_file.as_ref() {
-            Some(value) => value,
-            None => return,
-        };
+    /// Sets the callback to receive the events of the playback.
+    ///
+    /// # Arguments
+    ///
+    /// * `callback` - The function that takes an event and its sample offset.
+    ///
+    /// # Remarks
+    ///
+    /// The callback is called from the `render` method.
+    /// The sample offset is the position in the buffer passed to `render`
+    /// where the event takes effect.
+    /// Every message sent to the synthesizer during the playback is reported,
+    /// including the note-ons of the muted parts.
+    /// The messages replayed by `seek` are not reported.
+    pub fn set_event_callback<F: FnMut(SequencerEvent, usize) + Send + 'static>(
+        &mut self,
+        callback: F,
+    ) {
+        self.sequence.set_event_callback(Box::new(callback));
+    }
 
-        while self.msg_index < midi_file.messages.len() {
-            let time = midi_file.times[self.msg_index];
-            let msg = midi_file.messages[self.msg_index];
-
-            if time <= self.current_time {
-                if msg.get_message_type() == Message::NORMAL {
-                    self.synthesizer.process_midi_message(
-                        msg.channel as i32,
-                        msg.command as i32,
-                        msg.data1 as i32,
-                        msg.data2 as i32,
-                    );
-                } else if self.play_loop {
-                    if msg.get_message_type() == Message::LOOP_START {
-                        self.loop_index = self.msg_index;
-                    } else if msg.get_message_type() == Message::LOOP_END {
-                        self.current_time = midi_file.times[self.loop_index];
-                        self.msg_index = self.loop_index;
-                        self.synthesizer.note_off_all(false);
-                    }
-                }
-                self.msg_index += 1;
-            } else {
-                break;
-            }
+    /// Removes the callback set by `set_event_callback`.
+    pub fn clear_event_callback(&mut self) {
+        self.sequence.clear_event_callback();
+    }
+
+    /// Pauses the playback.
+    ///
+    /// # Arguments
+    ///
+    /// * `freeze_notes` - If `true`, the sounding notes are frozen and continue on resume.
+    ///   Otherwise, the sounding notes are released.
+    ///
+    /// # Remarks
+    ///
+    /// The playback position is kept, and the `render` method continues to output
+    /// the release of the notes and the tails of the effects.
+    /// The frozen notes fade out within a few milliseconds,
+    /// and only the channels used by the MIDI file are frozen.
+    pub fn pause(&mut self, freeze_notes: bool) {
+        self.sequence
+            .pause(self.synthesizer.borrow_mut(), freeze_notes);
+    }
+
+    /// Resumes the paused playback.
+    ///
+    /// # Arguments
+    ///
+    /// * `restart_held_notes` - If `true`, the notes released by `pause` are restarted.
+    ///
+    /// # Remarks
+    ///
+    /// The notes frozen by `pause` fade in and continue regardless of `restart_held_notes`.
+    pub fn resume(&mut self, restart_held_notes: bool) {
+        self.sequence
+            .resume(self.synthesizer.borrow_mut(), restart_held_notes);
+    }
+
+    /// Moves the playback position.
+    ///
+    /// # Arguments
+    ///
+    /// * `position` - The new playback position in seconds.
+    /// * `restart_held_notes` - If `true`, the notes held at the new position are restarted.
+    ///
+    /// # Remarks
+    ///
+    /// The state of the channels, such as the programs, the controllers and the pitch bend,
+    /// is rebuilt by replaying the events before the new position without rendering the waveform.
+    /// The notes are restarted from their attack, not from the middle of their sound.
+    /// Loop end points before the new position are ignored.
+    /// If the position is beyond the end of the sequence, the sequence will be at the end.
+    /// If the playback is paused, the restarted notes are frozen until the playback is resumed.
+    /// The value must be non-negative.
+    pub fn seek(&mut self, position: f64, restart_held_notes: bool) {
+        if position < 0.0 {
+            panic!("The playback position must be a non-negative value.");
         }
 
-        if self.msg_index == midi_file.messages.len() && self.play_loop {
-            self.current_time = midi_file.times[self.loop_index];
-            self.msg_index = self.loop_index;
-            self.synthesizer.note_off_all(false);
+        if self.sequence.midi_file.is_none() {
+            return;
         }
+
+        let synthesizer = self.synthesizer.borrow_mut();
+        self.block_wrote = synthesizer.block_size;
+        self.sequence
+            .seek(synthesizer, position, restart_held_notes);
     }
 
     /// Gets the synthesizer handled by the sequencer.
     pub fn get_synthesizer(&self) -> &Synthesizer {
-        &self.synthesizer
+        self.synthesizer.borrow()
+    }
+
+    /// Gets the synthesizer handled by the sequencer for modification.
+    ///
+    /// # Remarks
+    ///
+    /// The synthesizer can be used to change the settings such as the master volume,
+    /// or to play live notes along with the MIDI file.
+    /// Resetting the synthesizer or rendering the waveform directly with it
+    /// disturbs the timing of the playback.
+    pub fn get_synthesizer_mut(&mut self) -> &mut Synthesizer {
+        self.synthesizer.borrow_mut()
+    }
+
+    /// Consumes the sequencer and returns the synthesizer.
+    pub fn into_synthesizer(self) -> S {
+        self.synthesizer
     }
 
     /// Gets the currently playing MIDI file.
     pub fn get_midi_file(&self) -> Option<&MidiFile> {
-        match &self.midi_file {
+        match &self.sequence.midi_file {
             None => None,
             Some(value) => Some(value),
         }
     }
 
+    /// Mutes or unmutes a channel.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel` - The channel to be muted or unmuted.
+    /// * `value` - If `true`, the channel is muted.
+    ///
+    /// # Remarks
+    ///
+    /// The sounding notes of the muted channel are released.
+    /// The messages other than the note-ons are still processed,
+    /// so that the state of the channel is kept right while it is muted.
+    pub fn set_channel_mute(&mut self, channel: i32, value: bool) {
+        self.sequence.check_channel(channel);
+        self.sequence.channel_mutes[channel as usize] = value;
+        self.sequence
+            .release_inaudible_notes(self.synthesizer.borrow_mut());
+    }
+
+    /// Gets a value that indicates whether the channel is muted.
+    pub fn get_channel_mute(&self, channel: i32) -> bool {
+        self.sequence.check_channel(channel);
+        self.sequence.channel_mutes[channel as usize]
+    }
+
+    /// Soloes or unsoloes a channel.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel` - The channel to be soloed or unsoloed.
+    /// * `value` - If `true`, the channel is soloed.
+    ///
+    /// # Remarks
+    ///
+    /// If any channel or track is soloed, only the soloed channels and tracks are audible.
+    /// The mute takes priority over the solo.
+    pub fn set_channel_solo(&mut self, channel: i32, value: bool) {
+        self.sequence.check_channel(channel);
+        self.sequence.channel_solos[channel as usize] = value;
+        self.sequence
+            .release_inaudible_notes(self.synthesizer.borrow_mut());
+    }
+
+    /// Gets a value that indicates whether the channel is soloed.
+    pub fn get_channel_solo(&self, channel: i32) -> bool {
+        self.sequence.check_channel(channel);
+        self.sequence.channel_solos[channel as usize]
+    }
+
+    /// Mutes or unmutes a track.
+    ///
+    /// # Arguments
+    ///
+    /// * `track` - The index of the track in the MIDI file to be muted or unmuted.
+    /// * `value` - If `true`, the track is muted.
+    ///
+    /// # Remarks
+    ///
+    /// The sounding notes of the muted track are released.
+    /// The messages other than the note-ons are still processed,
+    /// so that the state of the channels is kept right while the track is muted.
+    /// The setting is kept when another MIDI file is played.
+    pub fn set_track_mute(&mut self, track: usize, value: bool) {
+        let track_mutes = &mut self.sequence.track_mutes;
+        if track >= track_mutes.len() {
+            track_mutes.resize(track + 1, false);
+        }
+        track_mutes[track] = value;
+        self.sequence
+            .release_inaudible_notes(self.synthesizer.borrow_mut());
+    }
+
+    /// Gets a value that indicates whether the track is muted.
+    pub fn get_track_mute(&self, track: usize) -> bool {
+        self.sequence
+            .track_mutes
+            .get(track)
+            .copied()
+            .unwrap_or(false)
+    }
+
+    /// Soloes or unsoloes a track.
+    ///
+    /// # Arguments
+    ///
+    /// * `track` - The index of the track in the MIDI file to be soloed or unsoloed.
+    /// * `value` - If `true`, the track is soloed.
+    ///
+    /// # Remarks
+    ///
+    /// If any channel or track is soloed, only the soloed channels and tracks are audible.
+    /// The mute takes priority over the solo.
+    pub fn set_track_solo(&mut self, track: usize, value: bool) {
+        let track_solos = &mut self.sequence.track_solos;
+        if track >= track_solos.len() {
+            track_solos.resize(track + 1, false);
+        }
+        track_solos[track] = value;
+        self.sequence
+            .release_inaudible_notes(self.synthesizer.borrow_mut());
+    }
+
+    /// Gets a value that indicates whether the track is soloed.
+    pub fn get_track_solo(&self, track: usize) -> bool {
+        self.sequence
+            .track_solos
+            .get(track)
+            .copied()
+            .unwrap_or(false)
+    }
+
+    /// Gets a value that indicates whether the playback is paused.
+    pub fn is_paused(&self) -> bool {
+        self.sequence.paused
+    }
+
     /// Gets the current playback position in seconds.
     pub fn get_position(&self) -> f64 {
-        self.current_time
+        self.sequence.current_time
+    }
+
+    /// Gets the index of the syllable of the lyrics at the current playback position.
+    ///
+    /// # Remarks
+    ///
+    /// The index refers to the list returned by `MidiFile::get_lyrics`.
+    /// If no syllable has been reached yet, this value will be `None`.
+    pub fn get_lyric_index(&self) -> Option<usize> {
+        self.sequence.get_lyric_index()
     }
 
     /// Gets a value that indicates whether the current playback position is at the end of the sequence.
@@ -170,12 +399,224 @@ impl MidiFileSequencer {
     /// # Remarks
     ///
     /// If the `play` method has not yet been called, this value will be `true`.
-    /// This value will never be `true` if loop playback is enabled.
+    /// If a MIDI file is queued by `queue`, this value will be `false` until the queued one ends.
+    /// If loop playback is enabled, this value will be `true` only after the number of loops
+    /// set by `set_loop_count` and the following fade-out have completed.
     pub fn end_of_sequence(&self) -> bool {
-        match &self.midi_file {
-            None => true,
-            Some(value) => self.msg_index == value.messages.len(),
+        self.sequence.end_of_sequence()
+    }
+
+    /// Gets the number of loops to be played before the playback ends.
+    ///
+    /// # Remarks
+    ///
+    /// The default value is `None`, which means the playback loops forever.
+    pub fn get_loop_count(&self) -> Option<usize> {
+        self.sequence.loop_count
+    }
+
+    /// Sets the number of loops to be played before the playback ends.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The number of times the loop section is played to its end,
+    ///   or `None` to loop forever.
+    ///
+    /// # Remarks
+    ///
+    /// This setting is used only if loop playback is enabled or the loop range is set.
+    /// When the loop section has been played the specified number of times,
+    /// the playback jumps back to the loop start once more and fades out
+    /// over the duration set by `set_fade_out_duration`.
+    /// If the duration is zero, the playback ends at the loop end instead.
+    /// The loops are counted from the start of the playback or the last seek.
+    /// The value must be positive.
+    pub fn set_loop_count(&mut self, value: Option<usize>) {
+        if value == Some(0) {
+            panic!("The loop count must be a positive value.");
         }
+
+        self.sequence.loop_count = value;
+    }
+
+    /// Gets the duration of the fade-out in seconds.
+    ///
+    /// # Remarks
+    ///
+    /// The default value is 0.
+    pub fn get_fade_out_duration(&self) -> f64 {
+        self.sequence.fade_out_duration
+    }
+
+    /// Sets the duration of the fade-out after the last loop.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The duration of the fade-out in seconds.
+    ///
+    /// # Remarks
+    ///
+    /// The duration is measured in the rendered waveform, regardless of the playback speed.
+    /// The fade-out does not proceed while the playback is paused.
+    /// The value must be non-negative.
+    pub fn set_fade_out_duration(&mut self, value: f64) {
+        if value < 0.0 {
+            panic!("The fade-out duration must be a non-negative value.");
+        }
+
+        self.sequence.fade_out_duration = value;
+    }
+
+    /// Gets the loop range in seconds.
+    ///
+    /// # Remarks
+    ///
+    /// If no loop range is set, this value will be `None`.
+    pub fn get_loop_range(&self) -> Option<(f64, f64)> {
+        self.sequence.loop_range
+    }
+
+    /// Sets the range to be looped during the playback.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - The start of the loop range in seconds.
+    /// * `end` - The end of the loop range in seconds.
+    ///
+    /// # Remarks
+    ///
+    /// The loop range overrides the loop points in the MIDI file, and is looped
+    /// regardless of whether loop playback is enabled.
+    /// The range can be changed during the playback.
+    /// If the playback position is already beyond the end, the playback jumps to the start
+    /// at the next synthesis block.
+    /// At the loop start, the state of the channels, such as the programs, the controllers and
+    /// the pitch bend, is restored by replaying the events before the start.
+    /// The loop range is cleared when another MIDI file is played.
+    /// The start must be non-negative, and the end must be greater than the start.
+    pub fn set_loop_range(&mut self, start: f64, end: f64) {
+        if start < 0.0 {
+            panic!("The start of the loop range must be a non-negative value.");
+        }
+
+        if end <= start {
+            panic!("The end of the loop range must be greater than the start.");
+        }
+
+        self.sequence.loop_range = Some((start, end));
+    }
+
+    /// Sets the range to be looped during the playback in ticks.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - The start of the loop range in ticks.
+    /// * `end` - The end of the loop range in ticks.
+    ///
+    /// # Remarks
+    ///
+    /// The range is converted to seconds using the tempo map of the current MIDI file.
+    /// See `set_loop_range` for the details.
+    /// If no MIDI file is being played, this method does nothing.
+    pub fn set_loop_range_ticks(&mut self, start: i32, end: i32) {
+        let tempo_map = match self.sequence.midi_file.as_ref() {
+            Some(value) => &value.tempo_map,
+            None => return,
+        };
+
+        let start = tempo_map.ticks_to_seconds(start as f64);
+        let end = tempo_map.ticks_to_seconds(end as f64);
+        self.set_loop_range(start, end);
+    }
+
+    /// Clears the loop range set by `set_loop_range`.
+    ///
+    /// # Remarks
+    ///
+    /// The playback continues from the current position.
+    pub fn clear_loop_range(&mut self) {
+        self.sequence.loop_range = None;
+    }
+
+    /// Gets a value that indicates whether the notes are kept sounding across the loop.
+    ///
+    /// # Remarks
+    ///
+    /// The default value is `false`.
+    pub fn get_keep_loop_tails(&self) -> bool {
+        self.sequence.keep_loop_tails
+    }
+
+    /// Sets whether the notes are kept sounding across the loop.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - If `true`, the notes held at the loop end continue for the rest of
+    ///   their lengths after the jump to the loop start.
+    ///   Otherwise, the notes are released at the loop end.
+    ///
+    /// # Remarks
+    ///
+    /// A note kept across the loop is released when the same key is played again.
+    /// In both cases, the release sounds and the reverb continue across the loop.
+    pub fn set_keep_loop_tails(&mut self, value: bool) {
+        self.sequence.keep_loop_tails = value;
+    }
+
+    /// Gets the duration of the crossfade at the transition to the queued MIDI file in seconds.
+    ///
+    /// # Remarks
+    ///
+    /// The default value is 0.
+    pub fn get_crossfade_duration(&self) -> f64 {
+        self.sequence.crossfade_duration
+    }
+
+    /// Sets the duration of the crossfade at the transition to the queued MIDI file.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The duration of the crossfade in seconds.
+    ///
+    /// # Remarks
+    ///
+    /// During the crossfade, the notes of the previous MIDI file fade out and stop,
+    /// while the notes of the queued MIDI file fade in.
+    /// The duration is measured in the rendered waveform, regardless of the playback speed.
+    /// The value must be non-negative.
+    pub fn set_crossfade_duration(&mut self, value: f64) {
+        if value < 0.0 {
+            panic!("The crossfade duration must be a non-negative value.");
+        }
+
+        self.sequence.crossfade_duration = value;
+    }
+
+    /// Gets a value that indicates whether the notes of the previous MIDI file
+    /// are released at the transition.
+    ///
+    /// # Remarks
+    ///
+    /// The default value is `true`.
+    pub fn get_release_tail(&self) -> bool {
+        self.sequence.release_tail
+    }
+
+    /// Sets whether the notes of the previous MIDI file are released at the transition.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - If `true`, the notes are released and their release sounds continue
+    ///   over the queued MIDI file.
+    ///   Otherwise, the notes keep sounding until the crossfade completes.
+    ///
+    /// # Remarks
+    ///
+    /// The notes held by the hold pedal are also released, since the channels are reset.
+    /// If this value is `false` and the crossfade duration is zero,
+    /// the notes stop immediately at the transition.
+    pub fn set_release_tail(&mut self, value: bool) {
+        self.sequence.release_tail = value;
     }
 
     /// Gets the current playback speed.
@@ -185,7 +626,7 @@ impl MidiFileSequencer {
     /// The default value is 1.
     /// The tempo will be multiplied by this value during playback.
     pub fn get_speed(&self) -> f64 {
-        self.speed
+        self.sequence.speed
     }
 
     /// Sets the playback speed.
@@ -198,6 +639,6 @@ impl MidiFileSequencer {
             panic!("The playback speed must be a non-negative value.");
         }
 
-        self.speed = value;
+        self.sequence.speed = value;
     }
 }
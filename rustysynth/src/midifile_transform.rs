@@ -0,0 +1,340 @@
+use crate::midifile::Message;
+use crate::midifile::MidiFile;
+use crate::synthesizer::Synthesizer;
+use crate::Lyric;
+
+impl MidiFile {
+    /// Creates a new MIDI file with the notes transposed.
+    ///
+    /// # Arguments
+    ///
+    /// * `semitones` - The number of semitones to transpose.
+    ///
+    /// # Remarks
+    ///
+    /// The percussion channel, which is the channel 9 of each port, is not affected.
+    /// Notes that go out of the MIDI key range are removed.
+    pub fn transpose(&self, semitones: i32) -> MidiFile {
+        self.map_messages(|message| {
+            let is_note = matches!(message.command, 0x80 | 0x90 | 0xA0);
+            let channel = message.channel as usize % Synthesizer::CHANNEL_COUNT;
+            if !is_note || channel == Synthesizer::PERCUSSION_CHANNEL {
+                return Some(*message);
+            }
+
+            let key = message.data1 as i32 + semitones;
+            if (0..=127).contains(&key) {
+                Some(Message {
+                    data1: key as u8,
+                    ..*message
+                })
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Creates a new MIDI file with the channels remapped.
+    ///
+    /// # Arguments
+    ///
+    /// * `mapping` - The function that takes a source channel and returns the destination channel.
+    ///
+    /// # Remarks
+    ///
+    /// The channels of the ports are numbered consecutively, so the channel `c` of the port `p`
+    /// is the channel `16 * p + c`.
+    /// If the destination channel is out of range, the messages of the source channel are removed.
+    pub fn remap_channels<F: Fn(i32) -> i32>(&self, mapping: F) -> MidiFile {
+        self.map_messages(|message| {
+            let channel = mapping(message.channel as i32);
+            if (0..Synthesizer::MAXIMUM_CHANNEL_COUNT as i32).contains(&channel) {
+                Some(Message {
+                    channel: channel as u8,
+                    ..*message
+                })
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Creates a new MIDI file with the tempo scaled.
+    ///
+    /// # Arguments
+    ///
+    /// * `factor` - The tempo will be multiplied by this value.
+    ///
+    /// # Remarks
+    ///
+    /// The value must be positive.
+    pub fn scale_tempo(&self, factor: f64) -> MidiFile {
+        if factor <= 0.0 {
+            panic!("The tempo scaling factor must be a positive value.");
+        }
+
+        let mut midi_file = self.clone();
+        midi_file.tempo_map = self.tempo_map.scale(factor);
+        for time in midi_file.times.iter_mut() {
+            *time /= factor;
+        }
+        for lyric in midi_file.lyrics.iter_mut() {
+            lyric.time /= factor;
+        }
+        midi_file
+    }
+
+    /// Creates a new MIDI file with the velocities of the notes scaled.
+    ///
+    /// # Arguments
+    ///
+    /// * `factor` - The velocities will be multiplied by this value.
+    ///
+    /// # Remarks
+    ///
+    /// The resulting velocities are clamped between 1 and 127.
+    pub fn scale_velocity(&self, factor: f64) -> MidiFile {
+        self.map_velocity(|velocity| (factor * velocity as f64).round() as i32)
+    }
+
+    /// Creates a new MIDI file with the velocities of the notes modified by a curve.
+    ///
+    /// # Arguments
+    ///
+    /// * `curve` - The function that takes a source velocity and returns the new velocity.
+    ///
+    /// # Remarks
+    ///
+    /// The resulting velocities are clamped between 1 and 127.
+    /// Note-on messages with velocity 0 are regarded as note-off and are not affected.
+    pub fn map_velocity<F: Fn(i32) -> i32>(&self, curve: F) -> MidiFile {
+        self.map_messages(|message| {
+            if message.command != 0x90 || message.data2 == 0 {
+                return Some(*message);
+            }
+
+            let velocity = curve(message.data2 as i32).clamp(1, 127);
+            Some(Message {
+                data2: velocity as u8,
+                ..*message
+            })
+        })
+    }
+
+    /// Creates a new MIDI file with the program of a channel overridden.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel` - The channel whose program will be overridden.
+    /// * `program` - The program number to be used.
+    ///
+    /// # Remarks
+    ///
+    /// All the program changes of the channel are replaced,
+    /// and a program change is inserted at the beginning of the sequence.
+    /// The channel must be less than `Synthesizer::MAXIMUM_CHANNEL_COUNT`,
+    /// and the program must be between 0 and 127.
+    pub fn override_program(&self, channel: i32, program: i32) -> MidiFile {
+        if !(0..Synthesizer::MAXIMUM_CHANNEL_COUNT as i32).contains(&channel) {
+            panic!("The channel must be between 0 and 239.");
+        }
+
+        if !(0..=127).contains(&program) {
+            panic!("The program must be between 0 and 127.");
+        }
+
+        let mut midi_file = self.map_messages(|message| {
+            if message.command == 0xC0 && message.channel as i32 == channel {
+                Some(Message {
+                    data1: program as u8,
+                    ..*message
+                })
+            } else {
+                Some(*message)
+            }
+        });
+
+        let message = Message {
+            channel: channel as u8,
+            command: 0xC0,
+            data1: program as u8,
+            data2: 0,
+        };
+        midi_file.messages.insert(0, message);
+        midi_file.times.insert(0, 0.0);
+        midi_file.ticks.insert(0, 0);
+        midi_file.tracks.insert(0, 0);
+
+        midi_file
+    }
+
+    /// Creates a new MIDI file containing the specified time range.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - The start of the range in seconds.
+    /// * `end` - The end of the range in seconds.
+    ///
+    /// # Remarks
+    ///
+    /// The controller and program states before the start are moved to the beginning of the sequence.
+    /// Notes that are sounding at the end are stopped.
+    pub fn trim(&self, start: f64, end: f64) -> MidiFile {
+        if end < start {
+            panic!("The end of the range must be greater than or equal to the start.");
+        }
+
+        let start_tick = self.tempo_map.seconds_to_ticks(start).round().max(0.0) as i32;
+        let end_tick = self.tempo_map.seconds_to_ticks(end).round().max(0.0) as i32;
+        let tempo_map = self.tempo_map.slice(start_tick);
+
+        let mut messages: Vec<Message> = Vec::new();
+        let mut ticks: Vec<i32> = Vec::new();
+        let mut tracks: Vec<usize> = Vec::new();
+        let mut sounding_notes: Vec<(u8, u8, usize)> = Vec::new();
+
+        for i in 0..self.messages.len() {
+            let message = &self.messages[i];
+            let tick = &self.ticks[i];
+            let track = self.tracks[i];
+
+            if *tick >= end_tick {
+                break;
+            }
+
+            let is_normal = message.get_message_type() == Message::NORMAL;
+            let is_note = matches!(message.command, 0x80 | 0x90 | 0xA0);
+
+            if *tick < start_tick {
+                if is_normal && !is_note {
+                    messages.push(*message);
+                    ticks.push(0);
+                    tracks.push(track);
+                }
+                continue;
+            }
+
+            if is_normal && (message.command == 0x80 || message.command == 0x90) {
+                let note = (message.channel, message.data1);
+                sounding_notes.retain(|value| (value.0, value.1) != note);
+                if message.command == 0x90 && message.data2 > 0 {
+                    sounding_notes.push((note.0, note.1, track));
+                }
+            }
+
+            messages.push(*message);
+            ticks.push(*tick - start_tick);
+            tracks.push(track);
+        }
+
+        let length = end_tick - start_tick;
+        for (channel, key, track) in sounding_notes {
+            messages.push(Message {
+                channel,
+                command: 0x80,
+                data1: key,
+                data2: 0,
+            });
+            ticks.push(length);
+            tracks.push(track);
+        }
+        messages.push(Message::end_of_track());
+        ticks.push(length);
+        tracks.push(0);
+
+        let times = ticks
+            .iter()
+            .map(|tick| tempo_map.ticks_to_seconds(*tick as f64))
+            .collect();
+
+        let mut midi_file = self.with_messages(messages, times, ticks, tracks);
+        midi_file.lyrics = self
+            .lyrics
+            .iter()
+            .filter(|lyric| lyric.tick >= start_tick && lyric.tick < end_tick)
+            .map(|lyric| Lyric {
+                tick: lyric.tick - start_tick,
+                time: tempo_map.ticks_to_seconds((lyric.tick - start_tick) as f64),
+                ..lyric.clone()
+            })
+            .collect();
+        midi_file.marker_ticks = self
+            .marker_ticks
+            .iter()
+            .filter(|tick| **tick >= start_tick && **tick < end_tick)
+            .map(|tick| tick - start_tick)
+            .collect();
+        midi_file.tempo_map = tempo_map;
+        midi_file
+    }
+
+    /// Creates a new MIDI file containing only the messages that satisfy the predicate.
+    ///
+    /// # Arguments
+    ///
+    /// * `predicate` - The function that takes the channel and the command of a message,
+    ///   and returns `true` if the message should be kept.
+    ///
+    /// # Remarks
+    ///
+    /// The command is the upper 4 bits of the status byte, such as `0x90` for note-on.
+    /// Meta events such as loop points are always kept.
+    pub fn filter_events<F: Fn(i32, i32) -> bool>(&self, predicate: F) -> MidiFile {
+        self.map_messages(|message| {
+            if predicate(message.channel as i32, message.command as i32) {
+                Some(*message)
+            } else {
+                None
+            }
+        })
+    }
+
+    fn map_messages<F: FnMut(&Message) -> Option<Message>>(&self, mut f: F) -> MidiFile {
+        let mut messages: Vec<Message> = Vec::new();
+        let mut times: Vec<f64> = Vec::new();
+        let mut ticks: Vec<i32> = Vec::new();
+        let mut tracks: Vec<usize> = Vec::new();
+
+        for i in 0..self.messages.len() {
+            let message = &self.messages[i];
+            let result = if message.get_message_type() == Message::NORMAL {
+                f(message)
+            } else {
+                Some(*message)
+            };
+
+            if let Some(value) = result {
+                messages.push(value);
+                times.push(self.times[i]);
+                ticks.push(self.ticks[i]);
+                tracks.push(self.tracks[i]);
+            }
+        }
+
+        self.with_messages(messages, times, ticks, tracks)
+    }
+
+    fn with_messages(
+        &self,
+        messages: Vec<Message>,
+        times: Vec<f64>,
+        ticks: Vec<i32>,
+        tracks: Vec<usize>,
+    ) -> MidiFile {
+        MidiFile {
+            messages,
+            times,
+            ticks,
+            tracks,
+            track_count: self.track_count,
+            tempo_map: self.tempo_map.clone(),
+            sound_font: self.sound_font.clone(),
+            bank_offset: self.bank_offset,
+            repairs: self.repairs.clone(),
+            ump_packets: self.ump_packets.clone(),
+            lyrics: self.lyrics.clone(),
+            lyric_headers: self.lyric_headers.clone(),
+            marker_ticks: self.marker_ticks.clone(),
+        }
+    }
+}
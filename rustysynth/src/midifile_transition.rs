@@ -0,0 +1,17 @@
+/// Specifies when the MIDI file queued to the sequencer starts playing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum MidiFileTransition {
+    /// The queued MIDI file starts at the beginning of the next synthesis block.
+    Immediate,
+
+    /// The queued MIDI file starts at the next beat of the current MIDI file.
+    NextBeat,
+
+    /// The queued MIDI file starts at the next bar line of the current MIDI file.
+    /// The bar lines are based on the time signature meta events.
+    NextBar,
+
+    /// The queued MIDI file starts at the next marker meta event of the current MIDI file.
+    NextMarker,
+}
@@ -0,0 +1,124 @@
+use crate::binary_reader::BinaryReader;
+use crate::four_cc::FourCC;
+use crate::midifile::Message;
+use crate::midifile::MidiFile;
+use crate::midifile_lyric::MetaText;
+use crate::MidiFileError;
+use crate::MidiFileLoopType;
+
+impl MidiFile {
+    // The XMIDI format ticks at 120 Hz regardless of the tempo events.
+    // With the default tempo of 120 BPM, 60 ticks per quarter note gives the same rate.
+    const XMI_DIVISION: i16 = 60;
+
+    pub(crate) fn read_xmi(
+        data: &[u8],
+        loop_type: MidiFileLoopType,
+    ) -> Result<Self, MidiFileError> {
+        let events = match MidiFile::find_xmi_events(data) {
+            Some(value) => value,
+            None => return Err(MidiFileError::ChunkNotFound(FourCC::from_bytes(*b"EVNT"))),
+        };
+
+        let reader = &mut &events[..];
+
+        // The FOR and NEXT controllers of the AIL driver are used as the loop points.
+        let native_loop_type = loop_type.or_native(116, 117);
+
+        let mut messages: Vec<Message> = Vec::new();
+        let mut ticks: Vec<i32> = Vec::new();
+        let mut texts: Vec<MetaText> = Vec::new();
+
+        let mut tick: i32 = 0;
+        let mut last_status: u8 = 0;
+        let mut port: u8 = 0;
+
+        while !reader.is_empty() {
+            let first = BinaryReader::read_u8(reader)?;
+
+            // A byte without the MSB is an interval, and consecutive intervals are summed.
+            if (first & 0x80) == 0 {
+                tick += first as i32;
+                continue;
+            }
+
+            let count = messages.len();
+            let end_of_track = MidiFile::read_message(
+                reader,
+                first,
+                native_loop_type,
+                tick,
+                &mut last_status,
+                &mut port,
+                &mut messages,
+                &mut ticks,
+                &mut texts,
+            )?;
+            if end_of_track {
+                break;
+            }
+
+            // A note-on has its duration instead of the corresponding note-off.
+            if (first & 0xF0) == 0x90 && messages.len() > count {
+                let duration = BinaryReader::read_i32_variable_length(reader)?;
+                let note_on = messages[count];
+                messages.push(Message::common2(
+                    0x80 | note_on.channel,
+                    note_on.data1,
+                    0,
+                    native_loop_type,
+                ));
+                ticks.push(tick + duration);
+            }
+        }
+
+        // The tempo events are not used by the AIL driver.
+        let mut i = 0;
+        while i < messages.len() {
+            if messages[i].get_message_type() == Message::TEMPO_CHANGE {
+                messages.remove(i);
+                ticks.remove(i);
+            } else {
+                i += 1;
+            }
+        }
+
+        MidiFile::sort_messages(&mut messages, &mut ticks);
+
+        MidiFile::from_tracks(
+            vec![messages],
+            vec![ticks],
+            MidiFile::XMI_DIVISION,
+            loop_type,
+            Vec::new(),
+            texts,
+        )
+    }
+
+    fn find_xmi_events(data: &[u8]) -> Option<&[u8]> {
+        let mut position = 0;
+        while position + 8 <= data.len() {
+            let id = &data[position..position + 4];
+            let size = u32::from_be_bytes(data[position + 4..position + 8].try_into().unwrap());
+            let start = position + 8;
+            let end = start.saturating_add(size as usize).min(data.len());
+            let chunk = &data[start..end];
+
+            match id {
+                // FORM and CAT have the form type before the nested chunks.
+                b"FORM" | b"CAT " if chunk.len() >= 4 => {
+                    if let Some(events) = MidiFile::find_xmi_events(&chunk[4..]) {
+                        return Some(events);
+                    }
+                }
+                b"EVNT" => return Some(chunk),
+                _ => (),
+            }
+
+            // IFF chunks are aligned to even offsets.
+            position = end + (end - start) % 2;
+        }
+
+        None
+    }
+}
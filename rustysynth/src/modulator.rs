@@ -0,0 +1,228 @@
+use std::io::Read;
+
+use crate::binary_reader::BinaryReader;
+use crate::error::ParseError;
+use crate::generator_type::GeneratorType;
+use crate::pressure_mapping::PressureMapping;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub(crate) struct Modulator {
+    pub(crate) source: u16,
+    pub(crate) destination: u16,
+    pub(crate) amount: i16,
+    pub(crate) amount_source: u16,
+    pub(crate) transform: u16,
+}
+
+impl Modulator {
+    const TERMINATOR: Modulator = Modulator {
+        source: 0,
+        destination: 0,
+        amount: 0,
+        amount_source: 0,
+        transform: 0,
+    };
+
+    fn new<R: Read>(reader: &mut R) -> Result<Self, ParseError> {
+        let source = BinaryReader::read_u16(reader)?;
+        let destination = BinaryReader::read_u16(reader)?;
+        let amount = BinaryReader::read_i16(reader)?;
+        let amount_source = BinaryReader::read_u16(reader)?;
+        let transform = BinaryReader::read_u16(reader)?;
+
+        Ok(Self {
+            source,
+            destination,
+            amount,
+            amount_source,
+            transform,
+        })
+    }
+
+    pub(crate) fn read_from_chunk<R: Read>(
+        reader: &mut R,
+        size: usize,
+    ) -> Result<Vec<Modulator>, ParseError> {
+        // The modulators are optional, so the broken list is ignored instead of failing the load.
+        if !size.is_multiple_of(10) {
+            BinaryReader::discard_data(reader, size)?;
+            return Ok(Vec::new());
+        }
+
+        let count = size / 10;
+
+        let mut modulators: Vec<Modulator> = Vec::new();
+        for _i in 0..count {
+            modulators.push(Modulator::new(reader)?);
+        }
+
+        // The last one is the terminator, but the list without the terminator is also allowed.
+        if modulators.last() == Some(&Modulator::TERMINATOR) {
+            modulators.pop();
+        }
+
+        Ok(modulators)
+    }
+
+    // The modulators in the local zone replace the identical ones in the global zone.
+    pub(crate) fn merge(global: &[Modulator], local: &[Modulator]) -> Vec<Modulator> {
+        let mut modulators: Vec<Modulator> = global
+            .iter()
+            .filter(|modulator| !local.iter().any(|value| value.is_identical(modulator)))
+            .copied()
+            .collect();
+        modulators.extend_from_slice(local);
+        modulators
+    }
+
+    fn is_identical(&self, other: &Modulator) -> bool {
+        self.source == other.source
+            && self.destination == other.destination
+            && self.amount_source == other.amount_source
+            && self.transform == other.transform
+    }
+}
+
+// A modulator driven by the channel pressure or the polyphonic key pressure,
+// which is evaluated for every block.
+#[derive(Clone, Copy)]
+#[non_exhaustive]
+pub(crate) struct PressureModulator {
+    source: u16,
+    amount_source: u16,
+    destination: usize,
+    amount: f32,
+    absolute: bool,
+}
+
+impl PressureModulator {
+    // The number of the pressure modulators kept for each voice,
+    // so that starting a voice does not allocate memory.
+    pub(crate) const MAXIMUM_COUNT: usize = 16;
+
+    // The indices in the general controller palette.
+    const NO_CONTROLLER: u16 = 0;
+    const POLY_PRESSURE: u16 = 10;
+    const CHANNEL_PRESSURE: u16 = 13;
+
+    // Returns `None` if the modulator is not driven by the pressure,
+    // or uses the sources other than the pressure.
+    pub(crate) fn from_modulator(modulator: &Modulator) -> Option<Self> {
+        let destination = modulator.destination as usize;
+        if destination >= GeneratorType::COUNT {
+            return None;
+        }
+
+        let source = PressureModulator::get_source_index(modulator.source)?;
+        PressureModulator::get_source_index(modulator.amount_source)?;
+        if source != PressureModulator::POLY_PRESSURE
+            && source != PressureModulator::CHANNEL_PRESSURE
+        {
+            return None;
+        }
+
+        Some(Self {
+            source: modulator.source,
+            amount_source: modulator.amount_source,
+            destination,
+            amount: modulator.amount as f32,
+            absolute: modulator.transform == 2,
+        })
+    }
+
+    // The default mapping is given as the linear and unipolar modulators
+    // by both types of pressure.
+    pub(crate) fn from_mapping(mapping: &PressureMapping, modulators: &mut Vec<Self>) {
+        let targets = [
+            (GeneratorType::VIBRATO_LFO_TO_PITCH, mapping.vibrato_depth),
+            (
+                GeneratorType::INITIAL_FILTER_CUTOFF_FREQUENCY,
+                mapping.filter_cutoff,
+            ),
+            // The attenuation is reduced to 40% in the same way as the note-on.
+            (GeneratorType::INITIAL_ATTENUATION, -25_f32 * mapping.volume),
+        ];
+
+        for (destination, amount) in targets {
+            if amount == 0_f32 {
+                continue;
+            }
+
+            for source in [
+                PressureModulator::CHANNEL_PRESSURE,
+                PressureModulator::POLY_PRESSURE,
+            ] {
+                modulators.push(Self {
+                    source,
+                    amount_source: PressureModulator::NO_CONTROLLER,
+                    destination: destination as usize,
+                    amount,
+                    absolute: false,
+                });
+            }
+        }
+    }
+
+    pub(crate) fn get_destination(&self) -> usize {
+        self.destination
+    }
+
+    // The pressures are normalized to the range from 0 to 1.
+    pub(crate) fn get_value(&self, channel_pressure: f32, key_pressure: f32) -> f32 {
+        let source = PressureModulator::map(self.source, channel_pressure, key_pressure);
+        let amount_source =
+            PressureModulator::map(self.amount_source, channel_pressure, key_pressure);
+        let value = self.amount * source * amount_source;
+        if self.absolute {
+            value.abs()
+        } else {
+            value
+        }
+    }
+
+    // Only the general controllers are supported.
+    fn get_source_index(source: u16) -> Option<u16> {
+        let index = source & 0x7F;
+        let is_cc = (source & 0x80) != 0;
+        let is_supported = index == PressureModulator::NO_CONTROLLER
+            || index == PressureModulator::POLY_PRESSURE
+            || index == PressureModulator::CHANNEL_PRESSURE;
+        if is_cc || !is_supported || (source >> 10) > 3 {
+            return None;
+        }
+
+        Some(index)
+    }
+
+    fn map(source: u16, channel_pressure: f32, key_pressure: f32) -> f32 {
+        let mut x = match source & 0x7F {
+            PressureModulator::POLY_PRESSURE => key_pressure,
+            PressureModulator::CHANNEL_PRESSURE => channel_pressure,
+            _ => return 1_f32,
+        };
+
+        // Direction.
+        if (source & 0x100) != 0 {
+            x = 1_f32 - x;
+        }
+
+        // Curve type.
+        let mut y = match source >> 10 {
+            1 if x >= 1_f32 => 1_f32,
+            1 => (-40_f32 / 96_f32 * (1_f32 - x).log10()).min(1_f32),
+            2 if x <= 0_f32 => 0_f32,
+            2 => (1_f32 + 40_f32 / 96_f32 * x.log10()).max(0_f32),
+            3 if x >= 0.5_f32 => 1_f32,
+            3 => 0_f32,
+            _ => x,
+        };
+
+        // Polarity.
+        if (source & 0x200) != 0 {
+            y = 2_f32 * y - 1_f32;
+        }
+
+        y
+    }
+}
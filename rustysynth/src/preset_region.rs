@@ -4,6 +4,7 @@ use crate::error::ParseError;
 use crate::generator::Generator;
 use crate::generator_type::GeneratorType;
 use crate::instrument::Instrument;
+use crate::modulator::Modulator;
 use crate::soundfont_math::SoundFontMath;
 use crate::zone::Zone;
 
@@ -22,6 +23,7 @@ fn set_parameter(gs: &mut [i16; GeneratorType::COUNT], generator: &Generator) {
 pub struct PresetRegion {
     pub(crate) gs: [i16; GeneratorType::COUNT],
     pub(crate) instrument: usize,
+    pub(crate) modulators: Vec<Modulator>,
 }
 
 impl PresetRegion {
@@ -54,6 +56,7 @@ impl PresetRegion {
         Ok(Self {
             gs,
             instrument: instrument_id,
+            modulators: Modulator::merge(&global.modulators, &local.modulators),
         })
     }
 
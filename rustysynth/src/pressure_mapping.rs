@@ -0,0 +1,35 @@
+/// Specifies how the channel pressure and the polyphonic key pressure affect the notes.
+///
+/// # Remarks
+///
+/// The mapping is used for the notes whose SoundFont regions have no modulators
+/// driven by the pressure.
+/// Each value is the change at the maximum pressure, and the change is proportional
+/// to the pressure.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct PressureMapping {
+    /// The depth of the vibrato in cents.
+    pub vibrato_depth: f32,
+    /// The change of the filter cutoff frequency in cents.
+    pub filter_cutoff: f32,
+    /// The change of the volume in decibels.
+    pub volume: f32,
+}
+
+impl PressureMapping {
+    // The vibrato depth follows the default modulator of the SoundFont 2.01 specification.
+    const DEFAULT_VIBRATO_DEPTH: f32 = 50_f32;
+    const DEFAULT_FILTER_CUTOFF: f32 = 0_f32;
+    const DEFAULT_VOLUME: f32 = 0_f32;
+}
+
+impl Default for PressureMapping {
+    fn default() -> Self {
+        Self {
+            vibrato_depth: PressureMapping::DEFAULT_VIBRATO_DEPTH,
+            filter_cutoff: PressureMapping::DEFAULT_FILTER_CUTOFF,
+            volume: PressureMapping::DEFAULT_VOLUME,
+        }
+    }
+}
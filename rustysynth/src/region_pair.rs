@@ -9,15 +9,25 @@ use crate::soundfont_math::SoundFontMath;
 pub(crate) struct RegionPair<'a> {
     pub(crate) preset: &'a PresetRegion,
     pub(crate) instrument: &'a InstrumentRegion,
+    pub(crate) offsets: &'a [i16; GeneratorType::COUNT],
 }
 
 impl<'a> RegionPair<'a> {
-    pub(crate) fn new(preset: &'a PresetRegion, instrument: &'a InstrumentRegion) -> Self {
-        Self { preset, instrument }
-    }
-
+    pub(crate) fn new(
+        preset: &'a PresetRegion,
+        instrument: &'a InstrumentRegion,
+        offsets: &'a [i16; GeneratorType::COUNT],
+    ) -> Self {
+        Self {
+            preset,
+            instrument,
+            offsets,
+        }
+    }
+
+    // The offsets are the real-time changes of the generators by the NRPN of the channel.
     fn gs(&self, i: usize) -> i32 {
-        self.preset.gs[i] as i32 + self.instrument.gs[i] as i32
+        self.preset.gs[i] as i32 + self.instrument.gs[i] as i32 + self.offsets[i] as i32
     }
 
     pub(crate) fn get_sample_start(&self) -> i32 {
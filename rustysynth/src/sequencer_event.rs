@@ -0,0 +1,33 @@
+/// Represents an event reported by the MIDI file sequencer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SequencerEvent {
+    /// A MIDI message was processed.
+    /// The track is the index of the track in the MIDI file.
+    Message {
+        track: usize,
+        channel: i32,
+        command: i32,
+        data1: i32,
+        data2: i32,
+    },
+
+    /// A Universal MIDI Packet was processed.
+    /// The track is the index of the track in the MIDI file.
+    Ump { track: usize, packet: [u32; 2] },
+
+    /// The loop start point was reached.
+    LoopStart,
+
+    /// The loop end point was reached, and the playback position jumped to the loop start point.
+    LoopEnd,
+
+    /// The end of the sequence was reached.
+    /// If loop playback is enabled, this is reported only when the playback ends
+    /// after the number of loops set by `MidiFileSequencer::set_loop_count`.
+    EndOfSequence,
+
+    /// The MIDI file queued by `MidiFileSequencer::queue` started playing
+    /// in place of the previous one.
+    Transition,
+}
@@ -2,17 +2,22 @@
 
 use std::cmp;
 use std::collections::HashMap;
+use std::collections::VecDeque;
 use std::sync::Arc;
 
 use crate::array_math::ArrayMath;
 use crate::channel::Channel;
 use crate::chorus::Chorus;
 use crate::error::SynthesizerError;
+use crate::pressure_mapping::PressureMapping;
 use crate::region_pair::RegionPair;
 use crate::reverb::Reverb;
 use crate::soundfont::SoundFont;
 use crate::soundfont_math::SoundFontMath;
+use crate::synthesizer_handle::EventQueue;
+use crate::synthesizer_handle::SynthesizerHandle;
 use crate::synthesizer_settings::SynthesizerSettings;
+use crate::voice::Voice;
 use crate::voice_collection::VoiceCollection;
 
 /// An instance of the SoundFont synthesizer.
@@ -26,28 +31,45 @@ pub struct Synthesizer {
     preset_lookup: HashMap<i32, usize>,
     default_preset: usize,
 
-    channels: Vec<Channel>,
+    pub(crate) channels: Vec<Channel>,
 
-    voices: VoiceCollection,
+    // The numbers of the member channels of the lower and upper MPE zones in each port.
+    pub(crate) mpe_zones: Vec<[usize; 2]>,
+
+    pub(crate) voices: VoiceCollection,
 
     block_left: Vec<f32>,
     block_right: Vec<f32>,
 
-    inverse_block_size: f32,
-
+    block_length: usize,
     block_read: usize,
+    rendered_frames: u64,
+
+    // The frame, the owner and the message.
+    // The owner is the ID of the sequence which scheduled the message,
+    // or zero for the messages given directly or through the handle.
+    scheduled_messages: VecDeque<(u64, usize, ScheduledMessage)>,
+    event_queue: Option<Arc<EventQueue>>,
+
+    fade_in_length: usize,
+    fade_in_end: u64,
 
     master_volume: f32,
+    pressure_mapping: PressureMapping,
 
     effects: Option<Effects>,
 }
 
 impl Synthesizer {
-    /// The number of channels.
+    /// The number of channels in each port, which is also the default number of channels.
     pub const CHANNEL_COUNT: usize = 16;
-    /// The percussion channel.
+    /// The maximum number of channels, which covers 15 ports.
+    pub const MAXIMUM_CHANNEL_COUNT: usize = 240;
+    /// The default percussion channel in each port.
     pub const PERCUSSION_CHANNEL: usize = 9;
 
+    const SOFT_PEDAL_VELOCITY: f32 = 0.8_f32;
+
     /// Initializes a new synthesizer using a specified SoundFont and settings.
     ///
     /// # Arguments
@@ -83,8 +105,10 @@ impl Synthesizer {
         }
 
         let mut channels: Vec<Channel> = Vec::new();
-        for i in 0..Synthesizer::CHANNEL_COUNT {
-            channels.push(Channel::new(i == Synthesizer::PERCUSSION_CHANNEL));
+        for i in 0..settings.channel_count {
+            let is_percussion_channel =
+                i % Synthesizer::CHANNEL_COUNT == Synthesizer::PERCUSSION_CHANNEL;
+            channels.push(Channel::new(is_percussion_channel));
         }
 
         let voices = VoiceCollection::new(settings);
@@ -92,10 +116,6 @@ impl Synthesizer {
         let block_left: Vec<f32> = vec![0_f32; settings.block_size];
         let block_right: Vec<f32> = vec![0_f32; settings.block_size];
 
-        let inverse_block_size = 1_f32 / settings.block_size as f32;
-
-        let block_read = settings.block_size;
-
         let master_volume = 0.5_f32;
 
         let effects = if settings.enable_reverb_and_chorus {
@@ -112,12 +132,19 @@ impl Synthesizer {
             preset_lookup,
             default_preset,
             channels,
+            mpe_zones: vec![[0; 2]; settings.channel_count.div_ceil(Synthesizer::CHANNEL_COUNT)],
             voices,
             block_left,
             block_right,
-            inverse_block_size,
-            block_read,
+            block_length: 0,
+            block_read: 0,
+            rendered_frames: 0,
+            scheduled_messages: VecDeque::new(),
+            event_queue: None,
+            fade_in_length: 0,
+            fade_in_end: 0,
             master_volume,
+            pressure_mapping: PressureMapping::default(),
             effects,
         })
     }
@@ -130,6 +157,26 @@ impl Synthesizer {
     /// * `command` - The type of the message.
     /// * `data1` - The first data part of the message.
     /// * `data2` - The second data part of the message.
+    ///
+    /// # Remarks
+    ///
+    /// The NRPN with the MSB 120 offsets a generator of the channel as defined in
+    /// the SoundFont 2.01 specification.
+    /// The offset is applied to the sounding notes and the subsequent notes,
+    /// except that the changes of the envelopes, the LFOs and the scale tuning
+    /// only affect the subsequent notes.
+    /// The offsets are cleared by the Reset All Controllers message.
+    ///
+    /// The channel pressure and the polyphonic key pressure are applied to the sounding notes
+    /// through the modulators in the SoundFont or the mapping set by `set_pressure_mapping`.
+    ///
+    /// The portamento time is given in milliseconds by the 14-bit value of the controllers 5 and 37.
+    /// The portamento control (controller 84) gives the source key of the glide to the next note,
+    /// regardless of whether the portamento is on.
+    /// In the mono mode, a note played while another note is held takes over its voices
+    /// without restarting the envelopes.
+    ///
+    /// The RPN 6 configures an MPE zone in the same way as `configure_mpe_zone`.
     pub fn process_midi_message(&mut self, channel: i32, command: i32, data1: i32, data2: i32) {
         if channel < 0 || channel >= self.channels.len() as i32 {
             return;
@@ -145,7 +192,9 @@ impl Synthesizer {
                 0x00 => channel_info.set_bank(data2), // Bank Selection
                 0x01 => channel_info.set_modulation_coarse(data2), // Modulation Coarse
                 0x21 => channel_info.set_modulation_fine(data2), // Modulation Fine
-                0x06 => channel_info.data_entry_coarse(data2), // Data Entry Coarse
+                0x05 => channel_info.set_portamento_time_coarse(data2), // Portamento Time Coarse
+                0x25 => channel_info.set_portamento_time_fine(data2), // Portamento Time Fine
+                0x06 => self.data_entry_coarse(channel, data2), // Data Entry Coarse
                 0x26 => channel_info.data_entry_fine(data2), // Data Entry Fine
                 0x07 => channel_info.set_volume_coarse(data2), // Channel Volume Coarse
                 0x27 => channel_info.set_volume_fine(data2), // Channel Volume Fine
@@ -154,21 +203,211 @@ impl Synthesizer {
                 0x0B => channel_info.set_expression_coarse(data2), // Expression Coarse
                 0x2B => channel_info.set_expression_fine(data2), // Expression Fine
                 0x40 => channel_info.set_hold_pedal(data2), // Hold Pedal
+                0x41 => channel_info.set_portamento(data2), // Portamento
+                0x42 => self.set_sostenuto_pedal(channel, data2), // Sostenuto Pedal
+                0x43 => channel_info.set_soft_pedal(data2), // Soft Pedal
+                0x4A => channel_info.set_brightness(data2 << 7), // Brightness
+                0x54 => channel_info.set_portamento_control(data2), // Portamento Control
                 0x5B => channel_info.set_reverb_send(data2), // Reverb Send
                 0x5D => channel_info.set_chorus_send(data2), // Chorus Send
+                0x60 => channel_info.data_increment(), // Data Increment
+                0x61 => channel_info.data_decrement(), // Data Decrement
+                0x63 => channel_info.set_nrpn_coarse(data2), // NRPN Coarse
+                0x62 => channel_info.set_nrpn_fine(data2), // NRPN Fine
                 0x65 => channel_info.set_rpn_coarse(data2), // RPN Coarse
                 0x64 => channel_info.set_rpn_fine(data2), // RPN Fine
                 0x78 => self.note_off_all_channel(channel, true), // All Sound Off
                 0x79 => self.reset_all_controllers_channel(channel), // Reset All Controllers
                 0x7B => self.note_off_all_channel(channel, false), // All Note Off
+                0x7E => self.set_mono_mode(channel, true), // Mono Mode On
+                0x7F => self.set_mono_mode(channel, false), // Poly Mode On
                 _ => (),
             },
-            0xC0 => channel_info.set_patch(data1), // Program Change
-            0xE0 => channel_info.set_pitch_bend(data1, data2), // Pitch Bend
+            0xA0 => channel_info.set_key_pressure(data1, data2 << 7), // Polyphonic Key Pressure
+            0xC0 => channel_info.set_patch(data1),                    // Program Change
+            0xD0 => channel_info.set_channel_pressure(data1 << 7),    // Channel Pressure
+            0xE0 => channel_info.set_pitch_bend(data1, data2),        // Pitch Bend
             _ => (),
         }
     }
 
+    /// Processes a MIDI message sent to a channel of a port.
+    ///
+    /// # Arguments
+    ///
+    /// * `port` - The port to which the message will be sent.
+    /// * `channel` - The channel in the port, from 0 to 15.
+    /// * `command` - The type of the message.
+    /// * `data1` - The first data part of the message.
+    /// * `data2` - The second data part of the message.
+    ///
+    /// # Remarks
+    ///
+    /// The channel `c` of the port `p` is the channel `16 * p + c` of the synthesizer.
+    /// The messages to the ports beyond the number of channels are ignored.
+    pub fn process_port_midi_message(
+        &mut self,
+        port: i32,
+        channel: i32,
+        command: i32,
+        data1: i32,
+        data2: i32,
+    ) {
+        if port < 0 || !(0..Synthesizer::CHANNEL_COUNT as i32).contains(&channel) {
+            return;
+        }
+
+        let channel = Synthesizer::CHANNEL_COUNT as i32 * port + channel;
+        self.process_midi_message(channel, command, data1, data2);
+    }
+
+    /// Processes a MIDI message at a specified position in the waveform to be rendered.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame_offset` - The position in frames where the message takes effect,
+    ///   counted from the first frame written by the next call to `render`.
+    /// * `channel` - The channel to which the message will be sent.
+    /// * `command` - The type of the message.
+    /// * `data1` - The first data part of the message.
+    /// * `data2` - The second data part of the message.
+    ///
+    /// # Remarks
+    ///
+    /// The synthesis block is split at the position, so the message takes effect
+    /// at the exact sample.
+    /// The messages at the same position are processed in the order they were given.
+    /// If the previous call to `render` did not end at a block boundary,
+    /// the rest of the block has already been synthesized,
+    /// and a message within it takes effect at the beginning of the next block.
+    pub fn process_midi_message_at(
+        &mut self,
+        frame_offset: usize,
+        channel: i32,
+        command: i32,
+        data1: i32,
+        data2: i32,
+    ) {
+        let message = ScheduledMessage::Midi {
+            channel,
+            command,
+            data1,
+            data2,
+        };
+        self.schedule(frame_offset, message);
+    }
+
+    pub(crate) fn schedule(&mut self, frame_offset: usize, message: ScheduledMessage) {
+        self.schedule_from(0, frame_offset, message);
+    }
+
+    // Schedules the message on behalf of the owner, so that it can be flushed separately.
+    pub(crate) fn schedule_from(
+        &mut self,
+        owner: usize,
+        frame_offset: usize,
+        message: ScheduledMessage,
+    ) {
+        let output_frames = self.rendered_frames - (self.block_length - self.block_read) as u64;
+        let frame = output_frames + frame_offset as u64;
+
+        // The messages at the same frame are kept in the given order.
+        let index = self
+            .scheduled_messages
+            .partition_point(|(value, _, _)| *value <= frame);
+        self.scheduled_messages
+            .insert(index, (frame, owner, message));
+    }
+
+    // Processes the scheduled messages of the owner immediately.
+    pub(crate) fn flush_messages_from(&mut self, owner: usize) {
+        let mut i = 0;
+        while i < self.scheduled_messages.len() {
+            if self.scheduled_messages[i].1 == owner {
+                if let Some((_, _, message)) = self.scheduled_messages.remove(i) {
+                    self.process_scheduled_message(message);
+                }
+            } else {
+                i += 1;
+            }
+        }
+    }
+
+    // Discards the scheduled messages for the channels.
+    pub(crate) fn cancel_channel_messages(&mut self, channels: &[u8]) {
+        let channel_count = self.channels.len();
+        self.scheduled_messages
+            .retain(|(_, _, message)| !message.is_sent_to(channels, channel_count));
+    }
+
+    fn process_scheduled_message(&mut self, message: ScheduledMessage) {
+        match message {
+            ScheduledMessage::Midi {
+                channel,
+                command,
+                data1,
+                data2,
+            } => self.process_midi_message(channel, command, data1, data2),
+            ScheduledMessage::Ump(packet) => self.process_ump(&packet),
+            ScheduledMessage::NoteOffAll(immediate) => self.note_off_all(immediate),
+            ScheduledMessage::MasterVolume(value) => self.master_volume = value,
+            ScheduledMessage::SysEx { length, data } => {
+                self.process_sysex(&data[..length as usize])
+            }
+            ScheduledMessage::FadeOutAll(length) => self.fade_out_all(length),
+            ScheduledMessage::FadeIn(length) => self.fade_in(length),
+            ScheduledMessage::ResetChannels => {
+                for channel in &mut self.channels {
+                    channel.reset();
+                }
+            }
+        }
+    }
+
+    // Fades out all the sounding notes, which stop when the fade-out completes.
+    pub(crate) fn fade_out_all(&mut self, length: usize) {
+        if length == 0 {
+            self.note_off_all(true);
+            return;
+        }
+
+        for voice in self.voices.get_active_voices().iter_mut() {
+            voice.fade_out(length);
+        }
+    }
+
+    // The notes started until the end of the fade-in are faded in.
+    pub(crate) fn fade_in(&mut self, length: usize) {
+        self.fade_in_length = length;
+        self.fade_in_end = self.rendered_frames + length as u64;
+    }
+
+    /// Creates a handle to send events to the synthesizer from other threads.
+    ///
+    /// # Remarks
+    ///
+    /// All the handles created by this method share the same queue,
+    /// which holds up to 1024 events.
+    /// The events in the queue are processed at the beginning of each synthesis block,
+    /// after the messages given directly to the synthesizer.
+    /// The events and the messages scheduled by `process_midi_message_at` share the space
+    /// for 1024 messages. If the space is used up, the events wait in the queue
+    /// until the scheduled messages are processed.
+    pub fn create_handle(&mut self) -> SynthesizerHandle {
+        let queue = match self.event_queue.as_ref() {
+            Some(value) => Arc::clone(value),
+            None => {
+                // The space for the events is reserved to avoid allocation in the rendering thread.
+                self.scheduled_messages.reserve(EventQueue::CAPACITY);
+                let queue = Arc::new(EventQueue::new());
+                self.event_queue = Some(Arc::clone(&queue));
+                queue
+            }
+        };
+
+        SynthesizerHandle { queue }
+    }
+
     /// Adjusts the pitch of a note.
     ///
     /// The `tuning` parameter increases the pitch by one semitone for a value of 1.0, two semitones
@@ -229,11 +468,58 @@ impl Synthesizer {
             return;
         }
 
+        self.start_note(channel, key, velocity, velocity as f32 / 127_f32);
+    }
+
+    pub(crate) fn start_note(
+        &mut self,
+        channel: i32,
+        key: i32,
+        velocity: i32,
+        normalized_velocity: f32,
+    ) {
         if channel < 0 || channel >= self.channels.len() as i32 {
             return;
         }
 
-        let channel_info = &self.channels[channel as usize];
+        // The notes in an MPE zone take the program and the controllers of the manager channel.
+        let zone_channel = self.get_zone_channel(channel);
+        let soft_pedal = self.channels[zone_channel as usize].get_soft_pedal();
+
+        let channel_info = &mut self.channels[channel as usize];
+
+        // The key pressure of the previous note is not taken over.
+        channel_info.set_key_pressure(key, 0);
+
+        let glide_source = channel_info.start_portamento(key);
+        let glide_length = match glide_source {
+            Some(_) => (channel_info.get_portamento_time() * self.sample_rate as f32) as usize,
+            None => 0,
+        };
+
+        // The soft pedal lowers the velocity of the new notes.
+        let (velocity, normalized_velocity) = if soft_pedal {
+            let factor = Synthesizer::SOFT_PEDAL_VELOCITY;
+            let velocity = ((factor * velocity as f32) as i32).max(1);
+            (velocity, factor * normalized_velocity)
+        } else {
+            (velocity, normalized_velocity)
+        };
+
+        if channel_info.get_mono_mode() {
+            let mut legato = false;
+            for voice in self.voices.get_active_voices().iter_mut() {
+                if voice.channel == channel && voice.is_playing() {
+                    voice.legato(key, glide_length);
+                    legato = true;
+                }
+            }
+            if legato {
+                return;
+            }
+        }
+
+        let channel_info = &self.channels[zone_channel as usize];
 
         let preset_id = (channel_info.get_bank_number() << 16) | channel_info.get_patch_number();
 
@@ -257,16 +543,40 @@ impl Synthesizer {
             }
         }
 
+        let generator_offsets = channel_info.get_generator_offsets();
+
         let preset = &self.sound_font.presets[preset];
         for preset_region in preset.regions.iter() {
             if preset_region.contains(key, velocity) {
                 let instrument = &self.sound_font.instruments[preset_region.instrument];
                 for instrument_region in instrument.regions.iter() {
                     if instrument_region.contains(key, velocity) {
-                        let region_pair = RegionPair::new(preset_region, instrument_region);
+                        let region_pair =
+                            RegionPair::new(preset_region, instrument_region, generator_offsets);
 
                         if let Some(voice) = self.voices.request_new(instrument_region, channel) {
-                            voice.start(&region_pair, channel, key, velocity)
+                            voice.start(
+                                &region_pair,
+                                channel,
+                                key,
+                                velocity,
+                                normalized_velocity,
+                                &self.pressure_mapping,
+                            );
+                            voice.zone_channel = zone_channel;
+
+                            if soft_pedal {
+                                voice.soften();
+                            }
+
+                            if let Some(source) = glide_source {
+                                voice.glide(source as f32, glide_length);
+                            }
+
+                            if self.rendered_frames < self.fade_in_end {
+                                let rest = (self.fade_in_end - self.rendered_frames) as usize;
+                                voice.fade_in(self.fade_in_length, self.fade_in_length - rest);
+                            }
                         }
                     }
                 }
@@ -296,21 +606,53 @@ impl Synthesizer {
     /// * `channel` - The channel in which the notes will be stopped.
     /// * `immediate` - If `true`, notes will stop immediately without the release sound.
     pub fn note_off_all_channel(&mut self, channel: i32, immediate: bool) {
+        // The notes in an MPE zone are also stopped by the manager channel.
         if immediate {
             for voice in self.voices.get_active_voices().iter_mut() {
-                if voice.channel == channel {
+                if voice.channel == channel || voice.zone_channel == channel {
                     voice.kill();
                 }
             }
         } else {
             for voice in self.voices.get_active_voices().iter_mut() {
-                if voice.channel == channel {
+                if voice.channel == channel || voice.zone_channel == channel {
                     voice.end();
                 }
             }
         }
     }
 
+    fn data_entry_coarse(&mut self, channel: i32, value: i32) {
+        let channel_info = &mut self.channels[channel as usize];
+        channel_info.data_entry_coarse(value);
+
+        // The MPE configuration message is the RPN 6, which only has the MSB.
+        if channel_info.get_selected_rpn() == Some(6) {
+            self.configure_mpe_zone(channel, value);
+        }
+    }
+
+    pub(crate) fn set_sostenuto_pedal(&mut self, channel: i32, value: i32) {
+        let channel_info = &mut self.channels[channel as usize];
+
+        // Only the notes held at the moment the pedal is pressed are sustained.
+        if value >= 64 && !channel_info.get_sostenuto_pedal() {
+            for voice in self.voices.get_active_voices().iter_mut() {
+                if voice.zone_channel == channel {
+                    voice.catch_sostenuto();
+                }
+            }
+        }
+
+        channel_info.set_sostenuto_pedal(value);
+    }
+
+    // The mode messages also stop all the notes in the channel.
+    fn set_mono_mode(&mut self, channel: i32, value: bool) {
+        self.note_off_all_channel(channel, false);
+        self.channels[channel as usize].set_mono_mode(value);
+    }
+
     /// Resets all the controllers.
     pub fn reset_all_controllers(&mut self) {
         for channel in &mut self.channels {
@@ -337,14 +679,20 @@ impl Synthesizer {
 
         for channel in &mut self.channels {
             channel.reset();
+            channel.unfreeze(0);
         }
+        self.clear_mpe_zones();
 
         if let Some(effects) = self.effects.as_mut() {
             effects.reverb.mute();
             effects.chorus.mute();
         }
 
-        self.block_read = self.block_size;
+        self.scheduled_messages.clear();
+        self.block_length = 0;
+        self.block_read = 0;
+        self.rendered_frames = 0;
+        self.fade_in_end = 0;
     }
 
     /// Renders the waveform.
@@ -366,12 +714,12 @@ impl Synthesizer {
 
         let mut wrote = 0;
         while wrote < left_length {
-            if self.block_read == self.block_size {
+            if self.block_read == self.block_length {
                 self.render_block();
                 self.block_read = 0;
             }
 
-            let src_rem = self.block_size - self.block_read;
+            let src_rem = self.block_length - self.block_read;
             let dst_rem = left_length - wrote;
             let rem = cmp::min(src_rem, dst_rem);
 
@@ -386,49 +734,89 @@ impl Synthesizer {
     }
 
     fn render_block(&mut self) {
-        self.voices
-            .process(&self.sound_font.wave_data, &self.channels);
+        // The events are taken only within the reserved space, so that the rendering thread
+        // does not allocate memory even if the messages given directly are also scheduled.
+        // The rest of the events wait in the queue until the next block.
+        if let Some(queue) = self.event_queue.clone() {
+            while self.scheduled_messages.len() < self.scheduled_messages.capacity() {
+                match queue.pop() {
+                    Some((frame_offset, message)) => self.schedule(frame_offset, message),
+                    None => break,
+                }
+            }
+        }
 
-        self.block_left.fill(0_f32);
-        self.block_right.fill(0_f32);
-        for voice in self.voices.get_active_voices().iter_mut() {
+        while let Some(&(frame, _, message)) = self.scheduled_messages.front() {
+            if frame > self.rendered_frames {
+                break;
+            }
+            self.scheduled_messages.pop_front();
+            self.process_scheduled_message(message);
+        }
+
+        // The block is split at the next scheduled message,
+        // but never crosses the boundaries of the regular blocks.
+        let block_size = self.block_size as u64;
+        let mut block_length = block_size - self.rendered_frames % block_size;
+        if let Some((frame, _, _)) = self.scheduled_messages.front() {
+            block_length = cmp::min(block_length, frame - self.rendered_frames);
+        }
+        let block_length = block_length as usize;
+        let inverse_block_length = 1_f32 / block_length as f32;
+
+        self.block_length = block_length;
+        self.rendered_frames += block_length as u64;
+
+        for channel in &mut self.channels {
+            channel.process_freeze(block_length);
+        }
+
+        self.voices
+            .process(&self.sound_font.wave_data, &self.channels, block_length);
+        let voices: &[Voice] = self.voices.get_active_voices();
+
+        let block_left = &mut self.block_left[..block_length];
+        let block_right = &mut self.block_right[..block_length];
+        block_left.fill(0_f32);
+        block_right.fill(0_f32);
+        for voice in voices {
             let previous_gain_left = self.master_volume * voice.previous_mix_gain_left;
             let current_gain_left = self.master_volume * voice.current_mix_gain_left;
             Synthesizer::write_block(
                 previous_gain_left,
                 current_gain_left,
-                &voice.block[..],
-                &mut self.block_left[..],
-                self.inverse_block_size,
+                &voice.block[..block_length],
+                block_left,
+                inverse_block_length,
             );
             let previous_gain_right = self.master_volume * voice.previous_mix_gain_right;
             let current_gain_right = self.master_volume * voice.current_mix_gain_right;
             Synthesizer::write_block(
                 previous_gain_right,
                 current_gain_right,
-                &voice.block[..],
-                &mut self.block_right[..],
-                self.inverse_block_size,
+                &voice.block[..block_length],
+                block_right,
+                inverse_block_length,
             );
         }
 
         if let Some(effects) = self.effects.as_mut() {
             let chorus = &mut effects.chorus;
-            let chorus_input_left = &mut effects.chorus_input_left[..];
-            let chorus_input_right = &mut effects.chorus_input_right[..];
-            let chorus_output_left = &mut effects.chorus_output_left[..];
-            let chorus_output_right = &mut effects.chorus_output_right[..];
+            let chorus_input_left = &mut effects.chorus_input_left[..block_length];
+            let chorus_input_right = &mut effects.chorus_input_right[..block_length];
+            let chorus_output_left = &mut effects.chorus_output_left[..block_length];
+            let chorus_output_right = &mut effects.chorus_output_right[..block_length];
             chorus_input_left.fill(0_f32);
             chorus_input_right.fill(0_f32);
-            for voice in self.voices.get_active_voices().iter_mut() {
+            for voice in voices {
                 let previous_gain_left = voice.previous_chorus_send * voice.previous_mix_gain_left;
                 let current_gain_left = voice.current_chorus_send * voice.current_mix_gain_left;
                 Synthesizer::write_block(
                     previous_gain_left,
                     current_gain_left,
-                    &voice.block[..],
+                    &voice.block[..block_length],
                     chorus_input_left,
-                    self.inverse_block_size,
+                    inverse_block_length,
                 );
                 let previous_gain_right =
                     voice.previous_chorus_send * voice.previous_mix_gain_right;
@@ -436,9 +824,9 @@ impl Synthesizer {
                 Synthesizer::write_block(
                     previous_gain_right,
                     current_gain_right,
-                    &voice.block[..],
+                    &voice.block[..block_length],
                     chorus_input_right,
-                    self.inverse_block_size,
+                    inverse_block_length,
                 );
             }
             chorus.process(
@@ -447,23 +835,15 @@ impl Synthesizer {
                 chorus_output_left,
                 chorus_output_right,
             );
-            ArrayMath::multiply_add(
-                self.master_volume,
-                chorus_output_left,
-                &mut self.block_left[..],
-            );
-            ArrayMath::multiply_add(
-                self.master_volume,
-                chorus_output_right,
-                &mut self.block_right[..],
-            );
+            ArrayMath::multiply_add(self.master_volume, chorus_output_left, block_left);
+            ArrayMath::multiply_add(self.master_volume, chorus_output_right, block_right);
 
             let reverb = &mut effects.reverb;
-            let reverb_input = &mut effects.reverb_input[..];
-            let reverb_output_left = &mut effects.reverb_output_left[..];
-            let reverb_output_right = &mut effects.reverb_output_right[..];
+            let reverb_input = &mut effects.reverb_input[..block_length];
+            let reverb_output_left = &mut effects.reverb_output_left[..block_length];
+            let reverb_output_right = &mut effects.reverb_output_right[..block_length];
             reverb_input.fill(0_f32);
-            for voice in self.voices.get_active_voices().iter_mut() {
+            for voice in voices {
                 let previous_gain = reverb.get_input_gain()
                     * voice.previous_reverb_send
                     * (voice.previous_mix_gain_left + voice.previous_mix_gain_right);
@@ -473,23 +853,15 @@ impl Synthesizer {
                 Synthesizer::write_block(
                     previous_gain,
                     current_gain,
-                    &voice.block[..],
-                    &mut reverb_input[..],
-                    self.inverse_block_size,
+                    &voice.block[..block_length],
+                    reverb_input,
+                    inverse_block_length,
                 );
             }
 
             reverb.process(reverb_input, reverb_output_left, reverb_output_right);
-            ArrayMath::multiply_add(
-                self.master_volume,
-                reverb_output_left,
-                &mut self.block_left[..],
-            );
-            ArrayMath::multiply_add(
-                self.master_volume,
-                reverb_output_right,
-                &mut self.block_right[..],
-            );
+            ArrayMath::multiply_add(self.master_volume, reverb_output_left, block_left);
+            ArrayMath::multiply_add(self.master_volume, reverb_output_right, block_right);
         }
     }
 
@@ -498,7 +870,7 @@ impl Synthesizer {
         current_gain: f32,
         source: &[f32],
         destination: &mut [f32],
-        inverse_block_size: f32,
+        inverse_block_length: f32,
     ) {
         if SoundFontMath::max(previous_gain, current_gain) < SoundFontMath::NON_AUDIBLE {
             return;
@@ -507,7 +879,7 @@ impl Synthesizer {
         if (current_gain - previous_gain).abs() < 1.0E-3_f32 {
             ArrayMath::multiply_add(current_gain, source, destination);
         } else {
-            let step = inverse_block_size * (current_gain - previous_gain);
+            let step = inverse_block_length * (current_gain - previous_gain);
             ArrayMath::multiply_add_slope(previous_gain, step, source, destination);
         }
     }
@@ -537,6 +909,65 @@ impl Synthesizer {
         self.effects.is_some()
     }
 
+    /// Gets the number of channels.
+    pub fn get_channel_count(&self) -> usize {
+        self.channels.len()
+    }
+
+    /// Gets a value that indicates whether the channel is used for percussion.
+    ///
+    /// # Remarks
+    ///
+    /// If the channel is out of range, this value will be `false`.
+    pub fn is_percussion_channel(&self, channel: i32) -> bool {
+        if channel < 0 || channel >= self.channels.len() as i32 {
+            return false;
+        }
+
+        self.channels[channel as usize].is_percussion_channel
+    }
+
+    /// Sets whether the channel is used for percussion.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel` - The channel to be set.
+    /// * `value` - If `true`, the channel selects the presets in the percussion banks.
+    ///
+    /// # Remarks
+    ///
+    /// By default, the channel 9 of each port is used for percussion.
+    /// The selected bank is moved to or from the percussion banks immediately,
+    /// and the setting is kept when the synthesizer is reset.
+    /// If the channel is out of range, this method does nothing.
+    pub fn set_percussion_channel(&mut self, channel: i32, value: bool) {
+        if channel < 0 || channel >= self.channels.len() as i32 {
+            return;
+        }
+
+        self.channels[channel as usize].set_percussion_channel(value);
+    }
+
+    /// Gets the mapping of the pressure to the parameters of the notes.
+    pub fn get_pressure_mapping(&self) -> PressureMapping {
+        self.pressure_mapping
+    }
+
+    /// Sets the mapping of the pressure to the parameters of the notes.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The new mapping.
+    ///
+    /// # Remarks
+    ///
+    /// The channel pressure and the polyphonic key pressure are applied through this mapping,
+    /// unless the SoundFont defines the modulators driven by the pressure.
+    /// The new mapping is used for the notes started after the change.
+    pub fn set_pressure_mapping(&mut self, value: PressureMapping) {
+        self.pressure_mapping = value;
+    }
+
     /// Gets the master volume.
     pub fn get_master_volume(&self) -> f32 {
         self.master_volume
@@ -580,3 +1011,34 @@ impl Effects {
         }
     }
 }
+
+#[derive(Clone, Copy)]
+pub(crate) enum ScheduledMessage {
+    Midi {
+        channel: i32,
+        command: i32,
+        data1: i32,
+        data2: i32,
+    },
+    Ump([u32; 2]),
+    NoteOffAll(bool),
+    MasterVolume(f32),
+    SysEx {
+        length: u8,
+        data: [u8; SynthesizerHandle::MAXIMUM_SYSEX_LENGTH],
+    },
+    FadeOutAll(usize),
+    FadeIn(usize),
+    ResetChannels,
+}
+
+impl ScheduledMessage {
+    fn is_sent_to(&self, channels: &[u8], channel_count: usize) -> bool {
+        let channel = match *self {
+            ScheduledMessage::Midi { channel, .. } => channel,
+            ScheduledMessage::Ump(packet) => Synthesizer::get_ump_channel(packet[0], channel_count),
+            _ => return false,
+        };
+        channels.iter().any(|value| *value as i32 == channel)
+    }
+}
@@ -0,0 +1,334 @@
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use crate::synthesizer::ScheduledMessage;
+
+/// A handle to send events to a synthesizer from other threads.
+///
+/// # Remarks
+///
+/// The handle is created by `Synthesizer::create_handle`, and can be cloned and sent
+/// to any number of threads.
+/// The events are stored in a bounded lock-free queue, and the synthesizer takes them
+/// at the beginning of each synthesis block.
+/// Neither the threads sending the events nor the thread rendering the waveform
+/// take a lock, and the rendering thread does not allocate memory for the events.
+#[derive(Clone)]
+#[non_exhaustive]
+pub struct SynthesizerHandle {
+    pub(crate) queue: Arc<EventQueue>,
+}
+
+impl SynthesizerHandle {
+    /// The maximum length of the SysEx messages sent through the handle.
+    pub const MAXIMUM_SYSEX_LENGTH: usize = 56;
+
+    /// Sends a MIDI message.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel` - The channel to which the message will be sent.
+    /// * `command` - The type of the message.
+    /// * `data1` - The first data part of the message.
+    /// * `data2` - The second data part of the message.
+    ///
+    /// # Remarks
+    ///
+    /// If the queue is full, the message is discarded and `false` is returned.
+    pub fn process_midi_message(&self, channel: i32, command: i32, data1: i32, data2: i32) -> bool {
+        self.process_midi_message_at(0, channel, command, data1, data2)
+    }
+
+    /// Sends a MIDI message to be processed at a specified position in the waveform.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame_offset` - The position in frames where the message takes effect,
+    ///   counted from the beginning of the synthesis block in which the message is received.
+    /// * `channel` - The channel to which the message will be sent.
+    /// * `command` - The type of the message.
+    /// * `data1` - The first data part of the message.
+    /// * `data2` - The second data part of the message.
+    ///
+    /// # Remarks
+    ///
+    /// If the queue is full, the message is discarded and `false` is returned.
+    pub fn process_midi_message_at(
+        &self,
+        frame_offset: usize,
+        channel: i32,
+        command: i32,
+        data1: i32,
+        data2: i32,
+    ) -> bool {
+        let message = ScheduledMessage::Midi {
+            channel,
+            command,
+            data1,
+            data2,
+        };
+        self.queue.push(frame_offset, message)
+    }
+
+    /// Sends a SysEx message.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The data of the message. The leading 0xF0 and the trailing 0xF7 are optional.
+    ///
+    /// # Remarks
+    ///
+    /// If the queue is full or the message is longer than `MAXIMUM_SYSEX_LENGTH`,
+    /// the message is discarded and `false` is returned.
+    pub fn process_sysex(&self, data: &[u8]) -> bool {
+        if data.len() > SynthesizerHandle::MAXIMUM_SYSEX_LENGTH {
+            return false;
+        }
+
+        let mut buffer = [0_u8; SynthesizerHandle::MAXIMUM_SYSEX_LENGTH];
+        buffer[..data.len()].copy_from_slice(data);
+        let message = ScheduledMessage::SysEx {
+            length: data.len() as u8,
+            data: buffer,
+        };
+        self.queue.push(0, message)
+    }
+
+    /// Sets the master volume.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The new value of the master volume.
+    ///
+    /// # Remarks
+    ///
+    /// If the queue is full, the change is discarded and `false` is returned.
+    pub fn set_master_volume(&self, value: f32) -> bool {
+        self.queue.push(0, ScheduledMessage::MasterVolume(value))
+    }
+
+    /// Stops all the notes.
+    ///
+    /// # Arguments
+    ///
+    /// * `immediate` - If `true`, notes will stop immediately without the release sound.
+    ///
+    /// # Remarks
+    ///
+    /// If the queue is full, the request is discarded and `false` is returned.
+    pub fn note_off_all(&self, immediate: bool) -> bool {
+        self.queue.push(0, ScheduledMessage::NoteOffAll(immediate))
+    }
+}
+
+// The frame offset and the kind, the length of the SysEx message, and its data.
+const WORD_COUNT: usize = 2 + SynthesizerHandle::MAXIMUM_SYSEX_LENGTH / 8;
+
+// A bounded multi-producer single-consumer queue.
+// Each slot has a sequence number which tells whether the slot is ready to be written or read.
+// The events are stored as atomic words, so that no unsafe code is needed.
+pub(crate) struct EventQueue {
+    slots: Vec<Slot>,
+    mask: usize,
+    enqueue_position: AtomicUsize,
+    dequeue_position: AtomicUsize,
+}
+
+struct Slot {
+    sequence: AtomicUsize,
+    words: [AtomicU64; WORD_COUNT],
+}
+
+impl EventQueue {
+    pub(crate) const CAPACITY: usize = 1024;
+
+    pub(crate) fn new() -> Self {
+        let slots = (0..EventQueue::CAPACITY)
+            .map(|i| Slot {
+                sequence: AtomicUsize::new(i),
+                words: Default::default(),
+            })
+            .collect();
+
+        Self {
+            slots,
+            mask: EventQueue::CAPACITY - 1,
+            enqueue_position: AtomicUsize::new(0),
+            dequeue_position: AtomicUsize::new(0),
+        }
+    }
+
+    fn push(&self, frame_offset: usize, message: ScheduledMessage) -> bool {
+        let words = EventQueue::encode(frame_offset, &message);
+
+        let mut position = self.enqueue_position.load(Ordering::Relaxed);
+        loop {
+            let slot = &self.slots[position & self.mask];
+            let sequence = slot.sequence.load(Ordering::Acquire);
+            let difference = sequence.wrapping_sub(position) as isize;
+
+            if difference == 0 {
+                match self.enqueue_position.compare_exchange_weak(
+                    position,
+                    position.wrapping_add(1),
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => {
+                        for (word, value) in slot.words.iter().zip(words.iter()) {
+                            word.store(*value, Ordering::Relaxed);
+                        }
+                        slot.sequence
+                            .store(position.wrapping_add(1), Ordering::Release);
+                        return true;
+                    }
+                    Err(value) => position = value,
+                }
+            } else if difference < 0 {
+                // The slot has not been read yet, which means the queue is full.
+                return false;
+            } else {
+                position = self.enqueue_position.load(Ordering::Relaxed);
+            }
+        }
+    }
+
+    // Must be called only from the thread which owns the synthesizer.
+    pub(crate) fn pop(&self) -> Option<(usize, ScheduledMessage)> {
+        let position = self.dequeue_position.load(Ordering::Relaxed);
+        let slot = &self.slots[position & self.mask];
+        if slot.sequence.load(Ordering::Acquire) != position.wrapping_add(1) {
+            return None;
+        }
+
+        let mut words = [0_u64; WORD_COUNT];
+        for (value, word) in words.iter_mut().zip(slot.words.iter()) {
+            *value = word.load(Ordering::Relaxed);
+        }
+        slot.sequence
+            .store(position.wrapping_add(self.mask + 1), Ordering::Release);
+        self.dequeue_position
+            .store(position.wrapping_add(1), Ordering::Relaxed);
+
+        EventQueue::decode(&words)
+    }
+
+    // The first word holds the frame offset and the kind of the event.
+    fn encode(frame_offset: usize, message: &ScheduledMessage) -> [u64; WORD_COUNT] {
+        let mut words = [0_u64; WORD_COUNT];
+        let frame_offset = frame_offset.min(u32::MAX as usize) as u64;
+        let kind: u64 = match *message {
+            ScheduledMessage::Midi {
+                channel,
+                command,
+                data1,
+                data2,
+            } => {
+                words[1] = EventQueue::pack(channel, command);
+                words[2] = EventQueue::pack(data1, data2);
+                0
+            }
+            ScheduledMessage::Ump(packet) => {
+                words[1] = (packet[0] as u64) | ((packet[1] as u64) << 32);
+                1
+            }
+            ScheduledMessage::NoteOffAll(immediate) => {
+                words[1] = immediate as u64;
+                2
+            }
+            ScheduledMessage::MasterVolume(value) => {
+                words[1] = value.to_bits() as u64;
+                3
+            }
+            ScheduledMessage::SysEx { length, data } => {
+                words[1] = length as u64;
+                for (word, bytes) in words[2..].iter_mut().zip(data.chunks(8)) {
+                    let mut buffer = [0_u8; 8];
+                    buffer[..bytes.len()].copy_from_slice(bytes);
+                    *word = u64::from_le_bytes(buffer);
+                }
+                4
+            }
+            ScheduledMessage::FadeOutAll(length) => {
+                words[1] = length as u64;
+                5
+            }
+            ScheduledMessage::FadeIn(length) => {
+                words[1] = length as u64;
+                6
+            }
+            ScheduledMessage::ResetChannels => 7,
+        };
+        words[0] = frame_offset | (kind << 32);
+        words
+    }
+
+    fn decode(words: &[u64; WORD_COUNT]) -> Option<(usize, ScheduledMessage)> {
+        let frame_offset = (words[0] & 0xFFFFFFFF) as usize;
+        let message = match words[0] >> 32 {
+            0 => {
+                let (channel, command) = EventQueue::unpack(words[1]);
+                let (data1, data2) = EventQueue::unpack(words[2]);
+                ScheduledMessage::Midi {
+                    channel,
+                    command,
+                    data1,
+                    data2,
+                }
+            }
+            1 => ScheduledMessage::Ump([words[1] as u32, (words[1] >> 32) as u32]),
+            2 => ScheduledMessage::NoteOffAll(words[1] != 0),
+            3 => ScheduledMessage::MasterVolume(f32::from_bits(words[1] as u32)),
+            4 => {
+                let mut data = [0_u8; SynthesizerHandle::MAXIMUM_SYSEX_LENGTH];
+                for (bytes, word) in data.chunks_mut(8).zip(words[2..].iter()) {
+                    let length = bytes.len();
+                    bytes.copy_from_slice(&word.to_le_bytes()[..length]);
+                }
+                ScheduledMessage::SysEx {
+                    length: words[1] as u8,
+                    data,
+                }
+            }
+            5 => ScheduledMessage::FadeOutAll(words[1] as usize),
+            6 => ScheduledMessage::FadeIn(words[1] as usize),
+            7 => ScheduledMessage::ResetChannels,
+            _ => return None,
+        };
+        Some((frame_offset, message))
+    }
+
+    fn pack(value1: i32, value2: i32) -> u64 {
+        (value1 as u32 as u64) | ((value2 as u32 as u64) << 32)
+    }
+
+    fn unpack(word: u64) -> (i32, i32) {
+        (word as u32 as i32, (word >> 32) as u32 as i32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn longest_sysex_round_trip() {
+        let handle = SynthesizerHandle {
+            queue: Arc::new(EventQueue::new()),
+        };
+        let expected: Vec<u8> = (0..SynthesizerHandle::MAXIMUM_SYSEX_LENGTH as u8)
+            .map(|i| i + 1)
+            .collect();
+        assert!(handle.process_sysex(&expected));
+
+        match handle.queue.pop() {
+            Some((0, ScheduledMessage::SysEx { length, data })) => {
+                assert_eq!(length as usize, expected.len());
+                assert_eq!(&data[..], &expected[..]);
+            }
+            _ => panic!("The SysEx message must be taken from the queue."),
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use crate::synthesizer::Synthesizer;
+
+impl Synthesizer {
+    // The pitch bend ranges set by the MPE configuration message.
+    const MPE_MANAGER_PITCH_BEND_RANGE: i32 = 2;
+    const MPE_MEMBER_PITCH_BEND_RANGE: i32 = 48;
+
+    /// Configures an MPE (MIDI Polyphonic Expression) zone.
+    ///
+    /// # Arguments
+    ///
+    /// * `manager_channel` - The manager channel of the zone.
+    /// * `member_count` - The number of the member channels, from 0 to 15.
+    ///
+    /// # Remarks
+    ///
+    /// This has the same effect as the MPE configuration message (RPN 6).
+    /// The channel 0 of a port is the manager of the lower zone, whose member channels
+    /// start from the channel 1, and the channel 15 is the manager of the upper zone,
+    /// whose member channels start from the channel 14 downward.
+    /// The member count of 0 disables the zone, and the other zone shrinks if the zones overlap.
+    /// The pitch bend range of the manager channel is set to 2 semitones,
+    /// and that of the member channels is set to 48 semitones.
+    ///
+    /// The notes on the member channels use the program and the controllers of the manager channel,
+    /// except for the pitch bend, the pressure and the brightness (controller 74),
+    /// which are applied to the notes on each member channel.
+    /// The pitch bend and the brightness of the manager channel are added to them.
+    /// The configuration of the channels other than the managers is ignored.
+    pub fn configure_mpe_zone(&mut self, manager_channel: i32, member_count: i32) {
+        if manager_channel < 0 || manager_channel >= self.channels.len() as i32 {
+            return;
+        }
+
+        let port = manager_channel as usize / Synthesizer::CHANNEL_COUNT;
+        let zone = match manager_channel as usize % Synthesizer::CHANNEL_COUNT {
+            0 => 0,
+            15 => 1,
+            _ => return,
+        };
+
+        let member_count = member_count.clamp(0, 15) as usize;
+        let zones = &mut self.mpe_zones[port];
+        zones[zone] = member_count;
+        zones[1 - zone] = zones[1 - zone].min(14_usize.saturating_sub(member_count));
+
+        let manager = &mut self.channels[manager_channel as usize];
+        manager.set_registered_parameter(0, Synthesizer::MPE_MANAGER_PITCH_BEND_RANGE << 7);
+
+        for i in 1..=member_count {
+            let local = if zone == 0 { i } else { 15 - i };
+            let channel = Synthesizer::CHANNEL_COUNT * port + local;
+            if let Some(member) = self.channels.get_mut(channel) {
+                member.set_registered_parameter(0, Synthesizer::MPE_MEMBER_PITCH_BEND_RANGE << 7);
+            }
+        }
+    }
+
+    /// Gets the number of the member channels of an MPE zone.
+    ///
+    /// # Arguments
+    ///
+    /// * `manager_channel` - The manager channel of the zone.
+    ///
+    /// # Remarks
+    ///
+    /// The value is 0 if the zone is disabled or the channel is not a manager channel.
+    pub fn get_mpe_member_count(&self, manager_channel: i32) -> usize {
+        if manager_channel < 0 || manager_channel >= self.channels.len() as i32 {
+            return 0;
+        }
+
+        let zones = &self.mpe_zones[manager_channel as usize / Synthesizer::CHANNEL_COUNT];
+        match manager_channel as usize % Synthesizer::CHANNEL_COUNT {
+            0 => zones[0],
+            15 => zones[1],
+            _ => 0,
+        }
+    }
+
+    // Gets the manager channel if the channel is a member of an MPE zone,
+    // or otherwise the channel itself.
+    pub(crate) fn get_zone_channel(&self, channel: i32) -> i32 {
+        let port = channel as usize / Synthesizer::CHANNEL_COUNT;
+        let local = channel as usize % Synthesizer::CHANNEL_COUNT;
+        let [lower, upper] = self.mpe_zones[port];
+
+        if (1..=lower).contains(&local) {
+            (Synthesizer::CHANNEL_COUNT * port) as i32
+        } else if (15 - upper..15).contains(&local) {
+            (Synthesizer::CHANNEL_COUNT * port + 15) as i32
+        } else {
+            channel
+        }
+    }
+
+    pub(crate) fn clear_mpe_zones(&mut self) {
+        for zones in &mut self.mpe_zones {
+            *zones = [0; 2];
+        }
+    }
+}
@@ -13,12 +13,16 @@ pub struct SynthesizerSettings {
     pub maximum_polyphony: usize,
     /// The value indicating whether reverb and chorus are enabled.
     pub enable_reverb_and_chorus: bool,
+    /// The number of channels.
+    /// The channels beyond 16 are addressed as the channels of the subsequent ports.
+    pub channel_count: usize,
 }
 
 impl SynthesizerSettings {
     const DEFAULT_BLOCK_SIZE: usize = 64;
     const DEFAULT_MAXIMUM_POLYPHONY: usize = 64;
     const DEFAULT_ENABLE_REVERB_AND_CHORUS: bool = true;
+    const DEFAULT_CHANNEL_COUNT: usize = 16;
 
     /// Initializes a new instance of synthesizer settings.
     ///
@@ -31,6 +35,7 @@ impl SynthesizerSettings {
             block_size: SynthesizerSettings::DEFAULT_BLOCK_SIZE,
             maximum_polyphony: SynthesizerSettings::DEFAULT_MAXIMUM_POLYPHONY,
             enable_reverb_and_chorus: SynthesizerSettings::DEFAULT_ENABLE_REVERB_AND_CHORUS,
+            channel_count: SynthesizerSettings::DEFAULT_CHANNEL_COUNT,
         }
     }
 
@@ -38,6 +43,7 @@ impl SynthesizerSettings {
         SynthesizerSettings::check_sample_rate(self.sample_rate)?;
         SynthesizerSettings::check_block_size(self.block_size)?;
         SynthesizerSettings::check_maximum_polyphony(self.maximum_polyphony)?;
+        SynthesizerSettings::check_channel_count(self.channel_count)?;
 
         Ok(())
     }
@@ -65,4 +71,12 @@ impl SynthesizerSettings {
 
         Ok(())
     }
+
+    fn check_channel_count(value: usize) -> Result<(), SynthesizerError> {
+        if !(1..=240).contains(&value) {
+            return Err(SynthesizerError::ChannelCountOutOfRange(value));
+        }
+
+        Ok(())
+    }
 }
@@ -0,0 +1,45 @@
+use crate::synthesizer::Synthesizer;
+
+impl Synthesizer {
+    /// Processes a SysEx message.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The data of the message. The leading 0xF0 and the trailing 0xF7 are optional.
+    ///
+    /// # Remarks
+    ///
+    /// The GM System On, GM2 System On, GS Reset and XG System On messages reset the channels
+    /// and the MPE zones, and release the sounding notes.
+    /// The Master Volume message sets the master volume, where the maximum value corresponds
+    /// to the default master volume.
+    /// Other messages are ignored.
+    pub fn process_sysex(&mut self, data: &[u8]) {
+        let data = data.strip_prefix(&[0xF0]).unwrap_or(data);
+        let data = data.strip_suffix(&[0xF7]).unwrap_or(data);
+
+        match data {
+            // GM System On, GM System Off and GM2 System On.
+            [0x7E, _, 0x09, 0x01..=0x03] => self.reset_channels(),
+            // GS Reset.
+            [0x41, _, 0x42, 0x12, 0x40, 0x00, 0x7F, 0x00, _] => self.reset_channels(),
+            // XG System On.
+            [0x43, 0x10..=0x1F, 0x4C, 0x00, 0x00, 0x7E, 0x00] => self.reset_channels(),
+            // Master Volume.
+            [0x7F, _, 0x04, 0x01, lsb, msb] => {
+                let value = ((*msb as i32 & 0x7F) << 7) | (*lsb as i32 & 0x7F);
+                self.set_master_volume(0.5_f32 * value as f32 / 16383_f32);
+            }
+            _ => (),
+        }
+    }
+
+    fn reset_channels(&mut self) {
+        self.note_off_all(false);
+
+        for channel in &mut self.channels {
+            channel.reset();
+        }
+        self.clear_mpe_zones();
+    }
+}
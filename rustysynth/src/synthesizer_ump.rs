@@ -0,0 +1,166 @@
+use crate::synthesizer::Synthesizer;
+
+impl Synthesizer {
+    /// Processes a Universal MIDI Packet (UMP).
+    ///
+    /// # Arguments
+    ///
+    /// * `packet` - The words of the packet. A 32-bit packet has one word and a 64-bit packet has two.
+    ///
+    /// # Remarks
+    ///
+    /// MIDI 1.0 channel voice messages (message type 0x2) and MIDI 2.0 channel voice messages
+    /// (message type 0x4) are supported. Other messages and incomplete packets are ignored.
+    /// If the synthesizer has more than 16 channels, the group selects the port,
+    /// so the channel `c` of the group `g` is the channel `16 * g + c` of the synthesizer,
+    /// and the messages to the groups beyond the number of channels are ignored.
+    /// Otherwise, the group is ignored, and all the groups address the same channels.
+    ///
+    /// MIDI 2.0 messages are processed at their higher resolution.
+    /// The 16-bit velocity and the 32-bit pitch bend are used as they are,
+    /// and the 32-bit controller and pressure values are mapped onto the 14-bit controller state.
+    /// The controllers with a 14-bit state, which are the modulation, the portamento time,
+    /// the data entry, the volume, the pan, the expression and the brightness,
+    /// keep the full 14-bit value, and the pedals are switched at the middle of the range.
+    /// The other controllers, including the LSB controllers (32 to 63),
+    /// are processed as MIDI 1.0 7-bit values.
+    /// The per-note pitch bend and the per-note pitch (registered per-note controller #3)
+    /// are applied through the tuning of the notes.
+    /// The assignable controllers (NRPN) are processed in the same way as the SoundFont 2.01 NRPN
+    /// given by MIDI 1.0 messages, without changing the parameter selected for the data entry.
+    pub fn process_ump(&mut self, packet: &[u32]) {
+        let word0 = match packet.first() {
+            Some(value) => *value,
+            None => return,
+        };
+
+        match word0 >> 28 {
+            0x2 => {
+                let channel = Synthesizer::get_ump_channel(word0, self.channels.len());
+                let command = ((word0 >> 16) & 0xF0) as i32;
+                let data1 = ((word0 >> 8) & 0x7F) as i32;
+                let data2 = (word0 & 0x7F) as i32;
+                self.process_midi_message(channel, command, data1, data2);
+            }
+            0x4 if packet.len() >= 2 => self.process_midi2_message(word0, packet[1]),
+            _ => (),
+        }
+    }
+
+    // Gets the channel of the synthesizer addressed by the group and the channel of the packet.
+    pub(crate) fn get_ump_channel(word0: u32, channel_count: usize) -> i32 {
+        let channel = ((word0 >> 16) & 0x0F) as i32;
+        if channel_count > Synthesizer::CHANNEL_COUNT {
+            let group = ((word0 >> 24) & 0x0F) as i32;
+            Synthesizer::CHANNEL_COUNT as i32 * group + channel
+        } else {
+            channel
+        }
+    }
+
+    fn process_midi2_message(&mut self, word0: u32, word1: u32) {
+        let channel = Synthesizer::get_ump_channel(word0, self.channels.len());
+        let index1 = ((word0 >> 8) & 0x7F) as i32;
+        let index2 = (word0 & 0xFF) as i32;
+
+        if channel >= self.channels.len() as i32 {
+            return;
+        }
+
+        match (word0 >> 20) & 0x0F {
+            // Registered per-note controller #3 is the absolute pitch in the 7.25 format.
+            0x0 if index2 == 3 => {
+                let pitch = word1 as f32 / (1 << 25) as f32;
+                self.note_tune(channel, index1, pitch - index1 as f32);
+            }
+            0x2 => {
+                // The data of the registered controller is given at once, unlike the data entry.
+                let channel_info = &mut self.channels[channel as usize];
+                channel_info.set_registered_parameter((index1 << 7) | index2, (word1 >> 18) as i32);
+
+                // The MPE configuration message only uses the MSB of the data.
+                if (index1 << 7) | index2 == 6 {
+                    self.configure_mpe_zone(channel, (word1 >> 25) as i32);
+                }
+            }
+            0x3 => {
+                // The assignable controller is processed as the NRPN with the 14-bit data,
+                // which controls the generators through the SoundFont 2.01 NRPN.
+                let channel_info = &mut self.channels[channel as usize];
+                channel_info.set_assignable_parameter(
+                    (index1 << 7) | (index2 & 0x7F),
+                    (word1 >> 18) as i32,
+                );
+            }
+            0x6 => {
+                let bend = Synthesizer::to_bipolar(word1);
+                let range = self.channels[channel as usize].get_pitch_bend_range();
+                self.note_tune(channel, index1, range * bend);
+            }
+            0x8 => self.note_off(channel, index1),
+            0x9 => {
+                // Unlike MIDI 1.0, the velocity 0 does not mean note-off.
+                let velocity = ((word1 >> 16) as i32).max(1 << 9);
+                self.start_note(channel, index1, velocity >> 9, velocity as f32 / 65535_f32);
+
+                // Attribute type #3 is the absolute pitch in the 7.9 format.
+                if index2 == 3 {
+                    let pitch = (word1 & 0xFFFF) as f32 / 512_f32;
+                    self.note_tune(channel, index1, pitch - index1 as f32);
+                }
+            }
+            0xA => {
+                let channel_info = &mut self.channels[channel as usize];
+                channel_info.set_key_pressure(index1, (word1 >> 18) as i32);
+            }
+            0xB => {
+                let channel_info = &mut self.channels[channel as usize];
+                let value = (word1 >> 18) as i32;
+                match index1 {
+                    0x01 => channel_info.set_modulation(value),
+                    0x05 => channel_info.set_portamento_time(value),
+                    0x06 => {
+                        channel_info.data_entry(value);
+
+                        // The MPE configuration message only uses the MSB of the data.
+                        if channel_info.get_selected_rpn() == Some(6) {
+                            self.configure_mpe_zone(channel, value >> 7);
+                        }
+                    }
+                    0x07 => channel_info.set_volume(value),
+                    0x0A => channel_info.set_pan(value),
+                    0x0B => channel_info.set_expression(value),
+                    // The pedals are switches, which are on in the upper half of the range.
+                    0x40 => channel_info.set_hold_pedal(value >> 7),
+                    0x41 => channel_info.set_portamento(value >> 7),
+                    0x42 => self.set_sostenuto_pedal(channel, value >> 7),
+                    0x43 => channel_info.set_soft_pedal(value >> 7),
+                    0x4A => channel_info.set_brightness(value),
+                    _ => self.process_midi_message(channel, 0xB0, index1, (word1 >> 25) as i32),
+                }
+            }
+            0xC => {
+                let channel_info = &mut self.channels[channel as usize];
+                if (word0 & 1) != 0 {
+                    channel_info.set_bank(((word1 >> 8) & 0x7F) as i32);
+                }
+                channel_info.set_patch(((word1 >> 24) & 0x7F) as i32);
+            }
+            0xD => {
+                let channel_info = &mut self.channels[channel as usize];
+                channel_info.set_channel_pressure((word1 >> 18) as i32);
+            }
+            0xE => {
+                let bend = Synthesizer::to_bipolar(word1);
+                self.channels[channel as usize].set_pitch_bend_normalized(bend);
+            }
+            // The per-note controllers are reset if the S flag is set.
+            0xF if (word0 & 1) != 0 => self.note_tune(channel, index1, 0_f32),
+            _ => (),
+        }
+    }
+
+    fn to_bipolar(value: u32) -> f32 {
+        ((value as f64 - 2147483648.0) / 2147483648.0) as f32
+    }
+}
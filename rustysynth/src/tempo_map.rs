@@ -0,0 +1,435 @@
+#![allow(dead_code)]
+
+use crate::midifile::Message;
+use crate::MidiFileError;
+
+/// Represents the tempo map of a MIDI file.
+///
+/// The tempo map holds the tempo changes and the time signatures of the MIDI file,
+/// and provides conversions between ticks, seconds and musical positions.
+#[derive(Clone)]
+#[non_exhaustive]
+pub struct TempoMap {
+    pub(crate) resolution: i32,
+    pub(crate) frames_per_second: Option<f64>,
+    pub(crate) tempo_changes: Vec<TempoChange>,
+    pub(crate) time_signatures: Vec<TimeSignature>,
+}
+
+/// Represents a tempo change in the tempo map.
+#[derive(Clone, Copy)]
+#[non_exhaustive]
+pub struct TempoChange {
+    pub(crate) tick: i32,
+    pub(crate) time: f64,
+    pub(crate) tempo: f64,
+    pub(crate) tick_length: f64,
+}
+
+/// Represents a time signature in the tempo map.
+#[derive(Clone, Copy)]
+#[non_exhaustive]
+pub struct TimeSignature {
+    pub(crate) tick: i32,
+    pub(crate) bar: i32,
+    pub(crate) numerator: i32,
+    pub(crate) denominator: i32,
+}
+
+impl TempoMap {
+    const DEFAULT_TEMPO: f64 = 120.0;
+
+    pub(crate) fn new(
+        division: i16,
+        messages: &[Message],
+        ticks: &[i32],
+    ) -> Result<Self, MidiFileError> {
+        if division == 0 {
+            return Err(MidiFileError::InvalidTimeDivision(division));
+        }
+
+        let mut tempo_map = if division > 0 {
+            let resolution = division as i32;
+            Self {
+                resolution,
+                frames_per_second: None,
+                tempo_changes: vec![TempoChange::new(
+                    0,
+                    0.0,
+                    TempoMap::DEFAULT_TEMPO,
+                    resolution,
+                )],
+                time_signatures: Vec::new(),
+            }
+        } else {
+            // If the MSB of the division is set, the upper byte represents the negative SMPTE format
+            // and the lower byte represents the number of ticks per frame.
+            let frames_per_second = match -((division >> 8) as i8) {
+                24 => 24.0,
+                25 => 25.0,
+                29 => 30000.0 / 1001.0, // 29.97 drop-frame
+                30 => 30.0,
+                _ => return Err(MidiFileError::InvalidTimeDivision(division)),
+            };
+
+            let ticks_per_frame = (division & 0xFF) as f64;
+            if ticks_per_frame == 0.0 {
+                return Err(MidiFileError::InvalidTimeDivision(division));
+            }
+
+            // The SMPTE time division has an absolute tick length, which is not affected by tempo changes.
+            // For the musical positions, a quarter note is regarded as a half second (120 BPM).
+            let tick_length = 1.0 / (frames_per_second * ticks_per_frame);
+            Self {
+                resolution: (0.5 / tick_length).round() as i32,
+                frames_per_second: Some(frames_per_second),
+                tempo_changes: vec![TempoChange {
+                    tick: 0,
+                    time: 0.0,
+                    tempo: TempoMap::DEFAULT_TEMPO,
+                    tick_length,
+                }],
+                time_signatures: Vec::new(),
+            }
+        };
+
+        tempo_map.time_signatures.push(TimeSignature {
+            tick: 0,
+            bar: 0,
+            numerator: 4,
+            denominator: 4,
+        });
+
+        for (message, tick) in messages.iter().zip(ticks.iter()) {
+            match message.get_message_type() {
+                Message::TEMPO_CHANGE if tempo_map.frames_per_second.is_none() => {
+                    tempo_map.add_tempo_change(*tick, message.get_tempo())
+                }
+                Message::TIME_SIGNATURE => tempo_map.add_time_signature(
+                    *tick,
+                    message.command as i32,
+                    1 << message.data1.min(6),
+                ),
+                _ => (),
+            }
+        }
+
+        Ok(tempo_map)
+    }
+
+    fn add_tempo_change(&mut self, tick: i32, tempo: f64) {
+        let last = self.tempo_changes.last_mut().unwrap();
+        if tick == last.tick {
+            *last = TempoChange::new(last.tick, last.time, tempo, self.resolution);
+        } else {
+            let time = last.time + last.tick_length * (tick - last.tick) as f64;
+            self.tempo_changes
+                .push(TempoChange::new(tick, time, tempo, self.resolution));
+        }
+    }
+
+    fn add_time_signature(&mut self, tick: i32, numerator: i32, denominator: i32) {
+        if numerator <= 0 {
+            return;
+        }
+
+        let last = *self.time_signatures.last().unwrap();
+
+        // A time signature change is expected to occur at a bar line.
+        // If not, the incomplete bar is counted as a whole bar.
+        let ticks_per_bar = last.get_ticks_per_bar(self.resolution);
+        let bar = last.bar + ((tick - last.tick) as f64 / ticks_per_bar).ceil() as i32;
+
+        let time_signature = TimeSignature {
+            tick,
+            bar,
+            numerator,
+            denominator,
+        };
+
+        if tick == last.tick {
+            *self.time_signatures.last_mut().unwrap() = TimeSignature {
+                bar: last.bar,
+                ..time_signature
+            };
+        } else {
+            self.time_signatures.push(time_signature);
+        }
+    }
+
+    pub(crate) fn scale(&self, factor: f64) -> Self {
+        let mut tempo_map = self.clone();
+        for change in tempo_map.tempo_changes.iter_mut() {
+            change.time /= factor;
+            change.tempo *= factor;
+            change.tick_length /= factor;
+        }
+        tempo_map
+    }
+
+    pub(crate) fn slice(&self, start_tick: i32) -> Self {
+        let first = self.find_tempo_change(start_tick as f64);
+        let mut tempo_map = Self {
+            resolution: self.resolution,
+            frames_per_second: self.frames_per_second,
+            tempo_changes: vec![TempoChange {
+                tick: 0,
+                time: 0.0,
+                ..*first
+            }],
+            time_signatures: vec![TimeSignature {
+                tick: 0,
+                bar: 0,
+                ..*self.find_time_signature(start_tick as f64)
+            }],
+        };
+
+        for change in self.tempo_changes.iter() {
+            if change.tick > start_tick {
+                tempo_map.add_tempo_change(change.tick - start_tick, change.tempo);
+            }
+        }
+
+        for signature in self.time_signatures.iter() {
+            if signature.tick > start_tick {
+                tempo_map.add_time_signature(
+                    signature.tick - start_tick,
+                    signature.numerator,
+                    signature.denominator,
+                );
+            }
+        }
+
+        tempo_map
+    }
+
+    fn find_tempo_change(&self, ticks: f64) -> &TempoChange {
+        let index = self
+            .tempo_changes
+            .partition_point(|change| change.tick as f64 <= ticks);
+        &self.tempo_changes[index.saturating_sub(1)]
+    }
+
+    fn find_time_signature(&self, ticks: f64) -> &TimeSignature {
+        let index = self
+            .time_signatures
+            .partition_point(|signature| signature.tick as f64 <= ticks);
+        &self.time_signatures[index.saturating_sub(1)]
+    }
+
+    // Gets the position of the first beat at or after the position in ticks.
+    pub(crate) fn get_next_beat(&self, ticks: f64) -> f64 {
+        self.get_next_division(ticks, |signature, resolution| {
+            signature.get_ticks_per_beat(resolution)
+        })
+    }
+
+    // Gets the position of the first bar line at or after the position in ticks.
+    pub(crate) fn get_next_bar(&self, ticks: f64) -> f64 {
+        self.get_next_division(ticks, |signature, resolution| {
+            signature.get_ticks_per_bar(resolution)
+        })
+    }
+
+    fn get_next_division<F: Fn(&TimeSignature, i32) -> f64>(&self, ticks: f64, f: F) -> f64 {
+        let index = self
+            .time_signatures
+            .partition_point(|signature| signature.tick as f64 <= ticks);
+        let signature = &self.time_signatures[index.saturating_sub(1)];
+        let length = f(signature, self.resolution);
+
+        // A small margin is subtracted to absorb the rounding error of the conversion.
+        let position = (ticks - signature.tick as f64).max(0.0) - 1.0E-6;
+        let next = signature.tick as f64 + (position / length).ceil().max(0.0) * length;
+
+        // The next time signature always starts a new bar.
+        match self.time_signatures.get(index) {
+            Some(value) if (value.tick as f64) < next => value.tick as f64,
+            _ => next,
+        }
+    }
+
+    /// Converts a position in ticks to seconds.
+    ///
+    /// # Arguments
+    ///
+    /// * `ticks` - The position in ticks.
+    pub fn ticks_to_seconds(&self, ticks: f64) -> f64 {
+        let change = self.find_tempo_change(ticks);
+        change.time + change.tick_length * (ticks - change.tick as f64)
+    }
+
+    /// Converts a position in seconds to ticks.
+    ///
+    /// # Arguments
+    ///
+    /// * `seconds` - The position in seconds.
+    ///
+    /// # Remarks
+    ///
+    /// The result may have a fractional part if the position is between two ticks.
+    pub fn seconds_to_ticks(&self, seconds: f64) -> f64 {
+        let index = self
+            .tempo_changes
+            .partition_point(|change| change.time <= seconds);
+        let change = &self.tempo_changes[index.saturating_sub(1)];
+        change.tick as f64 + (seconds - change.time) / change.tick_length
+    }
+
+    /// Converts a position in ticks to a musical position.
+    ///
+    /// # Arguments
+    ///
+    /// * `ticks` - The position in ticks.
+    ///
+    /// # Remarks
+    ///
+    /// The result is a tuple of the bar, the beat and the tick within the beat.
+    /// The bar and the beat start from 1, and the tick starts from 0.
+    pub fn ticks_to_bar_beat_tick(&self, ticks: f64) -> (i32, i32, i32) {
+        let signature = self.find_time_signature(ticks);
+        let ticks_per_beat = signature.get_ticks_per_beat(self.resolution);
+        let ticks_per_bar = signature.get_ticks_per_bar(self.resolution);
+
+        // A small margin is added to absorb the rounding error of the conversion.
+        let position = (ticks - signature.tick as f64).max(0.0) + 1.0E-6;
+        let bars = (position / ticks_per_bar).floor();
+        let position = position - bars * ticks_per_bar;
+        let beats = (position / ticks_per_beat).floor();
+        let position = position - beats * ticks_per_beat;
+
+        (
+            signature.bar + bars as i32 + 1,
+            beats as i32 + 1,
+            position.floor() as i32,
+        )
+    }
+
+    /// Converts a position in seconds to a musical position.
+    ///
+    /// # Arguments
+    ///
+    /// * `seconds` - The position in seconds.
+    ///
+    /// # Remarks
+    ///
+    /// The result is a tuple of the bar, the beat and the tick within the beat.
+    /// The bar and the beat start from 1, and the tick starts from 0.
+    pub fn seconds_to_bar_beat_tick(&self, seconds: f64) -> (i32, i32, i32) {
+        self.ticks_to_bar_beat_tick(self.seconds_to_ticks(seconds))
+    }
+
+    /// Gets the tempo in beats per minute at the specified position.
+    ///
+    /// # Arguments
+    ///
+    /// * `ticks` - The position in ticks.
+    pub fn get_tempo(&self, ticks: f64) -> f64 {
+        self.find_tempo_change(ticks).tempo
+    }
+
+    /// Gets the time signature at the specified position.
+    ///
+    /// # Arguments
+    ///
+    /// * `ticks` - The position in ticks.
+    pub fn get_time_signature(&self, ticks: f64) -> &TimeSignature {
+        self.find_time_signature(ticks)
+    }
+
+    /// Gets the number of ticks per quarter note.
+    ///
+    /// # Remarks
+    ///
+    /// If the MIDI file uses the SMPTE time division, a quarter note is regarded as a half second.
+    pub fn get_resolution(&self) -> i32 {
+        self.resolution
+    }
+
+    /// Gets the frame rate of the SMPTE time division.
+    ///
+    /// # Remarks
+    ///
+    /// This value will be `None` if the MIDI file uses the ticks-per-quarter-note time division.
+    pub fn get_frames_per_second(&self) -> Option<f64> {
+        self.frames_per_second
+    }
+
+    /// Gets the tempo changes.
+    ///
+    /// # Remarks
+    ///
+    /// The first element always represents the initial tempo at tick 0.
+    pub fn get_tempo_changes(&self) -> &[TempoChange] {
+        &self.tempo_changes[..]
+    }
+
+    /// Gets the time signatures.
+    ///
+    /// # Remarks
+    ///
+    /// The first element always represents the initial time signature at tick 0.
+    pub fn get_time_signatures(&self) -> &[TimeSignature] {
+        &self.time_signatures[..]
+    }
+}
+
+impl TempoChange {
+    fn new(tick: i32, time: f64, tempo: f64, resolution: i32) -> Self {
+        Self {
+            tick,
+            time,
+            tempo,
+            tick_length: 60.0 / (resolution as f64 * tempo),
+        }
+    }
+
+    /// Gets the position of the tempo change in ticks.
+    pub fn get_tick(&self) -> i32 {
+        self.tick
+    }
+
+    /// Gets the position of the tempo change in seconds.
+    pub fn get_time(&self) -> f64 {
+        self.time
+    }
+
+    /// Gets the tempo in beats per minute.
+    pub fn get_tempo(&self) -> f64 {
+        self.tempo
+    }
+}
+
+impl TimeSignature {
+    fn get_ticks_per_beat(&self, resolution: i32) -> f64 {
+        4.0 * resolution as f64 / self.denominator as f64
+    }
+
+    fn get_ticks_per_bar(&self, resolution: i32) -> f64 {
+        self.numerator as f64 * self.get_ticks_per_beat(resolution)
+    }
+
+    /// Gets the position of the time signature in ticks.
+    pub fn get_tick(&self) -> i32 {
+        self.tick
+    }
+
+    /// Gets the bar where the time signature starts.
+    ///
+    /// # Remarks
+    ///
+    /// The bar starts from 1.
+    pub fn get_bar(&self) -> i32 {
+        self.bar + 1
+    }
+
+    /// Gets the numerator of the time signature.
+    pub fn get_numerator(&self) -> i32 {
+        self.numerator
+    }
+
+    /// Gets the denominator of the time signature.
+    pub fn get_denominator(&self) -> i32 {
+        self.denominator
+    }
+}
@@ -4,9 +4,12 @@ use std::f32::consts;
 
 use crate::bi_quad_filter::BiQuadFilter;
 use crate::channel::Channel;
+use crate::generator_type::GeneratorType;
 use crate::lfo::Lfo;
 use crate::modulation_envelope::ModulationEnvelope;
+use crate::modulator::PressureModulator;
 use crate::oscillator::Oscillator;
+use crate::pressure_mapping::PressureMapping;
 use crate::region_ex::RegionEx;
 use crate::region_pair::RegionPair;
 use crate::soundfont_math::SoundFontMath;
@@ -45,19 +48,31 @@ pub(crate) struct Voice {
 
     pub(crate) exclusive_class: i32,
     pub(crate) channel: i32,
+    // The manager channel if the voice is in an MPE zone, or otherwise the same as the channel.
+    pub(crate) zone_channel: i32,
     pub(crate) key: i32,
     pub(crate) velocity: i32,
 
     note_gain: f32,
 
+    // The gain to fade the voice in or out, which changes by the step every sample.
+    fade_gain: f32,
+    fade_step: f32,
+
     cutoff: f32,
     resonance: f32,
+    filter_q: f32,
 
     tuning_to_pitch: f32,
+    generator_pitch: f32,
     vib_lfo_to_pitch: f32,
     mod_lfo_to_pitch: f32,
     mod_env_to_pitch: f32,
 
+    // The pitch relative to the key during the portamento, which changes by the step every sample.
+    glide_pitch: f32,
+    glide_step: f32,
+
     mod_lfo_to_cutoff: i32,
     mod_env_to_cutoff: i32,
     dynamic_cutoff: bool,
@@ -73,12 +88,21 @@ pub(crate) struct Voice {
     // This is used to smooth out the cutoff frequency.
     smoothed_cutoff: f32,
 
+    // The generator offsets of the channel and the pressure applied to the voice.
+    generator_offsets: [i16; GeneratorType::COUNT],
+    pressure_modulators: Vec<PressureModulator>,
+
+    // The voice is held by the sostenuto pedal if the key was down when the pedal was pressed.
+    sostenuto: bool,
+
     voice_state: i32,
     pub(crate) voice_length: usize,
     min_voice_length: usize,
 }
 
 impl Voice {
+    const SOFT_PEDAL_CUTOFF: f32 = -600_f32;
+
     pub(crate) fn new(settings: &SynthesizerSettings) -> Self {
         Self {
             sample_rate: settings.sample_rate,
@@ -100,15 +124,22 @@ impl Voice {
             current_chorus_send: 0_f32,
             exclusive_class: 0,
             channel: 0,
+            zone_channel: 0,
             key: 0,
             velocity: 0,
             note_gain: 0_f32,
+            fade_gain: 1_f32,
+            fade_step: 0_f32,
             cutoff: 0_f32,
             resonance: 0_f32,
+            filter_q: 0_f32,
             tuning_to_pitch: 0_f32,
+            generator_pitch: 0_f32,
             vib_lfo_to_pitch: 0_f32,
             mod_lfo_to_pitch: 0_f32,
             mod_env_to_pitch: 0_f32,
+            glide_pitch: 0_f32,
+            glide_step: 0_f32,
             mod_lfo_to_cutoff: 0,
             mod_env_to_cutoff: 0,
             dynamic_cutoff: false,
@@ -118,6 +149,9 @@ impl Voice {
             instrument_reverb: 0_f32,
             instrument_chorus: 0_f32,
             smoothed_cutoff: 0_f32,
+            generator_offsets: [0; GeneratorType::COUNT],
+            pressure_modulators: Vec::with_capacity(PressureModulator::MAXIMUM_COUNT),
+            sostenuto: false,
             voice_state: 0,
             voice_length: 0,
             min_voice_length: (settings.sample_rate / 500) as usize,
@@ -128,7 +162,15 @@ impl Voice {
         self.tuning_to_pitch = tuning;
     }
 
-    pub(crate) fn start(&mut self, region: &RegionPair, channel: i32, key: i32, velocity: i32) {
+    pub(crate) fn start(
+        &mut self,
+        region: &RegionPair,
+        channel: i32,
+        key: i32,
+        velocity: i32,
+        normalized_velocity: f32,
+        pressure_mapping: &PressureMapping,
+    ) {
         self.exclusive_class = region.get_exclusive_class();
         self.channel = channel;
         self.key = key;
@@ -139,7 +181,7 @@ impl Voice {
             // I'm not sure why, but this indeed improves the loudness variability.
             let sample_attenuation = 0.4_f32 * region.get_initial_attenuation();
             let filter_attenuation = 0.5_f32 * region.get_initial_filter_q();
-            let decibels = 2_f32 * SoundFontMath::linear_to_decibels(velocity as f32 / 127_f32)
+            let decibels = 2_f32 * SoundFontMath::linear_to_decibels(normalized_velocity)
                 - sample_attenuation
                 - filter_attenuation;
             self.note_gain = SoundFontMath::decibels_to_linear(decibels);
@@ -147,12 +189,31 @@ impl Voice {
             self.note_gain = 0_f32;
         }
 
+        self.fade_gain = 1_f32;
+        self.fade_step = 0_f32;
+
         self.cutoff = region.get_initial_filter_cutoff_frequency();
-        self.resonance = SoundFontMath::decibels_to_linear(region.get_initial_filter_q());
+        self.filter_q = region.get_initial_filter_q();
+        self.resonance = SoundFontMath::decibels_to_linear(self.filter_q);
+        self.generator_pitch = 0_f32;
+        self.generator_offsets = *region.offsets;
+
+        // The modulators in the SoundFont take precedence over the default mapping.
+        self.pressure_modulators.clear();
+        let modulators = region.instrument.modulators.iter();
+        let modulators = modulators.chain(region.preset.modulators.iter());
+        let modulators = modulators.filter_map(PressureModulator::from_modulator);
+        self.pressure_modulators
+            .extend(modulators.take(PressureModulator::MAXIMUM_COUNT));
+        if self.pressure_modulators.is_empty() {
+            PressureModulator::from_mapping(pressure_mapping, &mut self.pressure_modulators);
+        }
 
         self.vib_lfo_to_pitch = 0.01_f32 * region.get_vibrato_lfo_to_pitch() as f32;
         self.mod_lfo_to_pitch = 0.01_f32 * region.get_modulation_lfo_to_pitch() as f32;
         self.mod_env_to_pitch = 0.01_f32 * region.get_modulation_envelope_to_pitch() as f32;
+        self.glide_pitch = 0_f32;
+        self.glide_step = 0_f32;
 
         self.mod_lfo_to_cutoff = region.get_modulation_lfo_to_filter_cutoff_frequency();
         self.mod_env_to_cutoff = region.get_modulation_envelope_to_filter_cutoff_frequency();
@@ -175,10 +236,46 @@ impl Voice {
 
         self.smoothed_cutoff = self.cutoff;
 
+        self.sostenuto = false;
         self.voice_state = VoiceState::PLAYING;
         self.voice_length = 0;
     }
 
+    // The soft pedal lowers the filter cutoff frequency of the new notes.
+    pub(crate) fn soften(&mut self) {
+        self.cutoff *= SoundFontMath::cents_to_multiplying_factor(Voice::SOFT_PEDAL_CUTOFF);
+        self.smoothed_cutoff = self.cutoff;
+        self.filter.set_low_pass_filter(self.cutoff, self.resonance);
+    }
+
+    // The pitch glides from the source to the key over the length.
+    pub(crate) fn glide(&mut self, source: f32, length: usize) {
+        if length == 0 {
+            self.glide_pitch = 0_f32;
+            self.glide_step = 0_f32;
+        } else {
+            self.glide_pitch = source - self.key as f32;
+            self.glide_step = -self.glide_pitch / length as f32;
+        }
+    }
+
+    // Changes the key without restarting the envelopes.
+    // The glide starts from the current pitch, including the unfinished glide.
+    pub(crate) fn legato(&mut self, key: i32, glide_length: usize) {
+        let source = self.key as f32 + self.glide_pitch;
+        self.key = key;
+        self.glide(source, glide_length);
+    }
+
+    // Called when the sostenuto pedal is pressed.
+    pub(crate) fn catch_sostenuto(&mut self) {
+        self.sostenuto = self.voice_state == VoiceState::PLAYING;
+    }
+
+    pub(crate) fn is_playing(&self) -> bool {
+        self.voice_state == VoiceState::PLAYING && self.note_gain >= SoundFontMath::NON_AUDIBLE
+    }
+
     pub(crate) fn end(&mut self) {
         if self.voice_state == VoiceState::PLAYING {
             self.voice_state = VoiceState::RELEASE_REQUESTED;
@@ -189,41 +286,93 @@ impl Voice {
         self.note_gain = 0_f32;
     }
 
-    pub(crate) fn process(&mut self, data: &[i16], channels: &[Channel]) -> bool {
+    // The voice ends when the fade-out completes.
+    pub(crate) fn fade_out(&mut self, length: usize) {
+        self.fade_step = -self.fade_gain / length as f32;
+    }
+
+    // The fade-in starts from the position in the fade length, so that the voices
+    // started during a fade-in reach the full gain at the same time.
+    pub(crate) fn fade_in(&mut self, length: usize, position: usize) {
+        self.fade_gain = position as f32 / length as f32;
+        self.fade_step = 1_f32 / length as f32;
+    }
+
+    pub(crate) fn process(
+        &mut self,
+        data: &[i16],
+        channels: &[Channel],
+        block_length: usize,
+    ) -> bool {
         if self.note_gain < SoundFontMath::NON_AUDIBLE {
             return false;
         }
 
+        // The notes in an MPE zone take the controllers of the manager channel,
+        // except for the per-note controllers of their own channel.
         let channel_info = &channels[self.channel as usize];
+        let zone_info = &channels[self.zone_channel as usize];
+        let has_zone = self.zone_channel != self.channel;
+
+        // The notes of a frozen channel are kept as they are and make no sound.
+        if channel_info.is_frozen() {
+            self.previous_mix_gain_left = 0_f32;
+            self.previous_mix_gain_right = 0_f32;
+            self.current_mix_gain_left = 0_f32;
+            self.current_mix_gain_right = 0_f32;
+            return true;
+        }
 
-        self.release_if_necessary(channel_info);
+        self.release_if_necessary(zone_info);
+
+        let offsets = self.get_generator_offsets(channel_info, zone_info);
+        if offsets != self.generator_offsets {
+            self.apply_generator_offsets(&offsets);
+        }
 
-        if !self.vol_env.process(self.block_size) {
+        if !self.vol_env.process(block_length) {
             return false;
         }
 
-        self.mod_env.process(self.block_size);
-        self.vib_lfo.process();
-        self.mod_lfo.process();
+        self.mod_env.process(block_length);
+        self.vib_lfo.process(block_length);
+        self.mod_lfo.process(block_length);
 
         // You can set vibratto in soundfonts (part of the standard).
-        let vib_pitch_change = (0.01_f32 * channel_info.get_modulation() + self.vib_lfo_to_pitch)
+        let vib_pitch_change = (0.01_f32 * zone_info.get_modulation() + self.vib_lfo_to_pitch)
             * self.vib_lfo.get_value();
         // This is also part of the soundfont standard.
         let mod_pitch_change = self.mod_lfo_to_pitch * self.mod_lfo.get_value()
             + self.mod_env_to_pitch * self.mod_env.get_value();
         // This comes from MIDI input.
-        let channel_pitch_change = channel_info.get_tune() + channel_info.get_pitch_bend();
+        let mut channel_pitch_change = zone_info.get_tune() + channel_info.get_pitch_bend();
+        if has_zone {
+            channel_pitch_change += zone_info.get_pitch_bend();
+        }
         // Output pitch.
         let pitch = self.key as f32
             + vib_pitch_change
             + mod_pitch_change
             + channel_pitch_change
-            + self.tuning_to_pitch;
-        if !self.oscillator.process(data, &mut self.block[..], pitch) {
+            + self.tuning_to_pitch
+            + self.generator_pitch
+            + self.glide_pitch;
+        if !self
+            .oscillator
+            .process(data, &mut self.block[..block_length], pitch)
+        {
             return false;
         }
 
+        if self.glide_step != 0_f32 {
+            self.glide_pitch += self.glide_step * block_length as f32;
+            // The glide ends when the pitch reaches the key.
+            if self.glide_pitch * self.glide_step >= 0_f32 {
+                self.glide_pitch = 0_f32;
+                self.glide_step = 0_f32;
+            }
+        }
+
         if self.dynamic_cutoff {
             let cents = self.mod_lfo_to_cutoff as f32 * self.mod_lfo.get_value()
                 + self.mod_env_to_cutoff as f32 * self.mod_env.get_value();
@@ -238,7 +387,7 @@ impl Voice {
             self.filter
                 .set_low_pass_filter(self.smoothed_cutoff, self.resonance);
         }
-        self.filter.process(&mut self.block[..]);
+        self.filter.process(&mut self.block[..block_length]);
 
         self.previous_mix_gain_left = self.current_mix_gain_left;
         self.previous_mix_gain_right = self.current_mix_gain_right;
@@ -246,17 +395,30 @@ impl Voice {
         self.previous_chorus_send = self.current_chorus_send;
 
         // According to the GM spec, the following value should be squared.
-        let ve = channel_info.get_volume() * channel_info.get_expression();
-        let channel_gain = ve * ve;
+        let ve = zone_info.get_volume() * zone_info.get_expression();
+        let channel_gain = ve * ve * channel_info.output_gain * channel_info.get_freeze_gain();
+
+        if self.fade_step != 0_f32 {
+            let fade_gain = self.fade_gain + self.fade_step * block_length as f32;
+            self.fade_gain = SoundFontMath::clamp(fade_gain, 0_f32, 1_f32);
+            if self.fade_gain == 1_f32 {
+                self.fade_step = 0_f32;
+            }
+        }
 
-        let mut mix_gain = self.note_gain * channel_gain * self.vol_env.get_value();
+        let mut mix_gain =
+            self.note_gain * self.fade_gain * channel_gain * self.vol_env.get_value();
         if self.dynamic_volume {
             let decibels = self.mod_lfo_to_volume * self.mod_lfo.get_value();
             mix_gain *= SoundFontMath::decibels_to_linear(decibels);
         }
 
-        let angle =
-            (consts::PI / 200_f32) * (channel_info.get_pan() + self.instrument_pan + 50_f32);
+        // The voice stops after the block in which the fade-out completes.
+        if self.fade_gain == 0_f32 {
+            self.kill();
+        }
+
+        let angle = (consts::PI / 200_f32) * (zone_info.get_pan() + self.instrument_pan + 50_f32);
         if angle <= 0_f32 {
             self.current_mix_gain_left = mix_gain;
             self.current_mix_gain_right = 0_f32;
@@ -269,12 +431,12 @@ impl Voice {
         }
 
         self.current_reverb_send = SoundFontMath::clamp(
-            channel_info.get_reverb_send() + self.instrument_reverb,
+            zone_info.get_reverb_send() + self.instrument_reverb,
             0_f32,
             1_f32,
         );
         self.current_chorus_send = SoundFontMath::clamp(
-            channel_info.get_chorus_send() + self.instrument_chorus,
+            zone_info.get_chorus_send() + self.instrument_chorus,
             0_f32,
             1_f32,
         );
@@ -286,17 +448,99 @@ impl Voice {
             self.previous_chorus_send = self.current_chorus_send;
         }
 
-        self.voice_length += self.block_size;
+        self.voice_length += block_length;
 
         true
     }
 
+    // Adds the changes by the brightness and the pressure to the generator offsets of the channel.
+    fn get_generator_offsets(
+        &self,
+        channel_info: &Channel,
+        zone_info: &Channel,
+    ) -> [i16; GeneratorType::COUNT] {
+        let mut offsets = *zone_info.get_generator_offsets();
+
+        let mut brightness = channel_info.get_brightness();
+        if self.zone_channel != self.channel {
+            brightness += zone_info.get_brightness();
+        }
+        if brightness != 0 {
+            let offset = &mut offsets[GeneratorType::INITIAL_FILTER_CUTOFF_FREQUENCY as usize];
+            *offset = (*offset as i32 + brightness).clamp(i16::MIN as i32, i16::MAX as i32) as i16;
+        }
+
+        if self.pressure_modulators.is_empty() {
+            return offsets;
+        }
+
+        let channel_pressure = channel_info.get_channel_pressure();
+        let key_pressure = channel_info.get_key_pressure(self.key);
+        for modulator in &self.pressure_modulators {
+            let value = modulator.get_value(channel_pressure, key_pressure);
+            let offset = &mut offsets[modulator.get_destination()];
+            *offset = (*offset as f32 + value).clamp(i16::MIN as f32, i16::MAX as f32) as i16;
+        }
+
+        offsets
+    }
+
+    // Applies the changes of the generator offsets to the sounding voice.
+    // The envelopes, the LFOs and the scale tuning are determined at the note-on,
+    // so their changes only affect the subsequent notes.
+    fn apply_generator_offsets(&mut self, offsets: &[i16; GeneratorType::COUNT]) {
+        let mut delta = [0_f32; GeneratorType::COUNT];
+        for i in 0..GeneratorType::COUNT {
+            delta[i] = (offsets[i] as i32 - self.generator_offsets[i] as i32) as f32;
+        }
+        self.generator_offsets = *offsets;
+
+        let delta = |generator_type: u16| delta[generator_type as usize];
+
+        self.mod_lfo_to_pitch += 0.01_f32 * delta(GeneratorType::MODULATION_LFO_TO_PITCH);
+        self.vib_lfo_to_pitch += 0.01_f32 * delta(GeneratorType::VIBRATO_LFO_TO_PITCH);
+        self.mod_env_to_pitch += 0.01_f32 * delta(GeneratorType::MODULATION_ENVELOPE_TO_PITCH);
+        self.generator_pitch +=
+            delta(GeneratorType::COARSE_TUNE) + 0.01_f32 * delta(GeneratorType::FINE_TUNE);
+
+        self.cutoff *= SoundFontMath::cents_to_multiplying_factor(delta(
+            GeneratorType::INITIAL_FILTER_CUTOFF_FREQUENCY,
+        ));
+        self.filter_q += 0.1_f32 * delta(GeneratorType::INITIAL_FILTER_Q);
+        self.resonance = SoundFontMath::decibels_to_linear(self.filter_q);
+
+        self.mod_lfo_to_cutoff +=
+            delta(GeneratorType::MODULATION_LFO_TO_FILTER_CUTOFF_FREQUENCY) as i32;
+        self.mod_env_to_cutoff +=
+            delta(GeneratorType::MODULATION_ENVELOPE_TO_FILTER_CUTOFF_FREQUENCY) as i32;
+        self.dynamic_cutoff = self.mod_lfo_to_cutoff != 0 || self.mod_env_to_cutoff != 0;
+        if !self.dynamic_cutoff {
+            self.smoothed_cutoff = self.cutoff;
+            self.filter.set_low_pass_filter(self.cutoff, self.resonance);
+        }
+
+        self.mod_lfo_to_volume += 0.1_f32 * delta(GeneratorType::MODULATION_LFO_TO_VOLUME);
+        self.dynamic_volume = self.mod_lfo_to_volume > 0.05_f32;
+
+        // The attenuations are reduced in the same way as the note-on.
+        let decibels = -0.04_f32 * delta(GeneratorType::INITIAL_ATTENUATION)
+            - 0.05_f32 * delta(GeneratorType::INITIAL_FILTER_Q);
+        self.note_gain *= SoundFontMath::decibels_to_linear(decibels);
+
+        let pan = self.instrument_pan + 0.1_f32 * delta(GeneratorType::PAN);
+        self.instrument_pan = SoundFontMath::clamp(pan, -50_f32, 50_f32);
+        self.instrument_reverb += 0.001_f32 * delta(GeneratorType::REVERB_EFFECTS_SEND);
+        self.instrument_chorus += 0.001_f32 * delta(GeneratorType::CHORUS_EFFECTS_SEND);
+    }
+
     fn release_if_necessary(&mut self, channel_info: &Channel) {
         if self.voice_length < self.min_voice_length {
             return;
         }
 
-        if self.voice_state == VoiceState::RELEASE_REQUESTED && !channel_info.get_hold_pedal() {
+        let held =
+            channel_info.get_hold_pedal() || (self.sostenuto && channel_info.get_sostenuto_pedal());
+        if self.voice_state == VoiceState::RELEASE_REQUESTED && !held {
             self.vol_env.release();
             self.mod_env.release();
             self.oscillator.release();
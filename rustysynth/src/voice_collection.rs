@@ -69,7 +69,7 @@ impl VoiceCollection {
         Some(&mut self.voices[candidate])
     }
 
-    pub(crate) fn process(&mut self, data: &[i16], channels: &[Channel]) {
+    pub(crate) fn process(&mut self, data: &[i16], channels: &[Channel], block_length: usize) {
         let mut i: usize = 0;
 
         loop {
@@ -77,7 +77,7 @@ impl VoiceCollection {
                 return;
             }
 
-            if self.voices[i].process(data, channels) {
+            if self.voices[i].process(data, channels, block_length) {
                 i += 1;
             } else {
                 self.active_voice_count -= 1;
@@ -1,34 +1,45 @@
 use crate::error::ParseError;
 use crate::generator::Generator;
+use crate::modulator::Modulator;
 use crate::zone_info::ZoneInfo;
 
 #[non_exhaustive]
 pub(crate) struct Zone {
     pub(crate) generators: Vec<Generator>,
+    pub(crate) modulators: Vec<Modulator>,
 }
 
 impl Zone {
     pub(crate) fn empty() -> Self {
         Self {
             generators: Vec::new(),
+            modulators: Vec::new(),
         }
     }
 
-    fn new(info: &ZoneInfo, generators: &[Generator]) -> Self {
+    fn new(info: &ZoneInfo, generators: &[Generator], modulators: &[Modulator]) -> Self {
         let mut segment: Vec<Generator> = Vec::new();
 
         for i in 0..info.generator_count {
             segment.push(generators[(info.generator_index + i) as usize]);
         }
 
+        // The modulators out of the list are ignored, since they are not essential to play notes.
+        let modulators = (0..info.modulator_count)
+            .filter_map(|i| modulators.get((info.modulator_index + i) as usize))
+            .copied()
+            .collect();
+
         Self {
             generators: segment,
+            modulators,
         }
     }
 
     pub(crate) fn create(
         infos: &[ZoneInfo],
         generators: &[Generator],
+        modulators: &[Modulator],
     ) -> Result<Vec<Zone>, ParseError> {
         if infos.len() <= 1 {
             return Err(ParseError::ZoneNotFound);
@@ -39,7 +50,7 @@ impl Zone {
 
         let mut zones: Vec<Zone> = Vec::new();
         for info in infos.iter().take(count) {
-            zones.push(Zone::new(info, generators));
+            zones.push(Zone::new(info, generators, modulators));
         }
 
         Ok(zones)
@@ -1,6 +1,11 @@
+#![cfg(test)]
+
 mod instrument_util;
+mod midifile_util;
 mod preset_util;
 mod sample_util;
+mod soundfont_util;
+mod synthesizer_util;
 
 mod timgm6mb_info_test;
 mod timgm6mb_instrument_test;
@@ -13,3 +18,29 @@ mod musescore_preset_test;
 mod musescore_sample_test;
 
 mod soundfont3_test;
+
+mod midifile_clip_test;
+mod midifile_event_test;
+mod midifile_import_test;
+mod midifile_lenient_test;
+mod midifile_loop_test;
+mod midifile_looprange_test;
+mod midifile_lyric_test;
+mod midifile_mute_test;
+mod midifile_pause_test;
+mod midifile_player_test;
+mod midifile_rmid_test;
+mod midifile_seek_test;
+mod midifile_smpte_test;
+mod midifile_synthesizer_test;
+mod midifile_tempomap_test;
+mod midifile_timing_test;
+mod midifile_transform_test;
+mod midifile_transition_test;
+
+mod synthesizer_controller_test;
+mod synthesizer_handle_test;
+mod synthesizer_mpe_test;
+mod synthesizer_nrpn_test;
+mod synthesizer_port_test;
+mod synthesizer_pressure_test;
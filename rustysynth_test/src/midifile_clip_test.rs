@@ -0,0 +1,137 @@
+#![allow(dead_code)]
+#![allow(unused_imports)]
+
+use rustysynth::MidiFile;
+use rustysynth::MidiFileSequencer;
+use rustysynth::Synthesizer;
+use rustysynth::SynthesizerSettings;
+use std::sync::Arc;
+
+use crate::soundfont_util;
+
+fn clip_file(packets: &[u32]) -> Vec<u8> {
+    let mut data: Vec<u8> = b"SMF2CLIP".to_vec();
+    for packet in packets {
+        data.extend_from_slice(&packet.to_be_bytes());
+    }
+    data
+}
+
+fn render(synthesizer: &mut Synthesizer) -> Vec<f32> {
+    let mut left = vec![0_f32; 4096];
+    let mut right = vec![0_f32; 4096];
+    synthesizer.render(&mut left[..], &mut right[..]);
+    left
+}
+
+#[test]
+fn clip() {
+    let data = clip_file(&[
+        0x0030_01E0, // DCTPQ (480)
+        0xF020_0000,
+        0,
+        0,
+        0, // Start of Clip
+        0xD010_0000,
+        50_000_000,
+        0,
+        0, // Tempo (120 BPM)
+        0x4090_3C00,
+        0xFFFF_0000, // Note on
+        0x0040_03C0, // Delta clockstamp (960)
+        0x4080_3C00,
+        0x8000_0000, // Note off
+        0xF021_0000,
+        0,
+        0,
+        0, // End of Clip
+    ]);
+
+    let midi_file = Arc::new(MidiFile::new(&mut &data[..]).unwrap());
+    assert!((midi_file.get_length() - 1.0).abs() < 1.0E-9);
+
+    let settings = SynthesizerSettings::new(44100);
+    let synthesizer = Synthesizer::new(&soundfont_util::create_sound_font(), &settings).unwrap();
+    let mut sequencer = MidiFileSequencer::new(synthesizer);
+    sequencer.play(&midi_file, false);
+
+    let mut left = vec![0_f32; 4096];
+    let mut right = vec![0_f32; 4096];
+    sequencer.render(&mut left[..], &mut right[..]);
+    assert!(left.iter().any(|value| *value != 0_f32));
+}
+
+#[test]
+fn clip_without_dctpq() {
+    let data = clip_file(&[0x4090_3C00, 0xFFFF_0000]);
+    assert!(MidiFile::new(&mut &data[..]).is_err());
+}
+
+#[test]
+fn ump_matches_midi1() {
+    let settings = SynthesizerSettings::new(44100);
+    let sound_font = soundfont_util::create_sound_font();
+
+    let mut synthesizer1 = Synthesizer::new(&sound_font, &settings).unwrap();
+    synthesizer1.note_on(0, 60, 127);
+    let expected = render(&mut synthesizer1);
+
+    // The maximum 16-bit velocity is equivalent to the 7-bit velocity 127.
+    let mut synthesizer2 = Synthesizer::new(&sound_font, &settings).unwrap();
+    synthesizer2.process_ump(&[0x4090_3C00, 0xFFFF_0000]);
+    assert_eq!(render(&mut synthesizer2), expected);
+
+    // The MIDI 1.0 channel voice messages in the UMP format.
+    let mut synthesizer3 = Synthesizer::new(&sound_font, &settings).unwrap();
+    synthesizer3.process_ump(&[0x2090_3C7F]);
+    assert_eq!(render(&mut synthesizer3), expected);
+}
+
+#[test]
+fn ump_high_resolution() {
+    let settings = SynthesizerSettings::new(44100);
+    let sound_font = soundfont_util::create_sound_font();
+
+    // Velocities which are the same in 7-bit resolution make different outputs.
+    let mut synthesizer1 = Synthesizer::new(&sound_font, &settings).unwrap();
+    synthesizer1.process_ump(&[0x4090_3C00, 0x8000_0000]);
+    let mut synthesizer2 = Synthesizer::new(&sound_font, &settings).unwrap();
+    synthesizer2.process_ump(&[0x4090_3C00, 0x8100_0000]);
+    assert_ne!(render(&mut synthesizer1), render(&mut synthesizer2));
+
+    // Controller values which are the same in 7-bit resolution make different outputs.
+    let mut synthesizer3 = Synthesizer::new(&sound_font, &settings).unwrap();
+    synthesizer3.process_ump(&[0x40B0_0700, 0x8000_0000]);
+    synthesizer3.process_ump(&[0x4090_3C00, 0xFFFF_0000]);
+    let mut synthesizer4 = Synthesizer::new(&sound_font, &settings).unwrap();
+    synthesizer4.process_ump(&[0x40B0_0700, 0x8100_0000]);
+    synthesizer4.process_ump(&[0x4090_3C00, 0xFFFF_0000]);
+    assert_ne!(render(&mut synthesizer3), render(&mut synthesizer4));
+}
+
+#[test]
+fn ump_high_resolution_controllers() {
+    let settings = SynthesizerSettings::new(44100);
+    let sound_font = soundfont_util::create_sound_font();
+
+    // The data entry keeps the LSB, which is the same as the MIDI 1.0 coarse and fine pair.
+    let mut expected = Synthesizer::new(&sound_font, &settings).unwrap();
+    expected.process_midi_message(0, 0xB0, 0x65, 0);
+    expected.process_midi_message(0, 0xB0, 0x64, 0);
+    expected.process_midi_message(0, 0xB0, 0x06, 2);
+    expected.process_midi_message(0, 0xB0, 0x26, 50);
+    expected.process_midi_message(0, 0xB0, 0x40, 127);
+    expected.process_midi_message(0, 0xE0, 0x00, 0x60);
+    expected.process_midi_message(0, 0x90, 60, 127);
+    expected.process_midi_message(0, 0x80, 60, 0);
+
+    let mut actual = Synthesizer::new(&sound_font, &settings).unwrap();
+    actual.process_ump(&[0x40B0_6500, 0]);
+    actual.process_ump(&[0x40B0_6400, 0]);
+    actual.process_ump(&[0x40B0_0600, ((2 << 7) | 50) << 18]);
+    actual.process_ump(&[0x40B0_4000, 0x8000_0000]);
+    actual.process_ump(&[0x40E0_0000, 0xC000_0000]);
+    actual.process_ump(&[0x4090_3C00, 0xFFFF_0000]);
+    actual.process_ump(&[0x4080_3C00, 0]);
+    assert_eq!(render(&mut actual), render(&mut expected));
+}
@@ -0,0 +1,95 @@
+#![allow(dead_code)]
+#![allow(unused_imports)]
+
+use rustysynth::MidiFile;
+use rustysynth::MidiFileSequencer;
+use rustysynth::SequencerEvent;
+use rustysynth::Synthesizer;
+use rustysynth::SynthesizerSettings;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use crate::midifile_util;
+use crate::soundfont_util;
+
+type EventLog = Arc<Mutex<Vec<(SequencerEvent, usize)>>>;
+
+fn create_sequencer(play_loop: bool) -> (MidiFileSequencer, EventLog) {
+    let track = midifile_util::track(&[
+        midifile_util::event(0, &[0x90, 60, 100]),
+        midifile_util::event(480, &[0x80, 60, 0]),
+        midifile_util::end_of_track(0),
+    ]);
+    let data = midifile_util::midi_file(0, 480, &[track]);
+    let midi_file = Arc::new(MidiFile::new(&mut &data[..]).unwrap());
+
+    let settings = SynthesizerSettings::new(44100);
+    let synthesizer = Synthesizer::new(&soundfont_util::create_sound_font(), &settings).unwrap();
+    let mut sequencer = MidiFileSequencer::new(synthesizer);
+
+    let events: EventLog = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&events);
+    sequencer.set_event_callback(move |event, offset| sink.lock().unwrap().push((event, offset)));
+    sequencer.play(&midi_file, play_loop);
+
+    (sequencer, events)
+}
+
+#[test]
+fn messages_and_end_of_sequence() {
+    let (mut sequencer, events) = create_sequencer(false);
+
+    let mut left = vec![0_f32; 4410];
+    let mut right = vec![0_f32; 4410];
+    let mut positions: Vec<(SequencerEvent, usize)> = Vec::new();
+    for i in 0..10 {
+        sequencer.render(&mut left[..], &mut right[..]);
+        for (event, offset) in events.lock().unwrap().drain(..) {
+            assert!(offset < 4410);
+            positions.push((event, 4410 * i + offset));
+        }
+    }
+
+    assert_eq!(positions.len(), 3);
+    assert_eq!(
+        positions[0],
+        (
+            SequencerEvent::Message {
+                track: 0,
+                channel: 0,
+                command: 0x90,
+                data1: 60,
+                data2: 100
+            },
+            0
+        )
+    );
+
+    // The note-off is reported at the exact sample of 0.5 seconds.
+    assert!(matches!(
+        positions[1].0,
+        SequencerEvent::Message { command: 0x80, .. }
+    ));
+    assert_eq!(positions[1].1, 22050);
+
+    assert_eq!(positions[2].0, SequencerEvent::EndOfSequence);
+}
+
+#[test]
+fn loop_end() {
+    let (mut sequencer, events) = create_sequencer(true);
+
+    let mut left = vec![0_f32; 44100];
+    let mut right = vec![0_f32; 44100];
+    sequencer.render(&mut left[..], &mut right[..]);
+
+    let events = events.lock().unwrap();
+    let loop_ends = events
+        .iter()
+        .filter(|(event, _)| *event == SequencerEvent::LoopEnd)
+        .count();
+    assert_eq!(loop_ends, 1);
+    assert!(!events
+        .iter()
+        .any(|(event, _)| *event == SequencerEvent::EndOfSequence));
+}
@@ -0,0 +1,168 @@
+#![allow(dead_code)]
+#![allow(unused_imports)]
+
+use rustysynth::MidiFile;
+use rustysynth::MidiFileSequencer;
+use rustysynth::Synthesizer;
+use rustysynth::SynthesizerSettings;
+use std::sync::Arc;
+
+use crate::soundfont_util;
+
+fn iff_chunk(id: &[u8; 4], data: &[u8]) -> Vec<u8> {
+    let mut chunk: Vec<u8> = id.to_vec();
+    chunk.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    chunk.extend_from_slice(data);
+    if data.len() % 2 == 1 {
+        chunk.push(0);
+    }
+    chunk
+}
+
+fn write_u32(data: &mut [u8], position: usize, value: u32) {
+    data[position..position + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+#[test]
+fn mus() {
+    let score: Vec<u8> = vec![
+        0x40, 0x00, 0x05, // Program change
+        0x40, 0x03, 0x64, // Volume
+        0x20, 0x80, // Pitch wheel
+        0x9F, 0xA3, 0x40, 0x81, 0x0C, // Percussion note with delay
+        0x0F, 0x23, // Release note
+        0x60, // Score end
+    ];
+
+    let mut data: Vec<u8> = b"MUS\x1A".to_vec();
+    data.extend_from_slice(&(score.len() as u16).to_le_bytes());
+    data.extend_from_slice(&16_u16.to_le_bytes());
+    data.extend_from_slice(&[1, 0, 1, 0, 1, 0, 0, 0]);
+    data.extend(score);
+
+    // 140 ticks at 140 Hz.
+    let midi_file = MidiFile::new(&mut &data[..]).unwrap();
+    assert!((midi_file.get_length() - 1.0).abs() < 1.0E-9);
+}
+
+fn xmi_data() -> Vec<u8> {
+    let events: Vec<u8> = vec![
+        0xB0, 116, 0, // FOR
+        0xFF, 0x51, 0x03, 0x0F, 0x42, 0x40, // Tempo (ignored)
+        60,   // Interval
+        0x90, 60, 100, 120, // Note with duration
+        60,  // Interval
+        0xB0, 117, 127, // NEXT
+        0xFF, 0x2F, 0x00,
+    ];
+
+    let mut form: Vec<u8> = b"XMID".to_vec();
+    form.extend(iff_chunk(b"TIMB", &[1, 0, 0, 0]));
+    form.extend(iff_chunk(b"EVNT", &events));
+
+    let mut cat: Vec<u8> = b"XMID".to_vec();
+    cat.extend(iff_chunk(b"FORM", &form));
+
+    let mut xdir: Vec<u8> = b"XDIR".to_vec();
+    xdir.extend(iff_chunk(b"INFO", &[1, 0]));
+
+    let mut data = iff_chunk(b"FORM", &xdir);
+    data.extend(iff_chunk(b"CAT ", &cat));
+    data
+}
+
+#[test]
+fn xmi() {
+    let data = xmi_data();
+
+    // The note-off derived from the duration is at 180 ticks at 120 Hz.
+    let midi_file = Arc::new(MidiFile::new(&mut &data[..]).unwrap());
+    assert!((midi_file.get_length() - 1.5).abs() < 1.0E-9);
+
+    // The FOR and NEXT controllers make a loop between 0 and 1 second.
+    let settings = SynthesizerSettings::new(44100);
+    let synthesizer = Synthesizer::new(&soundfont_util::create_sound_font(), &settings).unwrap();
+    let mut sequencer = MidiFileSequencer::new(synthesizer);
+    sequencer.play(&midi_file, true);
+
+    let mut left = vec![0_f32; 44100 / 2];
+    let mut right = vec![0_f32; 44100 / 2];
+    for _ in 0..3 {
+        sequencer.render(&mut left[..], &mut right[..]);
+    }
+    assert!((0.0..1.0).contains(&sequencer.get_position()));
+}
+
+#[test]
+fn hmp() {
+    let events: Vec<u8> = vec![
+        0x80, 0x90, 60, 100, // Note on
+        0xE4, 0x80, 60, 0, // Note off after 100 ticks
+        0x80, 0xFF, 0x2F, 0x00,
+    ];
+
+    let mut data: Vec<u8> = vec![0; 0x308];
+    data[0..8].copy_from_slice(b"HMIMIDIP");
+    write_u32(&mut data, 0x30, 1);
+    write_u32(&mut data, 0x38, 100);
+
+    let mut header = vec![0; 12];
+    write_u32(&mut header, 4, (12 + events.len()) as u32);
+    data.extend(header);
+    data.extend(events);
+
+    // 100 ticks at 100 Hz.
+    let midi_file = MidiFile::new(&mut &data[..]).unwrap();
+    assert!((midi_file.get_length() - 1.0).abs() < 1.0E-9);
+}
+
+#[test]
+fn hmi() {
+    let events: Vec<u8> = vec![
+        0x00, 0xFE, 0x14, 0x00, 0x00, // Driver-specific event
+        0x00, 0x90, 60, 100, 100, // Note with duration
+        0x00, 0xFF, 0x2F, 0x00,
+    ];
+
+    let mut data: Vec<u8> = vec![0; 0x170];
+    data[0..18].copy_from_slice(b"HMI-MIDISONG061595");
+    write_u32(&mut data, 0xD4, 100);
+    write_u32(&mut data, 0xE4, 1);
+    write_u32(&mut data, 0xE8, 0x100);
+    write_u32(&mut data, 0x100, 0x110);
+    data[0x110..0x11D].copy_from_slice(b"HMI-MIDITRACK");
+    write_u32(&mut data, 0x110 + 0x57, 0x60);
+    data.extend(events);
+
+    // The note-off derived from the duration is at 100 ticks at 100 Hz.
+    let midi_file = MidiFile::new(&mut &data[..]).unwrap();
+    assert!((midi_file.get_length() - 1.0).abs() < 1.0E-9);
+}
+
+#[test]
+fn truncated_hmp() {
+    let events: Vec<u8> = vec![
+        0x80, 0x90, 60, 100, // Note on
+        0xE4, 0x80, 60, 0, // Note off after 100 ticks
+        0x80, 0xFF, 0x2F, 0x00,
+    ];
+
+    let mut data: Vec<u8> = vec![0; 0x308];
+    data[0..8].copy_from_slice(b"HMIMIDIP");
+    write_u32(&mut data, 0x30, 2);
+    write_u32(&mut data, 0x38, 100);
+
+    let mut header = vec![0; 12];
+    write_u32(&mut header, 4, (12 + events.len()) as u32);
+    data.extend(header);
+    data.extend(events);
+
+    // The header of the second track is cut off after the size field.
+    let mut header = vec![0; 12];
+    write_u32(&mut header, 4, 100);
+    data.extend(&header[..10]);
+
+    let midi_file = MidiFile::new(&mut &data[..]).unwrap();
+    assert_eq!(midi_file.get_track_count(), 2);
+    assert!((midi_file.get_length() - 1.0).abs() < 1.0E-9);
+}
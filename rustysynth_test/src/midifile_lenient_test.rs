@@ -0,0 +1,147 @@
+#![allow(dead_code)]
+#![allow(unused_imports)]
+
+use rustysynth::MidiFile;
+use rustysynth::MidiFileLoopType;
+use rustysynth::MidiFileRepair;
+
+use crate::midifile_util;
+
+fn notes(delta: u32) -> Vec<Vec<u8>> {
+    vec![
+        midifile_util::tempo(0, 500000),
+        midifile_util::event(0, &[0x90, 60, 100]),
+        midifile_util::event(delta, &[0x80, 60, 0]),
+    ]
+}
+
+fn load(data: &[u8]) -> MidiFile {
+    MidiFile::new_lenient(&mut &data[..], MidiFileLoopType::LoopPoint(0)).unwrap()
+}
+
+#[test]
+fn valid_file() {
+    let mut events = notes(960);
+    events.push(midifile_util::end_of_track(0));
+    let data = midifile_util::midi_file(0, 480, &[midifile_util::track(&events)]);
+
+    let midi_file = load(&data);
+    assert!(midi_file.get_repairs().is_empty());
+    assert!((midi_file.get_length() - 1.0).abs() < 1.0E-9);
+}
+
+#[test]
+fn missing_end_of_track() {
+    let first = midifile_util::track(&notes(960));
+    let mut events = notes(1920);
+    events.push(midifile_util::end_of_track(0));
+    let second = midifile_util::track(&events);
+    let data = midifile_util::midi_file(1, 480, &[first, second]);
+
+    let midi_file = load(&data);
+    assert_eq!(
+        midi_file.get_repairs(),
+        &[MidiFileRepair::MissingEndOfTrack(0)]
+    );
+    assert!((midi_file.get_length() - 2.0).abs() < 1.0E-9);
+}
+
+#[test]
+fn wrong_track_length() {
+    let mut events = notes(960);
+    events.push(midifile_util::end_of_track(0));
+    let mut first = midifile_util::track(&events);
+    let actual = first.len() - 8;
+    first[4..8].copy_from_slice(&1000_u32.to_be_bytes());
+
+    let mut events = notes(1440);
+    events.push(midifile_util::end_of_track(0));
+    let second = midifile_util::track(&events);
+    let data = midifile_util::midi_file(1, 480, &[first, second]);
+
+    let midi_file = load(&data);
+    assert_eq!(
+        midi_file.get_repairs(),
+        &[MidiFileRepair::TrackLengthMismatch {
+            track: 0,
+            expected: 1000,
+            actual,
+        }]
+    );
+    assert!((midi_file.get_length() - 1.5).abs() < 1.0E-9);
+}
+
+#[test]
+fn unknown_chunk_and_trailing_garbage() {
+    let mut events = notes(960);
+    events.push(midifile_util::end_of_track(0));
+    let track = midifile_util::track(&events);
+
+    let mut data = midifile_util::midi_file(1, 480, std::slice::from_ref(&track));
+    data.extend_from_slice(b"XFIH");
+    data.extend_from_slice(&4_u32.to_be_bytes());
+    data.extend_from_slice(&[1, 2, 3, 4]);
+    data.extend_from_slice(&track);
+    data.extend_from_slice(&[0x00, 0xFF, 0xFF]);
+
+    assert!(MidiFile::new(&mut &data[..])
+        .unwrap()
+        .get_repairs()
+        .is_empty());
+
+    let midi_file = load(&data);
+    let repairs = midi_file.get_repairs();
+    assert!(matches!(repairs[0], MidiFileRepair::UnknownChunkSkipped(id) if id == b"XFIH"));
+    assert_eq!(
+        &repairs[1..],
+        &[
+            MidiFileRepair::UnknownDataSkipped(3),
+            MidiFileRepair::TrackCountMismatch {
+                expected: 1,
+                actual: 2
+            },
+        ]
+    );
+}
+
+#[test]
+fn truncated_track() {
+    let mut events = notes(960);
+    events.push(midifile_util::event(480, &[0x90, 64, 100]));
+    events.push(midifile_util::end_of_track(480));
+    let mut data = midifile_util::midi_file(0, 480, &[midifile_util::track(&events)]);
+    data.truncate(data.len() - 6);
+
+    assert!(MidiFile::new(&mut &data[..]).is_err());
+
+    let midi_file = load(&data);
+    assert!(midi_file
+        .get_repairs()
+        .contains(&MidiFileRepair::TruncatedTrack(0)));
+    assert!((midi_file.get_length() - 1.0).abs() < 1.0E-9);
+}
+
+#[test]
+fn short_trailing_garbage() {
+    let mut events = notes(960);
+    events.push(midifile_util::end_of_track(0));
+    let track = midifile_util::track(&events);
+
+    // The garbage shorter than a chunk header is skipped, even if it looks like a chunk ID.
+    for garbage in [
+        &b"JUNK"[..],
+        b"JUNK\x00",
+        b"JUNK\x00\x00",
+        b"\x01\x02\x03\x04\x05\x06\x07",
+    ] {
+        let mut data = midifile_util::midi_file(0, 480, std::slice::from_ref(&track));
+        data.extend_from_slice(garbage);
+
+        let midi_file = load(&data);
+        assert_eq!(
+            midi_file.get_repairs(),
+            &[MidiFileRepair::UnknownDataSkipped(garbage.len())]
+        );
+        assert!((midi_file.get_length() - 1.0).abs() < 1.0E-9);
+    }
+}
@@ -0,0 +1,181 @@
+#![allow(dead_code)]
+#![allow(unused_imports)]
+
+use rustysynth::MidiFile;
+use rustysynth::MidiFileLoopType;
+use rustysynth::MidiFileSequencer;
+use rustysynth::Synthesizer;
+use rustysynth::SynthesizerSettings;
+use std::sync::Arc;
+
+use crate::midifile_util;
+use crate::soundfont_util;
+
+fn load(events: &[Vec<u8>], loop_type: MidiFileLoopType) -> Arc<MidiFile> {
+    let mut events = events.to_vec();
+    events.push(midifile_util::event(0, &[0x90, 60, 100]));
+    events.push(midifile_util::event(1920, &[0x80, 60, 0]));
+    events.push(midifile_util::end_of_track(0));
+    let track = midifile_util::track(&events);
+    let data = midifile_util::midi_file(0, 480, &[track]);
+    Arc::new(MidiFile::new_with_loop_type(&mut &data[..], loop_type).unwrap())
+}
+
+// Plays the MIDI file for 1.5 seconds and returns the playback position.
+fn play(midi_file: &Arc<MidiFile>) -> f64 {
+    let settings = SynthesizerSettings::new(44100);
+    let synthesizer = Synthesizer::new(&soundfont_util::create_sound_font(), &settings).unwrap();
+    let mut sequencer = MidiFileSequencer::new(synthesizer);
+    sequencer.play(midi_file, true);
+
+    let mut left = vec![0_f32; 44100 / 2];
+    let mut right = vec![0_f32; 44100 / 2];
+    for _ in 0..3 {
+        sequencer.render(&mut left[..], &mut right[..]);
+    }
+
+    sequencer.get_position()
+}
+
+#[test]
+fn marker_loop() {
+    let midi_file = load(
+        &[
+            midifile_util::meta_event(480, 0x06, b"loopStart"),
+            midifile_util::meta_event(480, 0x06, b"LOOPEND"),
+        ],
+        MidiFileLoopType::Marker,
+    );
+    let position = play(&midi_file);
+    assert!((0.5..1.0).contains(&position));
+}
+
+#[test]
+fn controller_loop() {
+    let midi_file = load(
+        &[
+            midifile_util::event(480, &[0xB0, 20, 0]),
+            midifile_util::event(480, &[0xB0, 21, 0]),
+        ],
+        MidiFileLoopType::Controller(20, 21),
+    );
+    let position = play(&midi_file);
+    assert!((0.5..1.0).contains(&position));
+}
+
+#[test]
+fn emidi_loop() {
+    let midi_file = load(
+        &[
+            midifile_util::event(480, &[0xB0, 118, 0]),
+            midifile_util::event(480, &[0xB0, 119, 127]),
+        ],
+        MidiFileLoopType::Emidi,
+    );
+    let position = play(&midi_file);
+    assert!((0.5..1.0).contains(&position));
+}
+
+#[test]
+fn loop_range() {
+    let midi_file = load(&[], MidiFileLoopType::LoopRange(480, 960));
+    let position = play(&midi_file);
+    assert!((0.5..1.0).contains(&position));
+
+    // Without the loop end, the whole sequence loops.
+    let midi_file = load(&[], MidiFileLoopType::LoopPoint(480));
+    let position = play(&midi_file);
+    assert!((1.0..2.0).contains(&position));
+}
+
+fn create_looping_sequencer(loop_count: usize, fade_out_duration: f64) -> MidiFileSequencer {
+    // The loop section is from 0.5 to 1.0 seconds, and a note starts at the loop start.
+    let track = midifile_util::track(&[
+        midifile_util::event(0, &[0x90, 60, 100]),
+        midifile_util::meta_event(480, 0x06, b"loopStart"),
+        midifile_util::event(0, &[0x90, 64, 100]),
+        midifile_util::meta_event(480, 0x06, b"loopEnd"),
+        midifile_util::event(960, &[0x80, 64, 0]),
+        midifile_util::end_of_track(0),
+    ]);
+    let data = midifile_util::midi_file(0, 480, &[track]);
+    let midi_file =
+        Arc::new(MidiFile::new_with_loop_type(&mut &data[..], MidiFileLoopType::Marker).unwrap());
+
+    let mut settings = SynthesizerSettings::new(44100);
+    settings.enable_reverb_and_chorus = false;
+    let synthesizer = Synthesizer::new(&soundfont_util::create_sound_font(), &settings).unwrap();
+    let mut sequencer = MidiFileSequencer::new(synthesizer);
+    sequencer.set_loop_count(Some(loop_count));
+    sequencer.set_fade_out_duration(fade_out_duration);
+    sequencer.play(&midi_file, true);
+    sequencer
+}
+
+// Renders the waveform in blocks of 0.1 seconds and returns the peak of each block
+// together with whether the sequence has ended.
+fn render_blocks(sequencer: &mut MidiFileSequencer, count: usize) -> Vec<(f32, bool)> {
+    let mut left = vec![0_f32; 4410];
+    let mut right = vec![0_f32; 4410];
+    let mut blocks: Vec<(f32, bool)> = Vec::new();
+    for _ in 0..count {
+        sequencer.render(&mut left[..], &mut right[..]);
+        let peak = left.iter().fold(0_f32, |max, value| max.max(value.abs()));
+        blocks.push((peak, sequencer.end_of_sequence()));
+    }
+    blocks
+}
+
+#[test]
+fn loop_count_without_fade_out() {
+    let mut sequencer = create_looping_sequencer(2, 0.0);
+    let blocks = render_blocks(&mut sequencer, 20);
+
+    // The loop section is played twice, and the playback ends at 1.5 seconds.
+    assert!(!blocks[13].1);
+    assert!(blocks[15].1);
+    assert!(blocks[19].0 < 1.0E-3);
+}
+
+#[test]
+fn fade_out() {
+    let mut sequencer = create_looping_sequencer(1, 1.0);
+    let blocks = render_blocks(&mut sequencer, 30);
+
+    // The fade-out starts at 1.0 seconds and completes at 2.0 seconds.
+    assert!(blocks[10].0 > blocks[14].0);
+    assert!(blocks[14].0 > blocks[18].0);
+    assert!(!blocks[18].1);
+    assert!(blocks[20].1);
+    for block in &blocks[20..] {
+        assert!(block.0 < 1.0E-6);
+        assert!(block.1);
+    }
+}
+
+#[test]
+fn synthesizer_sounds_after_fade_out() {
+    let mut sequencer = create_looping_sequencer(1, 1.0);
+    let blocks = render_blocks(&mut sequencer, 25);
+    assert!(blocks[24].1);
+
+    // The notes played after the fade-out are not muted.
+    sequencer.get_synthesizer_mut().note_on(0, 60, 100);
+    let blocks = render_blocks(&mut sequencer, 1);
+    assert!(blocks[0].0 > 0.01);
+
+    let mut synthesizer = sequencer.into_synthesizer();
+    synthesizer.note_on(0, 64, 100);
+    let mut left = vec![0_f32; 4410];
+    let mut right = vec![0_f32; 4410];
+    synthesizer.render(&mut left[..], &mut right[..]);
+    let peak = left.iter().fold(0_f32, |max, value| max.max(value.abs()));
+    assert!(peak > 0.01);
+}
+
+#[test]
+#[should_panic]
+fn zero_loop_count() {
+    let mut sequencer = create_looping_sequencer(1, 0.0);
+    sequencer.set_loop_count(Some(0));
+}
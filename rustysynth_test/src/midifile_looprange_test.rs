@@ -0,0 +1,177 @@
+#![allow(dead_code)]
+#![allow(unused_imports)]
+
+use rustysynth::MidiFile;
+use rustysynth::MidiFileSequencer;
+use rustysynth::SequencerEvent;
+use rustysynth::Synthesizer;
+use rustysynth::SynthesizerSettings;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use crate::midifile_util;
+use crate::soundfont_util;
+
+type EventLog = Arc<Mutex<Vec<(SequencerEvent, usize)>>>;
+
+// The volume is lowered at 0.5 seconds, a note is played from 1.0 to 1.9 seconds,
+// and the volume is lowered again at 1.5 seconds.
+fn create_midi_file() -> Arc<MidiFile> {
+    let track = midifile_util::track(&[
+        midifile_util::event(480, &[0xB0, 7, 50]),
+        midifile_util::event(480, &[0x90, 60, 100]),
+        midifile_util::event(480, &[0xB0, 7, 20]),
+        midifile_util::event(384, &[0x80, 60, 0]),
+        midifile_util::end_of_track(2016),
+    ]);
+    let data = midifile_util::midi_file(0, 480, &[track]);
+    Arc::new(MidiFile::new(&mut &data[..]).unwrap())
+}
+
+fn create_sequencer(midi_file: &Arc<MidiFile>) -> (MidiFileSequencer, EventLog) {
+    let mut settings = SynthesizerSettings::new(44100);
+    settings.enable_reverb_and_chorus = false;
+    let synthesizer = Synthesizer::new(&soundfont_util::create_sound_font(), &settings).unwrap();
+    let mut sequencer = MidiFileSequencer::new(synthesizer);
+
+    let events: EventLog = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&events);
+    sequencer.set_event_callback(move |event, offset| sink.lock().unwrap().push((event, offset)));
+    sequencer.play(midi_file, false);
+
+    (sequencer, events)
+}
+
+fn render(sequencer: &mut MidiFileSequencer, length: usize) -> Vec<f32> {
+    let mut left = vec![0_f32; length];
+    let mut right = vec![0_f32; length];
+    sequencer.render(&mut left[..], &mut right[..]);
+    left
+}
+
+fn rms(data: &[f32]) -> f32 {
+    (data.iter().map(|value| value * value).sum::<f32>() / data.len() as f32).sqrt()
+}
+
+// Gets the positions of the loop ends and the note-ons during the first 3 seconds.
+fn get_positions(sequencer: &mut MidiFileSequencer, events: &EventLog) -> (Vec<usize>, Vec<usize>) {
+    render(sequencer, 3 * 44100);
+
+    let events = events.lock().unwrap();
+    let loop_ends = events
+        .iter()
+        .filter(|(event, _)| *event == SequencerEvent::LoopEnd)
+        .map(|(_, offset)| *offset)
+        .collect();
+    let note_ons = events
+        .iter()
+        .filter(|(event, _)| matches!(event, SequencerEvent::Message { command: 0x90, .. }))
+        .map(|(_, offset)| *offset)
+        .collect();
+    (loop_ends, note_ons)
+}
+
+#[test]
+fn loop_range_seconds() {
+    let (mut sequencer, events) = create_sequencer(&create_midi_file());
+    sequencer.set_loop_range(1.0, 2.0);
+    assert_eq!(sequencer.get_loop_range(), Some((1.0, 2.0)));
+
+    let (loop_ends, note_ons) = get_positions(&mut sequencer, &events);
+    assert_eq!(loop_ends, vec![88200]);
+    assert_eq!(note_ons, vec![44100, 88200]);
+    assert!(!sequencer.end_of_sequence());
+}
+
+#[test]
+fn loop_range_ticks() {
+    let (mut sequencer, events) = create_sequencer(&create_midi_file());
+    sequencer.set_loop_range_ticks(960, 1920);
+    assert_eq!(sequencer.get_loop_range(), Some((1.0, 2.0)));
+
+    let (loop_ends, note_ons) = get_positions(&mut sequencer, &events);
+    assert_eq!(loop_ends, vec![88200]);
+    assert_eq!(note_ons, vec![44100, 88200]);
+}
+
+#[test]
+fn clear_loop_range() {
+    let (mut sequencer, events) = create_sequencer(&create_midi_file());
+    sequencer.set_loop_range(1.0, 2.0);
+    sequencer.clear_loop_range();
+
+    let (loop_ends, note_ons) = get_positions(&mut sequencer, &events);
+    assert!(loop_ends.is_empty());
+    assert_eq!(note_ons, vec![44100]);
+}
+
+#[test]
+fn controllers_are_chased() {
+    let (mut sequencer, _) = create_sequencer(&create_midi_file());
+    sequencer.set_loop_range(1.0, 2.0);
+    let first = render(&mut sequencer, 88200);
+    let second = render(&mut sequencer, 88200);
+
+    // The volume set before the loop start is restored at the loop start.
+    let expected = rms(&first[48510..52920]);
+    let lowered = rms(&first[70560..74970]);
+    let actual = rms(&second[4410..8820]);
+    assert!(expected > 2.0 * lowered);
+    assert!((actual - expected).abs() < 0.05 * expected);
+}
+
+#[test]
+fn mono_mode_is_chased() {
+    // The mono mode is set at 0.5 seconds, and two notes overlap from 1.2 to 1.8 seconds.
+    let track = midifile_util::track(&[
+        midifile_util::event(480, &[0xB0, 0x7E, 0]),
+        midifile_util::event(480, &[0x90, 60, 100]),
+        midifile_util::event(192, &[0x90, 64, 100]),
+        midifile_util::event(576, &[0x80, 60, 0]),
+        midifile_util::event(0, &[0x80, 64, 0]),
+        midifile_util::end_of_track(960),
+    ]);
+    let data = midifile_util::midi_file(0, 480, &[track]);
+    let midi_file = Arc::new(MidiFile::new(&mut &data[..]).unwrap());
+
+    let (mut sequencer, _) = create_sequencer(&midi_file);
+    sequencer.set_loop_range(1.0, 2.0);
+    let first = render(&mut sequencer, 88200);
+    let second = render(&mut sequencer, 88200);
+
+    // Only the second note sounds in the loop, as well as in the first playback.
+    let expected = rms(&first[57330..61740]);
+    let actual = rms(&second[13230..17640]);
+    assert!((actual - expected).abs() < 0.05 * expected);
+}
+
+#[test]
+fn keep_loop_tails() {
+    // A note is held from 1.5 to 2.5 seconds across the loop end at 2 seconds.
+    let track = midifile_util::track(&[
+        midifile_util::event(1440, &[0x90, 60, 100]),
+        midifile_util::event(960, &[0x80, 60, 0]),
+        midifile_util::end_of_track(960),
+    ]);
+    let data = midifile_util::midi_file(0, 480, &[track]);
+    let midi_file = Arc::new(MidiFile::new(&mut &data[..]).unwrap());
+
+    // Gets the waveform from 2.2 to 2.3 seconds.
+    let tail = |keep_loop_tails: bool| {
+        let (mut sequencer, _) = create_sequencer(&midi_file);
+        sequencer.set_loop_range(1.0, 2.0);
+        sequencer.set_keep_loop_tails(keep_loop_tails);
+        render(&mut sequencer, 97020);
+        rms(&render(&mut sequencer, 4410))
+    };
+
+    assert!(tail(false) < 1.0E-6);
+    assert!(tail(true) > 0.01);
+}
+
+#[test]
+#[should_panic]
+fn invalid_loop_range() {
+    let (mut sequencer, _) = create_sequencer(&create_midi_file());
+    sequencer.set_loop_range(2.0, 1.0);
+}
@@ -0,0 +1,109 @@
+#![allow(dead_code)]
+#![allow(unused_imports)]
+
+use rustysynth::MidiFile;
+use rustysynth::MidiFileSequencer;
+use rustysynth::Synthesizer;
+use rustysynth::SynthesizerSettings;
+use std::sync::Arc;
+
+use crate::midifile_util;
+use crate::soundfont_util;
+
+fn kar_file() -> Vec<u8> {
+    let music = midifile_util::track(&[
+        midifile_util::event(0, &[0x90, 60, 100]),
+        midifile_util::event(960, &[0x80, 60, 0]),
+        midifile_util::end_of_track(0),
+    ]);
+    let words = midifile_util::track(&[
+        midifile_util::meta_event(0, 0x01, b"@KMIDI KARAOKE FILE"),
+        midifile_util::meta_event(0, 0x01, b"@TSong title"),
+        midifile_util::meta_event(0, 0x01, b"\\Hel"),
+        midifile_util::meta_event(240, 0x01, b"lo "),
+        midifile_util::meta_event(240, 0x01, b"/world"),
+        midifile_util::meta_event(240, 0x05, b"ignored"),
+        midifile_util::end_of_track(0),
+    ]);
+    midifile_util::midi_file(1, 480, &[music, words])
+}
+
+#[test]
+fn kar() {
+    let data = kar_file();
+    let midi_file = MidiFile::new(&mut &data[..]).unwrap();
+
+    let headers = midi_file.get_lyric_headers();
+    assert_eq!(headers, ["KMIDI KARAOKE FILE", "TSong title"]);
+
+    let lyrics = midi_file.get_lyrics();
+    assert_eq!(lyrics.len(), 3);
+    assert_eq!(lyrics[0].get_text(), "Hel");
+    assert!(lyrics[0].is_new_paragraph() && lyrics[0].is_new_line());
+    assert_eq!(lyrics[1].get_text(), "lo ");
+    assert!(!lyrics[1].is_new_line());
+    assert_eq!(lyrics[2].get_text(), "world");
+    assert!(lyrics[2].is_new_line() && !lyrics[2].is_new_paragraph());
+    assert_eq!(lyrics[2].get_tick(), 480);
+    assert!((lyrics[2].get_time() - 0.5).abs() < 1.0E-9);
+}
+
+#[test]
+fn lyric_events() {
+    let track = midifile_util::track(&[
+        midifile_util::meta_event(0, 0x01, b"Not a lyric"),
+        midifile_util::meta_event(0, 0x05, b"Caf\xE9\r"),
+        midifile_util::meta_event(480, 0x05, b"\xE3\x81\x82"),
+        midifile_util::end_of_track(0),
+    ]);
+    let data = midifile_util::midi_file(0, 480, &[track]);
+    let midi_file = MidiFile::new(&mut &data[..]).unwrap();
+
+    // The text is decoded as Latin-1 if it is not valid UTF-8.
+    let lyrics = midi_file.get_lyrics();
+    assert_eq!(lyrics.len(), 2);
+    assert_eq!(lyrics[0].get_text(), "Caf\u{E9}");
+    assert_eq!(lyrics[1].get_text(), "\u{3042}");
+    assert!(lyrics[1].is_new_line());
+    assert!(midi_file.get_lyric_headers().is_empty());
+}
+
+#[test]
+fn current_syllable() {
+    let data = kar_file();
+    let midi_file = Arc::new(MidiFile::new(&mut &data[..]).unwrap());
+
+    let settings = SynthesizerSettings::new(44100);
+    let synthesizer = Synthesizer::new(&soundfont_util::create_sound_font(), &settings).unwrap();
+    let mut sequencer = MidiFileSequencer::new(synthesizer);
+    assert_eq!(sequencer.get_lyric_index(), None);
+
+    sequencer.play(&midi_file, false);
+    let mut left = vec![0_f32; 4410];
+    let mut right = vec![0_f32; 4410];
+    let mut indices: Vec<Option<usize>> = Vec::new();
+    for _ in 0..8 {
+        sequencer.render(&mut left[..], &mut right[..]);
+        let index = sequencer.get_lyric_index();
+        if indices.last() != Some(&index) {
+            indices.push(index);
+        }
+    }
+    assert_eq!(indices, [Some(0), Some(1), Some(2)]);
+}
+
+#[test]
+fn transforms() {
+    let data = kar_file();
+    let midi_file = MidiFile::new(&mut &data[..]).unwrap();
+
+    let scaled = midi_file.scale_tempo(2.0);
+    assert!((scaled.get_lyrics()[2].get_time() - 0.25).abs() < 1.0E-9);
+
+    let trimmed = midi_file.trim(0.25, 1.0);
+    let lyrics = trimmed.get_lyrics();
+    assert_eq!(lyrics.len(), 2);
+    assert_eq!(lyrics[0].get_text(), "lo ");
+    assert_eq!(lyrics[0].get_tick(), 0);
+    assert!((lyrics[1].get_time() - 0.25).abs() < 1.0E-9);
+}
@@ -0,0 +1,130 @@
+#![allow(dead_code)]
+#![allow(unused_imports)]
+
+use rustysynth::MidiFile;
+use rustysynth::MidiFileSequencer;
+use rustysynth::Synthesizer;
+use rustysynth::SynthesizerSettings;
+use std::sync::Arc;
+
+use crate::midifile_util;
+use crate::soundfont_util;
+
+fn create_sequencer(tracks: &[Vec<u8>]) -> MidiFileSequencer {
+    let data = midifile_util::midi_file(1, 480, tracks);
+    let midi_file = Arc::new(MidiFile::new(&mut &data[..]).unwrap());
+
+    let mut settings = SynthesizerSettings::new(44100);
+    settings.enable_reverb_and_chorus = false;
+    let synthesizer = Synthesizer::new(&soundfont_util::create_sound_font(), &settings).unwrap();
+    let mut sequencer = MidiFileSequencer::new(synthesizer);
+    sequencer.play(&midi_file, false);
+    sequencer
+}
+
+fn is_silent(sequencer: &mut MidiFileSequencer, length: usize) -> bool {
+    let mut left = vec![0_f32; length];
+    let mut right = vec![0_f32; length];
+    sequencer.render(&mut left[..], &mut right[..]);
+    left.iter().all(|value| value.abs() < 1.0E-6)
+}
+
+fn note_track(channel: u8) -> Vec<u8> {
+    midifile_util::track(&[
+        midifile_util::event(0, &[0x90 | channel, 60, 100]),
+        midifile_util::event(3840, &[0x80 | channel, 60, 0]),
+        midifile_util::end_of_track(0),
+    ])
+}
+
+fn controller_track(channel: u8) -> Vec<u8> {
+    midifile_util::track(&[
+        midifile_util::event(0, &[0xB0 | channel, 10, 0]),
+        midifile_util::end_of_track(0),
+    ])
+}
+
+#[test]
+fn track_count() {
+    let data = midifile_util::midi_file(1, 480, &[note_track(0), controller_track(1)]);
+    let midi_file = MidiFile::new(&mut &data[..]).unwrap();
+    assert_eq!(midi_file.get_track_count(), 2);
+    assert_eq!(midi_file.transpose(1).get_track_count(), 2);
+}
+
+#[test]
+fn channel_mute() {
+    let mut sequencer = create_sequencer(&[note_track(0)]);
+    sequencer.set_channel_mute(0, true);
+    assert!(sequencer.get_channel_mute(0));
+    assert!(is_silent(&mut sequencer, 4410));
+
+    // The note-on dropped while muted is not restarted by unmuting.
+    sequencer.set_channel_mute(0, false);
+    assert!(is_silent(&mut sequencer, 4410));
+}
+
+#[test]
+fn mute_during_playback() {
+    let mut sequencer = create_sequencer(&[note_track(0)]);
+    assert!(!is_silent(&mut sequencer, 4410));
+
+    // The sounding note is released.
+    sequencer.set_track_mute(0, true);
+    assert!(sequencer.get_track_mute(0));
+    is_silent(&mut sequencer, 44100);
+    assert!(is_silent(&mut sequencer, 4410));
+}
+
+#[test]
+fn solo() {
+    // Only the track without notes is audible.
+    let mut sequencer = create_sequencer(&[note_track(0), controller_track(1)]);
+    sequencer.set_track_solo(1, true);
+    assert!(is_silent(&mut sequencer, 4410));
+
+    // The soloed channel is audible in addition to the soloed track.
+    let mut sequencer = create_sequencer(&[note_track(0), controller_track(1)]);
+    sequencer.set_track_solo(1, true);
+    sequencer.set_channel_solo(0, true);
+    assert!(!is_silent(&mut sequencer, 4410));
+
+    // The mute takes priority over the solo.
+    let mut sequencer = create_sequencer(&[note_track(0), controller_track(1)]);
+    sequencer.set_channel_solo(0, true);
+    sequencer.set_track_mute(0, true);
+    assert!(is_silent(&mut sequencer, 4410));
+}
+
+#[test]
+fn same_key_in_two_tracks() {
+    let mut sequencer = create_sequencer(&[note_track(0), note_track(0)]);
+    assert!(!is_silent(&mut sequencer, 4410));
+
+    // The key is kept while the soloed track holds it.
+    sequencer.set_track_solo(0, true);
+    is_silent(&mut sequencer, 44100);
+    assert!(!is_silent(&mut sequencer, 4410));
+
+    // The key is released when neither track is audible.
+    sequencer.set_track_mute(0, true);
+    is_silent(&mut sequencer, 44100);
+    assert!(is_silent(&mut sequencer, 4410));
+}
+
+#[test]
+fn controllers_reach_muted_channel() {
+    let track = midifile_util::track(&[
+        midifile_util::event(0, &[0xB0, 7, 0]),
+        midifile_util::event(960, &[0x90, 60, 100]),
+        midifile_util::event(960, &[0x80, 60, 0]),
+        midifile_util::end_of_track(0),
+    ]);
+
+    // The volume set while muted is applied to the note after unmuting.
+    let mut sequencer = create_sequencer(&[track]);
+    sequencer.set_channel_mute(0, true);
+    assert!(is_silent(&mut sequencer, 4410));
+    sequencer.set_channel_mute(0, false);
+    assert!(is_silent(&mut sequencer, 88200));
+}
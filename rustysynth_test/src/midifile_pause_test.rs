@@ -0,0 +1,121 @@
+#![allow(dead_code)]
+#![allow(unused_imports)]
+
+use rustysynth::MidiFile;
+use rustysynth::MidiFileSequencer;
+use rustysynth::Synthesizer;
+use rustysynth::SynthesizerSettings;
+use std::sync::Arc;
+
+use crate::midifile_util;
+use crate::soundfont_util;
+
+fn create_sequencer() -> MidiFileSequencer {
+    let track = midifile_util::track(&[
+        midifile_util::event(0, &[0x90, 60, 100]),
+        midifile_util::event(1920, &[0x80, 60, 0]),
+        midifile_util::end_of_track(0),
+    ]);
+    let data = midifile_util::midi_file(0, 480, &[track]);
+    let midi_file = Arc::new(MidiFile::new(&mut &data[..]).unwrap());
+
+    let mut settings = SynthesizerSettings::new(44100);
+    settings.enable_reverb_and_chorus = false;
+    let synthesizer = Synthesizer::new(&soundfont_util::create_sound_font(), &settings).unwrap();
+    let mut sequencer = MidiFileSequencer::new(synthesizer);
+    sequencer.play(&midi_file, false);
+    sequencer
+}
+
+fn render(sequencer: &mut MidiFileSequencer, length: usize) -> Vec<f32> {
+    let mut left = vec![0_f32; length];
+    let mut right = vec![0_f32; length];
+    sequencer.render(&mut left[..], &mut right[..]);
+    left
+}
+
+fn is_silent(block: &[f32]) -> bool {
+    block.iter().all(|value| value.abs() < 1.0E-6)
+}
+
+#[test]
+fn pause_keeps_position() {
+    let mut sequencer = create_sequencer();
+    render(&mut sequencer, 22050);
+    let position = sequencer.get_position();
+
+    sequencer.pause(false);
+    assert!(sequencer.is_paused());
+    render(&mut sequencer, 44100);
+    assert_eq!(sequencer.get_position(), position);
+
+    sequencer.resume(false);
+    assert!(!sequencer.is_paused());
+    render(&mut sequencer, 4410);
+    assert!(sequencer.get_position() > position);
+}
+
+#[test]
+fn release_and_restart() {
+    let mut sequencer = create_sequencer();
+    render(&mut sequencer, 22050);
+
+    // The released notes fade out while paused.
+    sequencer.pause(false);
+    render(&mut sequencer, 44100);
+    assert!(is_silent(&render(&mut sequencer, 4410)));
+
+    sequencer.resume(true);
+    assert!(!is_silent(&render(&mut sequencer, 4410)));
+
+    let mut sequencer = create_sequencer();
+    render(&mut sequencer, 22050);
+    sequencer.pause(false);
+    render(&mut sequencer, 44100);
+    sequencer.resume(false);
+    assert!(is_silent(&render(&mut sequencer, 4410)));
+}
+
+#[test]
+fn freeze() {
+    let mut sequencer = create_sequencer();
+    render(&mut sequencer, 22050);
+
+    sequencer.pause(true);
+    render(&mut sequencer, 44100);
+    assert!(is_silent(&render(&mut sequencer, 4410)));
+
+    // The frozen notes continue without being restarted.
+    sequencer.resume(false);
+    assert!(!is_silent(&render(&mut sequencer, 4410)));
+}
+
+#[test]
+fn freeze_ramps_the_gain() {
+    let mut sequencer = create_sequencer();
+    render(&mut sequencer, 22050);
+
+    // The notes fade out for a few milliseconds instead of stopping at once.
+    sequencer.pause(true);
+    assert!(!is_silent(&render(&mut sequencer, 64)));
+    render(&mut sequencer, 4410);
+    assert!(is_silent(&render(&mut sequencer, 4410)));
+
+    // The notes fade in from silence.
+    sequencer.resume(false);
+    let block = render(&mut sequencer, 4410);
+    assert!(block[0].abs() < 1.0E-3);
+    assert!(!is_silent(&block));
+}
+
+#[test]
+fn freeze_keeps_other_channels() {
+    let mut sequencer = create_sequencer();
+    render(&mut sequencer, 22050);
+    sequencer.pause(true);
+    render(&mut sequencer, 4410);
+
+    // The live input on the channels not used by the MIDI file is not frozen.
+    sequencer.get_synthesizer_mut().note_on(1, 60, 100);
+    assert!(!is_silent(&render(&mut sequencer, 4410)));
+}
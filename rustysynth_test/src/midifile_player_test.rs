@@ -0,0 +1,161 @@
+use rustysynth::MidiFile;
+use rustysynth::MidiFilePlayer;
+use std::sync::Arc;
+
+use crate::midifile_util;
+use crate::synthesizer_util;
+
+fn create_midi_file(key: u8, length: u32) -> Arc<MidiFile> {
+    let track = midifile_util::track(&[
+        midifile_util::event(0, &[0x90, key, 100]),
+        midifile_util::event(length, &[0x80, key, 0]),
+        midifile_util::end_of_track(0),
+    ]);
+    let data = midifile_util::midi_file(0, 480, &[track]);
+    Arc::new(MidiFile::new(&mut &data[..]).unwrap())
+}
+
+fn render(player: &mut MidiFilePlayer, length: usize) -> Vec<f32> {
+    let mut left = vec![0_f32; length];
+    let mut right = vec![0_f32; length];
+    player.render(&mut left[..], &mut right[..]);
+    left
+}
+
+fn render_alone(midi_file: &Arc<MidiFile>, channel: i32, length: usize) -> Vec<f32> {
+    let mut player = MidiFilePlayer::new(synthesizer_util::create_synthesizer());
+    player.play(midi_file, &[channel], false);
+    render(&mut player, length)
+}
+
+#[test]
+fn stems_in_sync() {
+    let drums = create_midi_file(60, 960);
+    let strings = create_midi_file(67, 960);
+
+    let mut player = MidiFilePlayer::new(synthesizer_util::create_synthesizer());
+    player.play(&drums, &[0], false);
+    player.play(&strings, &[1], false);
+    let actual = render(&mut player, 44100);
+
+    // The stems must be mixed without any shift in time.
+    let drums_alone = render_alone(&drums, 0, 44100);
+    let strings_alone = render_alone(&strings, 1, 44100);
+    let expected: Vec<f32> = drums_alone
+        .iter()
+        .zip(strings_alone.iter())
+        .map(|(a, b)| a + b)
+        .collect();
+    assert!(expected.iter().any(|value| value.abs() > 1.0E-3));
+    synthesizer_util::assert_same(&actual, &expected);
+}
+
+#[test]
+fn stop_one_stem() {
+    let drums = create_midi_file(60, 960);
+    let strings = create_midi_file(67, 960);
+
+    let mut player = MidiFilePlayer::new(synthesizer_util::create_synthesizer());
+    let id = player.play(&drums, &[0], false);
+    player.play(&strings, &[1], false);
+    player.stop(id);
+
+    assert!(player.end_of_sequence(id));
+    synthesizer_util::assert_same(
+        &render(&mut player, 22050),
+        &render_alone(&strings, 1, 22050),
+    );
+}
+
+#[test]
+fn volume() {
+    let drums = create_midi_file(60, 960);
+    let strings = create_midi_file(67, 960);
+
+    let mut player = MidiFilePlayer::new(synthesizer_util::create_synthesizer());
+    let id = player.play(&drums, &[0], false);
+    player.play(&strings, &[1], false);
+    player.set_volume(id, 0_f32);
+
+    assert_eq!(player.get_volume(id), Some(0_f32));
+    synthesizer_util::assert_same(
+        &render(&mut player, 22050),
+        &render_alone(&strings, 1, 22050),
+    );
+}
+
+#[test]
+fn stinger_is_removed_at_end() {
+    let music = create_midi_file(60, 9600);
+    let stinger = create_midi_file(72, 240);
+
+    let mut player = MidiFilePlayer::new(synthesizer_util::create_synthesizer());
+    let music_id = player.play(&music, &[0], false);
+    let stinger_id = player.play(&stinger, &[1], false);
+    render(&mut player, 4410);
+    assert!(!player.end_of_sequence(stinger_id));
+
+    // The stinger lasts 0.25 seconds.
+    render(&mut player, 22050);
+    assert!(player.end_of_sequence(stinger_id));
+    assert!(!player.end_of_sequence(music_id));
+    assert_eq!(player.get_sequence_ids(), vec![music_id]);
+    assert!(player.get_position(music_id).unwrap() > 0.5);
+}
+
+#[test]
+fn channel_gain_is_restored_at_end() {
+    let music = create_midi_file(60, 480);
+
+    let mut player = MidiFilePlayer::new(synthesizer_util::create_synthesizer());
+    let id = player.play(&music, &[0], true);
+    player.set_loop_count(id, Some(1));
+    player.set_fade_out_duration(id, 0.1);
+    player.set_volume(id, 0.5);
+    render(&mut player, 64 * 1400);
+    assert!(player.get_sequence_ids().is_empty());
+
+    // A note played after the faded sequence ends is not affected by its gain.
+    let mut expected = synthesizer_util::create_synthesizer();
+    expected.note_on(0, 67, 100);
+    player.get_synthesizer_mut().note_on(0, 67, 100);
+    synthesizer_util::assert_same(
+        &render(&mut player, 4410),
+        &synthesizer_util::render(&mut expected, 4410),
+    );
+}
+
+#[test]
+fn ump_channel_map() {
+    let data: Vec<u8> = [
+        b"SMF2CLIP".to_vec(),
+        [0x0030_01E0_u32, 0x4090_3C00, 0xFFFF_0000]
+            .iter()
+            .flat_map(|packet| packet.to_be_bytes())
+            .collect(),
+    ]
+    .concat();
+    let midi_file = Arc::new(MidiFile::new(&mut &data[..]).unwrap());
+
+    let mut settings = synthesizer_util::create_settings();
+    settings.channel_count = 48;
+    let synthesizer = synthesizer_util::create_synthesizer_with_settings(&settings);
+    let mut player = MidiFilePlayer::new(synthesizer);
+    player.play(&midi_file, &[20], false);
+    assert!(synthesizer_util::peak(&render(&mut player, 4410)) > 0.01);
+
+    // The note is played on the channel 20, not on the channel 4,
+    // and stops after the short release of the immediate note-off.
+    player.get_synthesizer_mut().note_off_all_channel(20, true);
+    render(&mut player, 4410);
+    assert!(synthesizer_util::peak(&render(&mut player, 4410)) < 1.0E-6);
+}
+
+#[test]
+#[should_panic]
+fn invalid_channel_map() {
+    let music = create_midi_file(60, 960);
+
+    let mut player = MidiFilePlayer::new(synthesizer_util::create_synthesizer());
+    player.play(&music, &[16], false);
+}
@@ -0,0 +1,90 @@
+#![allow(dead_code)]
+#![allow(unused_imports)]
+
+use rustysynth::MidiFile;
+use rustysynth::MidiFileError;
+
+use crate::midifile_util;
+use crate::soundfont_util;
+
+fn smf() -> Vec<u8> {
+    let track = midifile_util::track(&[
+        midifile_util::event(0, &[0x90, 60, 100]),
+        midifile_util::event(960, &[0x80, 60, 0]),
+        midifile_util::end_of_track(0),
+    ]);
+    midifile_util::midi_file(0, 480, &[track])
+}
+
+fn rmid(chunks: &[Vec<u8>]) -> Vec<u8> {
+    let mut data: Vec<u8> = b"RMID".to_vec();
+    for value in chunks {
+        data.extend_from_slice(value);
+    }
+    soundfont_util::chunk(b"RIFF", &data)
+}
+
+#[test]
+fn rmid_without_sound_font() {
+    let data = rmid(&[
+        soundfont_util::chunk(b"data", &smf()),
+        soundfont_util::chunk(b"ISFT", b"test\0"),
+    ]);
+    let midi_file = MidiFile::new(&mut &data[..]).unwrap();
+    assert!((midi_file.get_length() - 1.0).abs() < 1.0E-9);
+    assert!(midi_file.get_sound_font().is_none());
+    assert_eq!(midi_file.get_bank_offset(), 0);
+}
+
+#[test]
+fn rmid_with_sound_font() {
+    let data = rmid(&[
+        soundfont_util::chunk(b"DBNK", &[2, 0]),
+        soundfont_util::create_sound_font_data(),
+        soundfont_util::chunk(b"data", &smf()),
+    ]);
+    let midi_file = MidiFile::new(&mut &data[..]).unwrap();
+    assert!((midi_file.get_length() - 1.0).abs() < 1.0E-9);
+    let sound_font = midi_file.get_sound_font().unwrap();
+    assert_eq!(sound_font.get_presets().len(), 2);
+    assert_eq!(midi_file.get_bank_offset(), 2);
+
+    // The bank offset defaults to 1.
+    let data = rmid(&[
+        soundfont_util::chunk(b"data", &smf()),
+        soundfont_util::create_sound_font_data(),
+    ]);
+    let midi_file = MidiFile::new(&mut &data[..]).unwrap();
+    assert!(midi_file.get_sound_font().is_some());
+    assert_eq!(midi_file.get_bank_offset(), 1);
+}
+
+#[test]
+fn rmid_with_bank_offset_in_info() {
+    // The DBNK chunk follows the other INFO chunks, one of which has an odd size.
+    let info = soundfont_util::list(
+        b"INFO",
+        &[
+            soundfont_util::chunk(b"INAM", b"Song\0\0"),
+            soundfont_util::chunk(b"ICOP", b"test\0"),
+            soundfont_util::chunk(b"DBNK", &[0, 0]),
+        ],
+    );
+    let data = rmid(&[
+        info,
+        soundfont_util::create_sound_font_data(),
+        soundfont_util::chunk(b"data", &smf()),
+    ]);
+    let midi_file = MidiFile::new(&mut &data[..]).unwrap();
+    assert!(midi_file.get_sound_font().is_some());
+    assert_eq!(midi_file.get_bank_offset(), 0);
+}
+
+#[test]
+fn rmid_without_data() {
+    let data = rmid(&[soundfont_util::create_sound_font_data()]);
+    match MidiFile::new(&mut &data[..]) {
+        Err(MidiFileError::ChunkNotFound(_)) => (),
+        _ => panic!("the data chunk must be required"),
+    }
+}
@@ -0,0 +1,97 @@
+#![allow(dead_code)]
+#![allow(unused_imports)]
+
+use rustysynth::MidiFile;
+use rustysynth::MidiFileSequencer;
+use rustysynth::Synthesizer;
+use rustysynth::SynthesizerSettings;
+use std::sync::Arc;
+
+use crate::midifile_util;
+use crate::soundfont_util;
+
+fn create_sequencer(events: &[Vec<u8>]) -> MidiFileSequencer {
+    let track = midifile_util::track(events);
+    let data = midifile_util::midi_file(0, 480, &[track]);
+    let midi_file = Arc::new(MidiFile::new(&mut &data[..]).unwrap());
+
+    let settings = SynthesizerSettings::new(44100);
+    let synthesizer = Synthesizer::new(&soundfont_util::create_sound_font(), &settings).unwrap();
+    let mut sequencer = MidiFileSequencer::new(synthesizer);
+    sequencer.play(&midi_file, false);
+    sequencer
+}
+
+fn is_silent(sequencer: &mut MidiFileSequencer) -> bool {
+    let mut left = vec![0_f32; 4410];
+    let mut right = vec![0_f32; 4410];
+    sequencer.render(&mut left[..], &mut right[..]);
+    left.iter().all(|value| *value == 0_f32)
+}
+
+fn held_note() -> Vec<Vec<u8>> {
+    vec![
+        midifile_util::event(0, &[0xB0, 7, 100]),
+        midifile_util::event(0, &[0x90, 60, 100]),
+        midifile_util::event(1920, &[0x80, 60, 0]),
+        midifile_util::event(480, &[0x90, 64, 100]),
+        midifile_util::event(480, &[0x80, 64, 0]),
+        midifile_util::end_of_track(0),
+    ]
+}
+
+#[test]
+fn restart_held_notes() {
+    let mut sequencer = create_sequencer(&held_note());
+    sequencer.seek(1.0, true);
+    assert!((sequencer.get_position() - 1.0).abs() < 1.0E-9);
+    assert!(!is_silent(&mut sequencer));
+
+    let mut sequencer = create_sequencer(&held_note());
+    sequencer.seek(1.0, false);
+    assert!(is_silent(&mut sequencer));
+}
+
+#[test]
+fn events_at_position() {
+    // The note starting at the new position is played by the next rendering.
+    let mut sequencer = create_sequencer(&held_note());
+    sequencer.seek(2.5, false);
+    assert!(!is_silent(&mut sequencer));
+
+    // The note ended before the new position is not restarted.
+    let mut sequencer = create_sequencer(&held_note());
+    sequencer.seek(2.2, true);
+    assert!(is_silent(&mut sequencer));
+}
+
+#[test]
+fn hold_pedal() {
+    let events = vec![
+        midifile_util::event(0, &[0xB0, 64, 127]),
+        midifile_util::event(0, &[0x90, 60, 100]),
+        midifile_util::event(480, &[0x80, 60, 0]),
+        midifile_util::event(960, &[0xB0, 64, 0]),
+        midifile_util::end_of_track(480),
+    ];
+
+    // The released note is still sounding since the hold pedal is pressed.
+    let mut sequencer = create_sequencer(&events);
+    sequencer.seek(1.0, true);
+    assert!(!is_silent(&mut sequencer));
+
+    let mut sequencer = create_sequencer(&events);
+    sequencer.seek(1.6, true);
+    assert!(is_silent(&mut sequencer));
+}
+
+#[test]
+fn beyond_end() {
+    let mut sequencer = create_sequencer(&held_note());
+    sequencer.seek(10.0, true);
+    assert!(sequencer.end_of_sequence());
+
+    sequencer.seek(0.0, true);
+    assert!(!sequencer.end_of_sequence());
+    assert!(!is_silent(&mut sequencer));
+}
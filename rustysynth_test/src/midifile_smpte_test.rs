@@ -0,0 +1,53 @@
+#![allow(dead_code)]
+#![allow(unused_imports)]
+
+use rustysynth::MidiFile;
+use rustysynth::MidiFileError;
+
+use crate::midifile_util;
+
+fn smpte_division(frames_per_second: i8, ticks_per_frame: u8) -> i16 {
+    (((-frames_per_second) as u8 as i16) << 8) | ticks_per_frame as i16
+}
+
+fn load(division: i16) -> Result<MidiFile, MidiFileError> {
+    let track = midifile_util::track(&[
+        midifile_util::tempo(0, 250000),
+        midifile_util::event(0, &[0x90, 60, 100]),
+        midifile_util::event(3000, &[0x80, 60, 0]),
+        midifile_util::end_of_track(0),
+    ]);
+    let data = midifile_util::midi_file(0, division, &[track]);
+    MidiFile::new(&mut &data[..])
+}
+
+#[test]
+fn ticks_per_quarter_note() {
+    // 480 ticks per beat at 240 BPM.
+    let midi_file = load(480).unwrap();
+    assert!((midi_file.get_length() - 1.5625).abs() < 1.0E-9);
+}
+
+#[test]
+fn smpte_frame_rates() {
+    // The tempo event must be ignored.
+    let midi_file = load(smpte_division(24, 125)).unwrap();
+    assert!((midi_file.get_length() - 1.0).abs() < 1.0E-9);
+
+    let midi_file = load(smpte_division(25, 40)).unwrap();
+    assert!((midi_file.get_length() - 3.0).abs() < 1.0E-9);
+
+    let midi_file = load(smpte_division(29, 100)).unwrap();
+    assert!((midi_file.get_length() - 1.001).abs() < 1.0E-9);
+
+    let midi_file = load(smpte_division(30, 100)).unwrap();
+    assert!((midi_file.get_length() - 1.0).abs() < 1.0E-9);
+}
+
+#[test]
+fn invalid_smpte_frame_rate() {
+    match load(smpte_division(23, 100)) {
+        Err(MidiFileError::InvalidTimeDivision(_)) => (),
+        _ => panic!("an invalid frame rate must be rejected"),
+    }
+}
@@ -0,0 +1,107 @@
+use rustysynth::MidiFile;
+use rustysynth::MidiFileSequencer;
+use rustysynth::Synthesizer;
+use std::sync::Arc;
+
+use crate::midifile_util;
+use crate::synthesizer_util;
+
+fn create_midi_file() -> Arc<MidiFile> {
+    let track = midifile_util::track(&[
+        midifile_util::event(0, &[0x90, 60, 100]),
+        midifile_util::event(1920, &[0x80, 60, 0]),
+        midifile_util::end_of_track(0),
+    ]);
+    let data = midifile_util::midi_file(0, 480, &[track]);
+    Arc::new(MidiFile::new(&mut &data[..]).unwrap())
+}
+
+fn render<S: std::borrow::BorrowMut<Synthesizer>>(
+    sequencer: &mut MidiFileSequencer<S>,
+    length: usize,
+) -> Vec<f32> {
+    let mut left = vec![0_f32; length];
+    let mut right = vec![0_f32; length];
+    sequencer.render(&mut left[..], &mut right[..]);
+    left
+}
+
+#[test]
+fn master_volume_during_playback() {
+    let midi_file = create_midi_file();
+
+    let mut sequencer1 = MidiFileSequencer::new(synthesizer_util::create_synthesizer());
+    sequencer1.play(&midi_file, false);
+    let mut sequencer2 = MidiFileSequencer::new(synthesizer_util::create_synthesizer());
+    sequencer2.play(&midi_file, false);
+
+    // The length is a multiple of the block size to apply the new volume from the next sample.
+    render(&mut sequencer1, 4416);
+    render(&mut sequencer2, 4416);
+
+    let volume = sequencer2.get_synthesizer().get_master_volume();
+    sequencer2
+        .get_synthesizer_mut()
+        .set_master_volume(volume / 2.0);
+
+    let block1 = render(&mut sequencer1, 4410);
+    let block2 = render(&mut sequencer2, 4410);
+    for (value1, value2) in block1.iter().zip(block2.iter()) {
+        assert!((value1 / 2.0 - value2).abs() < 1.0E-6);
+    }
+}
+
+#[test]
+fn into_synthesizer() {
+    let mut sequencer = MidiFileSequencer::new(synthesizer_util::create_synthesizer());
+    sequencer.play(&create_midi_file(), false);
+    render(&mut sequencer, 4410);
+
+    let mut synthesizer = sequencer.into_synthesizer();
+    let mut left = vec![0_f32; 4410];
+    let mut right = vec![0_f32; 4410];
+    synthesizer.render(&mut left[..], &mut right[..]);
+    assert!(left.iter().any(|value| value.abs() > 1.0E-3));
+}
+
+#[test]
+fn borrowed_synthesizer_with_live_input() {
+    let midi_file = create_midi_file();
+    let mut synthesizer = synthesizer_util::create_synthesizer();
+
+    let song = {
+        let mut sequencer = MidiFileSequencer::new(&mut synthesizer);
+        sequencer.play(&midi_file, false);
+        render(&mut sequencer, 4410)
+    };
+
+    // The live note is layered on top of the song.
+    let mut sequencer = MidiFileSequencer::new(&mut synthesizer);
+    sequencer.play(&midi_file, false);
+    sequencer.get_synthesizer_mut().note_on(1, 72, 100);
+    let layered = render(&mut sequencer, 4410);
+    assert!(song
+        .iter()
+        .zip(layered.iter())
+        .any(|(value1, value2)| (value1 - value2).abs() > 1.0E-3));
+
+    drop(sequencer);
+    assert_eq!(synthesizer.get_sample_rate(), 44100);
+}
+
+#[test]
+fn pause_keeps_live_timed_messages() {
+    let mut synthesizer = synthesizer_util::create_synthesizer();
+    let mut sequencer = MidiFileSequencer::new(&mut synthesizer);
+    sequencer.play(&create_midi_file(), false);
+    render(&mut sequencer, 4410);
+
+    // The note given with a frame offset is not moved forward by the pause.
+    sequencer
+        .get_synthesizer_mut()
+        .process_midi_message_at(2000, 1, 0x90, 72, 100);
+    sequencer.pause(true);
+    let block = render(&mut sequencer, 4410);
+    assert!(block[1000..1900].iter().all(|value| value.abs() < 1.0E-6));
+    assert!(block[2000..].iter().any(|value| value.abs() > 1.0E-3));
+}
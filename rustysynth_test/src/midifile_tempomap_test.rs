@@ -0,0 +1,61 @@
+#![allow(dead_code)]
+#![allow(unused_imports)]
+
+use rustysynth::MidiFile;
+
+use crate::midifile_util;
+
+fn load() -> MidiFile {
+    let conductor = midifile_util::track(&[
+        midifile_util::tempo(0, 500000),
+        midifile_util::meta_event(0, 0x58, &[3, 2, 24, 8]),
+        midifile_util::tempo(1440, 250000),
+        midifile_util::meta_event(1440, 0x58, &[6, 3, 24, 8]),
+        midifile_util::end_of_track(0),
+    ]);
+    let notes = midifile_util::track(&[
+        midifile_util::event(1920, &[0x90, 60, 100]),
+        midifile_util::event(2400, &[0x80, 60, 0]),
+        midifile_util::end_of_track(0),
+    ]);
+    let data = midifile_util::midi_file(1, 480, &[conductor, notes]);
+    MidiFile::new(&mut &data[..]).unwrap()
+}
+
+#[test]
+fn tick_to_seconds() {
+    let midi_file = load();
+    let tempo_map = midi_file.get_tempo_map();
+
+    assert_eq!(tempo_map.get_resolution(), 480);
+    assert!(tempo_map.get_frames_per_second().is_none());
+    assert_eq!(tempo_map.get_tempo_changes().len(), 2);
+    assert_eq!(tempo_map.get_tempo_changes()[1].get_tick(), 1440);
+    assert!((tempo_map.get_tempo_changes()[1].get_time() - 1.5).abs() < 1.0E-9);
+
+    assert!((tempo_map.ticks_to_seconds(960.0) - 1.0).abs() < 1.0E-9);
+    assert!((tempo_map.ticks_to_seconds(1920.0) - 1.75).abs() < 1.0E-9);
+    assert!((tempo_map.seconds_to_ticks(1.0) - 960.0).abs() < 1.0E-6);
+    assert!((tempo_map.seconds_to_ticks(1.75) - 1920.0).abs() < 1.0E-6);
+    assert!((tempo_map.get_tempo(0.0) - 120.0).abs() < 1.0E-9);
+    assert!((tempo_map.get_tempo(2000.0) - 240.0).abs() < 1.0E-9);
+
+    // The tempo events must not affect the note timing after the conversion.
+    assert!((midi_file.get_length() - 3.0).abs() < 1.0E-9);
+}
+
+#[test]
+fn bar_beat_tick() {
+    let midi_file = load();
+    let tempo_map = midi_file.get_tempo_map();
+
+    assert_eq!(tempo_map.ticks_to_bar_beat_tick(0.0), (1, 1, 0));
+    assert_eq!(tempo_map.ticks_to_bar_beat_tick(1920.0), (2, 2, 0));
+    assert_eq!(tempo_map.seconds_to_bar_beat_tick(1.75), (2, 2, 0));
+
+    let signature = tempo_map.get_time_signature(3000.0);
+    assert_eq!(signature.get_numerator(), 6);
+    assert_eq!(signature.get_denominator(), 8);
+    assert_eq!(signature.get_bar(), 3);
+    assert_eq!(tempo_map.ticks_to_bar_beat_tick(4570.0), (4, 2, 10));
+}
@@ -0,0 +1,82 @@
+use rustysynth::MidiFile;
+use rustysynth::MidiFileSequencer;
+use rustysynth::Synthesizer;
+use std::sync::Arc;
+
+use crate::midifile_util;
+use crate::synthesizer_util;
+
+fn create_synthesizer(block_size: usize) -> Synthesizer {
+    let mut settings = synthesizer_util::create_settings();
+    settings.block_size = block_size;
+    synthesizer_util::create_synthesizer_with_settings(&settings)
+}
+
+fn get_first_sound(block: &[f32]) -> Option<usize> {
+    block.iter().position(|value| value.abs() > 1.0E-6)
+}
+
+// The default envelope of the SoundFont has a delay and an attack of 1 ms each.
+const ONSET: usize = 128;
+
+#[test]
+fn timestamped_message() {
+    for frame_offset in [0, 1, 63, 100, 1000] {
+        let mut synthesizer = create_synthesizer(64);
+        synthesizer.process_midi_message_at(frame_offset, 0, 0x90, 60, 100);
+
+        let mut left = vec![0_f32; 2048];
+        let mut right = vec![0_f32; 2048];
+        synthesizer.render(&mut left[..], &mut right[..]);
+
+        let first = get_first_sound(&left).unwrap();
+        assert!(first >= frame_offset && first < frame_offset + ONSET);
+    }
+}
+
+#[test]
+fn timestamped_message_across_renders() {
+    let mut synthesizer = create_synthesizer(64);
+    let mut left = vec![0_f32; 1000];
+    let mut right = vec![0_f32; 1000];
+
+    // The offset is counted from the beginning of the next render.
+    synthesizer.render(&mut left[..128], &mut right[..128]);
+    synthesizer.process_midi_message_at(300, 0, 0x90, 60, 100);
+    synthesizer.render(&mut left[..], &mut right[..]);
+
+    let first = get_first_sound(&left).unwrap();
+    assert!((300..300 + ONSET).contains(&first));
+}
+
+#[test]
+fn sequencer_events_independent_of_block_size() {
+    let track = midifile_util::track(&[
+        midifile_util::event(0, &[0xC0, 0]),
+        midifile_util::event(240, &[0x90, 60, 100]),
+        midifile_util::event(240, &[0x80, 60, 0]),
+        midifile_util::end_of_track(0),
+    ]);
+    let data = midifile_util::midi_file(0, 480, &[track]);
+    let midi_file = Arc::new(MidiFile::new(&mut &data[..]).unwrap());
+
+    let mut firsts: Vec<usize> = Vec::new();
+    for block_size in [64, 100, 1024] {
+        let mut sequencer = MidiFileSequencer::new(create_synthesizer(block_size));
+        sequencer.play(&midi_file, false);
+
+        let mut left = vec![0_f32; 44100];
+        let mut right = vec![0_f32; 44100];
+        for chunk in 0..(44100 / 441) {
+            let range = chunk * 441..(chunk + 1) * 441;
+            sequencer.render(&mut left[range.clone()], &mut right[range]);
+        }
+
+        // The note starts at 0.25 seconds.
+        firsts.push(get_first_sound(&left).unwrap());
+    }
+
+    for first in firsts {
+        assert!((11025..11025 + ONSET).contains(&first));
+    }
+}
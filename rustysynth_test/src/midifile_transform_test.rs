@@ -0,0 +1,176 @@
+#![allow(dead_code)]
+#![allow(unused_imports)]
+
+use rustysynth::MidiFile;
+use rustysynth::MidiFileSequencer;
+use rustysynth::SequencerEvent;
+use rustysynth::Synthesizer;
+use rustysynth::SynthesizerSettings;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use crate::midifile_util;
+use crate::soundfont_util;
+
+fn load() -> MidiFile {
+    let track = midifile_util::track(&[
+        midifile_util::tempo(0, 500000),
+        midifile_util::event(0, &[0xC0, 10]),
+        midifile_util::event(0, &[0x90, 60, 100]),
+        midifile_util::tempo(960, 250000),
+        midifile_util::event(0, &[0x80, 60, 0]),
+        midifile_util::event(0, &[0x99, 36, 100]),
+        midifile_util::event(960, &[0x89, 36, 0]),
+        midifile_util::end_of_track(0),
+    ]);
+    let data = midifile_util::midi_file(0, 480, &[track]);
+    MidiFile::new(&mut &data[..]).unwrap()
+}
+
+// Gets the channel messages sent by the sequencer as (channel, command, data1, data2).
+fn get_messages(midi_file: MidiFile) -> Vec<(i32, i32, i32, i32)> {
+    let mut settings = SynthesizerSettings::new(44100);
+    settings.enable_reverb_and_chorus = false;
+    let synthesizer = Synthesizer::new(&soundfont_util::create_sound_font(), &settings).unwrap();
+    let mut sequencer = MidiFileSequencer::new(synthesizer);
+
+    let messages = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&messages);
+    sequencer.set_event_callback(move |event, _| {
+        if let SequencerEvent::Message {
+            channel,
+            command,
+            data1,
+            data2,
+            ..
+        } = event
+        {
+            sink.lock().unwrap().push((channel, command, data1, data2));
+        }
+    });
+    sequencer.play(&Arc::new(midi_file), false);
+
+    let mut left = vec![0_f32; 2 * 44100];
+    let mut right = vec![0_f32; 2 * 44100];
+    sequencer.render(&mut left[..], &mut right[..]);
+
+    let messages = messages.lock().unwrap();
+    messages.clone()
+}
+
+#[test]
+fn scale_tempo() {
+    let midi_file = load().scale_tempo(2.0);
+    assert!((midi_file.get_length() - 0.75).abs() < 1.0E-9);
+
+    let tempo_map = midi_file.get_tempo_map();
+    assert!((tempo_map.get_tempo(0.0) - 240.0).abs() < 1.0E-9);
+    assert!((tempo_map.ticks_to_seconds(1920.0) - 0.75).abs() < 1.0E-9);
+}
+
+#[test]
+fn trim() {
+    let midi_file = load().trim(0.5, 1.25);
+    assert!((midi_file.get_length() - 0.75).abs() < 1.0E-9);
+
+    let tempo_map = midi_file.get_tempo_map();
+    assert_eq!(tempo_map.get_tempo_changes().len(), 2);
+    assert!((tempo_map.get_tempo(0.0) - 120.0).abs() < 1.0E-9);
+    assert!((tempo_map.ticks_to_seconds(480.0) - 0.5).abs() < 1.0E-9);
+}
+
+#[test]
+fn message_transforms_keep_timing() {
+    let midi_file = load();
+    let transformed = midi_file
+        .transpose(12)
+        .remap_channels(|channel| if channel == 9 { 9 } else { channel + 1 })
+        .scale_velocity(0.5)
+        .map_velocity(|velocity| velocity + 10)
+        .override_program(1, 20)
+        .filter_events(|channel, _command| channel != 9);
+    assert!((transformed.get_length() - midi_file.get_length()).abs() < 1.0E-9);
+}
+
+#[test]
+fn transpose() {
+    // The percussion channel is not transposed.
+    let messages = get_messages(load().transpose(12));
+    assert_eq!(
+        messages,
+        vec![
+            (0, 0xC0, 10, 0),
+            (0, 0x90, 72, 100),
+            (0, 0x80, 72, 0),
+            (9, 0x90, 36, 100),
+            (9, 0x80, 36, 0),
+        ]
+    );
+
+    // The notes out of the key range are removed.
+    let messages = get_messages(load().transpose(100));
+    assert_eq!(
+        messages,
+        vec![(0, 0xC0, 10, 0), (9, 0x90, 36, 100), (9, 0x80, 36, 0)]
+    );
+}
+
+#[test]
+fn remap_channels() {
+    let messages = get_messages(load().remap_channels(|channel| match channel {
+        0 => 3,
+        _ => 300,
+    }));
+    assert_eq!(
+        messages,
+        vec![(3, 0xC0, 10, 0), (3, 0x90, 60, 100), (3, 0x80, 60, 0)]
+    );
+}
+
+#[test]
+fn scale_velocity() {
+    let messages = get_messages(
+        load()
+            .scale_velocity(0.5)
+            .map_velocity(|velocity| velocity + 10),
+    );
+    assert_eq!(
+        messages,
+        vec![
+            (0, 0xC0, 10, 0),
+            (0, 0x90, 60, 60),
+            (0, 0x80, 60, 0),
+            (9, 0x90, 36, 60),
+            (9, 0x80, 36, 0),
+        ]
+    );
+
+    // The velocities are clamped.
+    let messages = get_messages(load().scale_velocity(0.0));
+    assert_eq!(messages[1], (0, 0x90, 60, 1));
+}
+
+#[test]
+fn override_program() {
+    let messages = get_messages(load().override_program(0, 20));
+    assert_eq!(&messages[0..2], &[(0, 0xC0, 20, 0), (0, 0xC0, 20, 0)]);
+}
+
+#[test]
+#[should_panic]
+fn override_program_invalid_channel() {
+    load().override_program(250, 0);
+}
+
+#[test]
+#[should_panic]
+fn override_program_invalid_program() {
+    load().override_program(0, 128);
+}
+
+#[test]
+fn filter_events() {
+    let messages =
+        get_messages(load().filter_events(|channel, command| channel != 9 && command != 0xC0));
+    assert_eq!(messages, vec![(0, 0x90, 60, 100), (0, 0x80, 60, 0)]);
+}
@@ -0,0 +1,210 @@
+#![allow(dead_code)]
+#![allow(unused_imports)]
+
+use rustysynth::MidiFile;
+use rustysynth::MidiFileSequencer;
+use rustysynth::MidiFileTransition;
+use rustysynth::SequencerEvent;
+use rustysynth::Synthesizer;
+use rustysynth::SynthesizerSettings;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use crate::midifile_util;
+use crate::soundfont_util;
+
+type EventLog = Arc<Mutex<Vec<(SequencerEvent, usize)>>>;
+
+// A note held for 8 bars in 4/4 at 120 BPM, which has a marker at 1.5 seconds.
+fn create_current_file(numerator: u8) -> Arc<MidiFile> {
+    let track = midifile_util::track(&[
+        midifile_util::meta_event(0, 0x58, &[numerator, 2, 24, 8]),
+        midifile_util::event(0, &[0x90, 60, 100]),
+        midifile_util::meta_event(1440, 0x06, b"Chorus"),
+        midifile_util::event(13920, &[0x80, 60, 0]),
+        midifile_util::end_of_track(0),
+    ]);
+    let data = midifile_util::midi_file(0, 480, &[track]);
+    Arc::new(MidiFile::new(&mut &data[..]).unwrap())
+}
+
+fn create_next_file(key: u8) -> Arc<MidiFile> {
+    let track = midifile_util::track(&[
+        midifile_util::event(0, &[0x91, key, 100]),
+        midifile_util::event(1920, &[0x81, key, 0]),
+        midifile_util::end_of_track(0),
+    ]);
+    let data = midifile_util::midi_file(0, 480, &[track]);
+    Arc::new(MidiFile::new(&mut &data[..]).unwrap())
+}
+
+fn create_sequencer(midi_file: &Arc<MidiFile>) -> (MidiFileSequencer, EventLog) {
+    let mut settings = SynthesizerSettings::new(44100);
+    settings.enable_reverb_and_chorus = false;
+    let synthesizer = Synthesizer::new(&soundfont_util::create_sound_font(), &settings).unwrap();
+    let mut sequencer = MidiFileSequencer::new(synthesizer);
+
+    let events: EventLog = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&events);
+    sequencer.set_event_callback(move |event, offset| sink.lock().unwrap().push((event, offset)));
+    sequencer.play(midi_file, false);
+
+    (sequencer, events)
+}
+
+fn render(sequencer: &mut MidiFileSequencer, length: usize) -> Vec<f32> {
+    let mut left = vec![0_f32; length];
+    let mut right = vec![0_f32; length];
+    sequencer.render(&mut left[..], &mut right[..]);
+    left
+}
+
+// Queues the next MIDI file at 0.3 seconds, and gets the position of the transition.
+fn get_transition_position(midi_file: &Arc<MidiFile>, transition: MidiFileTransition) -> usize {
+    let (mut sequencer, events) = create_sequencer(midi_file);
+    render(&mut sequencer, 13230);
+    sequencer.queue(&create_next_file(72), false, transition);
+    render(&mut sequencer, 4 * 44100);
+
+    let events = events.lock().unwrap();
+    let (_, offset) = events
+        .iter()
+        .find(|(event, _)| *event == SequencerEvent::Transition)
+        .unwrap();
+
+    // The first note of the queued MIDI file is played at the transition.
+    assert!(events.iter().any(|(event, value)| {
+        matches!(
+            event,
+            SequencerEvent::Message {
+                command: 0x90,
+                data1: 72,
+                ..
+            }
+        ) && value == offset
+    }));
+
+    13230 + offset
+}
+
+fn rms(data: &[f32]) -> f32 {
+    (data.iter().map(|value| value * value).sum::<f32>() / data.len() as f32).sqrt()
+}
+
+#[test]
+fn transition_points() {
+    let midi_file = create_current_file(4);
+    assert_eq!(
+        get_transition_position(&midi_file, MidiFileTransition::NextBeat),
+        22050
+    );
+    assert_eq!(
+        get_transition_position(&midi_file, MidiFileTransition::NextBar),
+        88200
+    );
+    assert_eq!(
+        get_transition_position(&midi_file, MidiFileTransition::NextMarker),
+        66150
+    );
+
+    // The immediate transition occurs at the beginning of the next block.
+    let position = get_transition_position(&midi_file, MidiFileTransition::Immediate);
+    assert!((13230..13230 + 64).contains(&position));
+}
+
+#[test]
+fn time_signature() {
+    let midi_file = create_current_file(3);
+    assert_eq!(
+        get_transition_position(&midi_file, MidiFileTransition::NextBar),
+        66150
+    );
+}
+
+#[test]
+fn end_before_transition_point() {
+    // Without markers, the transition occurs at the end of the MIDI file.
+    let track = midifile_util::track(&[
+        midifile_util::event(0, &[0x90, 60, 100]),
+        midifile_util::event(960, &[0x80, 60, 0]),
+        midifile_util::end_of_track(0),
+    ]);
+    let data = midifile_util::midi_file(0, 480, &[track]);
+    let midi_file = Arc::new(MidiFile::new(&mut &data[..]).unwrap());
+
+    assert_eq!(
+        get_transition_position(&midi_file, MidiFileTransition::NextMarker),
+        44100
+    );
+}
+
+#[test]
+fn queued_file_plays_to_end() {
+    let (mut sequencer, _) = create_sequencer(&create_current_file(4));
+    render(&mut sequencer, 4410);
+    sequencer.queue(&create_next_file(72), false, MidiFileTransition::NextBar);
+
+    render(&mut sequencer, 88200 - 4410);
+    assert!(!sequencer.end_of_sequence());
+
+    // The queued MIDI file lasts 2 seconds.
+    render(&mut sequencer, 88200 + 64);
+    assert!(sequencer.end_of_sequence());
+}
+
+#[test]
+fn tail_handling() {
+    // The silent MIDI file is queued to hear only the tail of the previous one.
+    let track = midifile_util::track(&[midifile_util::end_of_track(9600)]);
+    let data = midifile_util::midi_file(0, 480, &[track]);
+    let silence = Arc::new(MidiFile::new(&mut &data[..]).unwrap());
+
+    // Gets the waveforms just before and after the transition at 0.5 seconds.
+    let tail = |crossfade_duration: f64, release_tail: bool| {
+        let (mut sequencer, _) = create_sequencer(&create_current_file(4));
+        sequencer.set_crossfade_duration(crossfade_duration);
+        sequencer.set_release_tail(release_tail);
+        render(&mut sequencer, 13230);
+        sequencer.queue(&silence, false, MidiFileTransition::NextBeat);
+        let before = render(&mut sequencer, 8820);
+        let after = render(&mut sequencer, 44100);
+        (rms(&before[8820 - 441..]), after)
+    };
+
+    // The released notes stop after the short release of the instrument.
+    let (before, after) = tail(0.0, true);
+    assert!(before > 0.01);
+    assert!(rms(&after[2205..]) < 1.0E-6);
+
+    let (_, after) = tail(0.0, false);
+    assert!(rms(&after[64..]) < 1.0E-6);
+
+    // The sustained notes fade out over the crossfade.
+    let (_, after) = tail(0.5, false);
+    let middle = rms(&after[8820..11025]);
+    assert!(middle > 0.2 * before && middle < 0.8 * before);
+    assert!(rms(&after[22050 + 128..]) < 1.0E-6);
+}
+
+#[test]
+fn crossfade_fades_in() {
+    let (mut sequencer, _) = create_sequencer(&create_current_file(4));
+    sequencer.set_crossfade_duration(0.5);
+    sequencer.set_release_tail(true);
+    render(&mut sequencer, 13230);
+    sequencer.queue(&create_next_file(72), false, MidiFileTransition::NextBeat);
+    render(&mut sequencer, 8820);
+
+    // The notes of the queued MIDI file reach the full volume after the crossfade.
+    let after = render(&mut sequencer, 44100);
+    let start = rms(&after[441..882]);
+    let end = rms(&after[22050 + 441..22050 + 882]);
+    assert!(start < 0.1 * end);
+}
+
+#[test]
+#[should_panic]
+fn negative_crossfade_duration() {
+    let (mut sequencer, _) = create_sequencer(&create_current_file(4));
+    sequencer.set_crossfade_duration(-1.0);
+}
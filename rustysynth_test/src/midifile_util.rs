@@ -0,0 +1,56 @@
+#![allow(dead_code)]
+#![allow(unused_imports)]
+
+pub fn variable_length(value: u32) -> Vec<u8> {
+    let mut data: Vec<u8> = vec![(value & 0x7F) as u8];
+    let mut value = value >> 7;
+    while value > 0 {
+        data.insert(0, ((value & 0x7F) | 0x80) as u8);
+        value >>= 7;
+    }
+    data
+}
+
+pub fn event(delta: u32, data: &[u8]) -> Vec<u8> {
+    let mut event = variable_length(delta);
+    event.extend_from_slice(data);
+    event
+}
+
+pub fn meta_event(delta: u32, meta_type: u8, data: &[u8]) -> Vec<u8> {
+    let mut event = variable_length(delta);
+    event.push(0xFF);
+    event.push(meta_type);
+    event.extend(variable_length(data.len() as u32));
+    event.extend_from_slice(data);
+    event
+}
+
+pub fn tempo(delta: u32, microseconds_per_beat: u32) -> Vec<u8> {
+    let bytes = microseconds_per_beat.to_be_bytes();
+    meta_event(delta, 0x51, &bytes[1..4])
+}
+
+pub fn end_of_track(delta: u32) -> Vec<u8> {
+    meta_event(delta, 0x2F, &[])
+}
+
+pub fn track(events: &[Vec<u8>]) -> Vec<u8> {
+    let data: Vec<u8> = events.concat();
+    let mut track: Vec<u8> = b"MTrk".to_vec();
+    track.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    track.extend(data);
+    track
+}
+
+pub fn midi_file(format: i16, division: i16, tracks: &[Vec<u8>]) -> Vec<u8> {
+    let mut data: Vec<u8> = b"MThd".to_vec();
+    data.extend_from_slice(&6_u32.to_be_bytes());
+    data.extend_from_slice(&format.to_be_bytes());
+    data.extend_from_slice(&(tracks.len() as i16).to_be_bytes());
+    data.extend_from_slice(&division.to_be_bytes());
+    for track in tracks {
+        data.extend_from_slice(track);
+    }
+    data
+}
@@ -0,0 +1,168 @@
+#![allow(dead_code)]
+#![allow(unused_imports)]
+
+use rustysynth::SoundFont;
+use std::sync::Arc;
+
+pub fn chunk(id: &[u8; 4], data: &[u8]) -> Vec<u8> {
+    let mut chunk: Vec<u8> = id.to_vec();
+    chunk.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    chunk.extend_from_slice(data);
+    if data.len() % 2 == 1 {
+        chunk.push(0);
+    }
+    chunk
+}
+
+pub fn list(list_type: &[u8; 4], chunks: &[Vec<u8>]) -> Vec<u8> {
+    let mut data: Vec<u8> = list_type.to_vec();
+    for value in chunks {
+        data.extend_from_slice(value);
+    }
+    chunk(b"LIST", &data)
+}
+
+fn name(value: &str) -> Vec<u8> {
+    let mut data = value.as_bytes().to_vec();
+    data.resize(20, 0);
+    data
+}
+
+fn preset_header(preset: &str, patch: u16, bank: u16, bag_index: u16) -> Vec<u8> {
+    let mut data = name(preset);
+    data.extend_from_slice(&patch.to_le_bytes());
+    data.extend_from_slice(&bank.to_le_bytes());
+    data.extend_from_slice(&bag_index.to_le_bytes());
+    data.extend_from_slice(&[0; 12]);
+    data
+}
+
+fn instrument_header(instrument: &str, bag_index: u16) -> Vec<u8> {
+    let mut data = name(instrument);
+    data.extend_from_slice(&bag_index.to_le_bytes());
+    data
+}
+
+fn bag(generator_index: u16, modulator_index: u16) -> Vec<u8> {
+    let mut data = generator_index.to_le_bytes().to_vec();
+    data.extend_from_slice(&modulator_index.to_le_bytes());
+    data
+}
+
+fn generator(generator_type: u16, value: u16) -> Vec<u8> {
+    let mut data = generator_type.to_le_bytes().to_vec();
+    data.extend_from_slice(&value.to_le_bytes());
+    data
+}
+
+/// Creates a modulator record with the linear transform.
+pub fn modulator(source: u16, destination: u16, amount: i16, amount_source: u16) -> Vec<u8> {
+    let mut data = source.to_le_bytes().to_vec();
+    data.extend_from_slice(&destination.to_le_bytes());
+    data.extend_from_slice(&amount.to_le_bytes());
+    data.extend_from_slice(&amount_source.to_le_bytes());
+    data.extend_from_slice(&0_u16.to_le_bytes());
+    data
+}
+
+fn sample_header(sample: &str, start: u32, end: u32, sample_rate: u32) -> Vec<u8> {
+    let mut data = name(sample);
+    data.extend_from_slice(&start.to_le_bytes());
+    data.extend_from_slice(&end.to_le_bytes());
+    data.extend_from_slice(&start.to_le_bytes());
+    data.extend_from_slice(&end.to_le_bytes());
+    data.extend_from_slice(&sample_rate.to_le_bytes());
+    data.push(69);
+    data.push(0);
+    data.extend_from_slice(&0_u16.to_le_bytes());
+    data.extend_from_slice(&1_u16.to_le_bytes());
+    data
+}
+
+/// Creates a minimal SoundFont which has a looped 440 Hz square wave
+/// as the preset 0:0 and the percussion preset 128:0.
+pub fn create_sound_font_data() -> Vec<u8> {
+    create_sound_font_data_with_modulators(&[])
+}
+
+/// Creates the same SoundFont as `create_sound_font_data`,
+/// with the given modulators in the instrument zone.
+pub fn create_sound_font_data_with_modulators(modulators: &[Vec<u8>]) -> Vec<u8> {
+    create_sound_font_data_with_modulator_list(modulators, true)
+}
+
+/// Creates the same SoundFont as `create_sound_font_data_with_modulators`,
+/// without the terminator at the end of the instrument modulator list.
+pub fn create_sound_font_data_without_modulator_terminator(modulators: &[Vec<u8>]) -> Vec<u8> {
+    create_sound_font_data_with_modulator_list(modulators, false)
+}
+
+fn create_sound_font_data_with_modulator_list(modulators: &[Vec<u8>], terminated: bool) -> Vec<u8> {
+    let sample_rate: u32 = 44000;
+    let sample_length: u32 = 1000;
+
+    let mut samples: Vec<u8> = Vec::new();
+    for t in 0..sample_length {
+        let value: i16 = if (t / 50) % 2 == 0 { 8192 } else { -8192 };
+        samples.extend_from_slice(&value.to_le_bytes());
+    }
+    samples.extend_from_slice(&[0; 92]);
+
+    let info = list(
+        b"INFO",
+        &[
+            chunk(b"ifil", &[2, 0, 1, 0]),
+            chunk(b"isng", b"EMU8000\0"),
+            chunk(b"INAM", b"Test\0\0"),
+        ],
+    );
+
+    let sdta = list(b"sdta", &[chunk(b"smpl", &samples)]);
+
+    let phdr = [
+        preset_header("Square", 0, 0, 0),
+        preset_header("Drums", 0, 128, 1),
+        preset_header("EOP", 0, 0, 2),
+    ]
+    .concat();
+    let pbag = [bag(0, 0), bag(1, 0), bag(2, 0)].concat();
+    let pgen = [generator(41, 0), generator(41, 0), generator(0, 0)].concat();
+    let inst = [instrument_header("Square", 0), instrument_header("EOI", 1)].concat();
+    let ibag = [bag(0, 0), bag(2, modulators.len() as u16)].concat();
+    let mut imod = modulators.concat();
+    if terminated {
+        imod.extend_from_slice(&[0; 10]);
+    }
+    let igen = [generator(54, 1), generator(53, 0), generator(0, 0)].concat();
+    let shdr = [
+        sample_header("Square", 0, sample_length, sample_rate),
+        sample_header("EOS", 0, 0, 0),
+    ]
+    .concat();
+
+    let pdta = list(
+        b"pdta",
+        &[
+            chunk(b"phdr", &phdr),
+            chunk(b"pbag", &pbag),
+            chunk(b"pmod", &[0; 10]),
+            chunk(b"pgen", &pgen),
+            chunk(b"inst", &inst),
+            chunk(b"ibag", &ibag),
+            chunk(b"imod", &imod),
+            chunk(b"igen", &igen),
+            chunk(b"shdr", &shdr),
+        ],
+    );
+
+    let mut data: Vec<u8> = b"sfbk".to_vec();
+    data.extend(info);
+    data.extend(sdta);
+    data.extend(pdta);
+    chunk(b"RIFF", &data)
+}
+
+pub fn create_sound_font() -> Arc<SoundFont> {
+    let data = create_sound_font_data();
+    Arc::new(SoundFont::new(&mut &data[..]).unwrap())
+}
@@ -0,0 +1,180 @@
+use rustysynth::Synthesizer;
+
+use crate::synthesizer_util;
+
+// Estimates the frequency of the square wave from the zero crossings.
+fn frequency(data: &[f32]) -> f32 {
+    let crossings = data
+        .windows(2)
+        .filter(|pair| (pair[0] < 0_f32) != (pair[1] < 0_f32))
+        .count();
+    44100_f32 * crossings as f32 / (2 * data.len()) as f32
+}
+
+fn set_portamento_time(synthesizer: &mut Synthesizer, milliseconds: i32) {
+    synthesizer.process_midi_message(0, 0xB0, 0x05, milliseconds >> 7);
+    synthesizer.process_midi_message(0, 0xB0, 0x25, milliseconds & 0x7F);
+}
+
+#[test]
+fn sostenuto_pedal() {
+    let mut expected = synthesizer_util::create_synthesizer();
+    expected.note_on(0, 60, 100);
+
+    // Only the note held when the pedal is pressed is sustained.
+    let mut actual = synthesizer_util::create_synthesizer();
+    actual.note_on(0, 60, 100);
+    actual.process_midi_message(0, 0xB0, 0x42, 127);
+    actual.note_on(0, 64, 100);
+    actual.note_off(0, 60);
+    actual.note_off(0, 64);
+
+    let expected = synthesizer_util::render(&mut expected, 8820);
+    let sustained = synthesizer_util::render(&mut actual, 8820);
+    synthesizer_util::assert_same(&sustained[4410..], &expected[4410..]);
+
+    // The note is released with the pedal.
+    actual.process_midi_message(0, 0xB0, 0x42, 0);
+    synthesizer_util::render(&mut actual, 4410);
+    assert!(synthesizer_util::rms(&synthesizer_util::render(&mut actual, 4410)) < 1.0E-4);
+}
+
+#[test]
+fn soft_pedal() {
+    let mut expected = synthesizer_util::create_synthesizer();
+    expected.note_on(0, 60, 100);
+
+    let mut actual = synthesizer_util::create_synthesizer();
+    actual.process_midi_message(0, 0xB0, 0x43, 127);
+    actual.note_on(0, 60, 100);
+
+    let ratio = synthesizer_util::rms(&synthesizer_util::render(&mut actual, 4410))
+        / synthesizer_util::rms(&synthesizer_util::render(&mut expected, 4410));
+    assert!(ratio < 0.9);
+
+    // The sounding notes are not affected by the pedal.
+    actual.process_midi_message(0, 0xB0, 0x43, 0);
+    expected.process_midi_message(0, 0xB0, 0x43, 127);
+    let ratio = synthesizer_util::rms(&synthesizer_util::render(&mut actual, 4410))
+        / synthesizer_util::rms(&synthesizer_util::render(&mut expected, 4410));
+    assert!(ratio < 0.9);
+}
+
+#[test]
+fn portamento() {
+    let mut synthesizer = synthesizer_util::create_synthesizer();
+    set_portamento_time(&mut synthesizer, 200);
+    synthesizer.process_midi_message(0, 0xB0, 0x41, 127);
+    synthesizer.note_on(0, 60, 100);
+    synthesizer.note_off(0, 60);
+    synthesizer_util::render(&mut synthesizer, 4410);
+
+    // The pitch glides from the key 60 (261.6 Hz) to the key 72 (523.3 Hz) in 200 ms.
+    synthesizer.note_on(0, 72, 100);
+    let data = synthesizer_util::render(&mut synthesizer, 13230);
+    assert!(frequency(&data[0..2205]) < 350_f32);
+    assert!((frequency(&data[8820..13230]) - 523.3).abs() < 10_f32);
+
+    // The portamento can be turned off.
+    synthesizer.note_off(0, 72);
+    synthesizer_util::render(&mut synthesizer, 4410);
+    synthesizer.process_midi_message(0, 0xB0, 0x41, 0);
+    synthesizer.note_on(0, 60, 100);
+    let data = synthesizer_util::render(&mut synthesizer, 4410);
+    assert!((frequency(&data) - 261.6).abs() < 10_f32);
+}
+
+#[test]
+fn portamento_control() {
+    // The glide to the next note starts from the key 48 (130.8 Hz) even if the portamento is off.
+    let mut synthesizer = synthesizer_util::create_synthesizer();
+    set_portamento_time(&mut synthesizer, 200);
+    synthesizer.process_midi_message(0, 0xB0, 0x54, 48);
+    synthesizer.note_on(0, 60, 100);
+    let data = synthesizer_util::render(&mut synthesizer, 13230);
+    assert!(frequency(&data[0..2205]) < 200_f32);
+    assert!((frequency(&data[8820..13230]) - 261.6).abs() < 10_f32);
+
+    // The portamento control only affects the next note.
+    synthesizer.note_off(0, 60);
+    synthesizer.note_on(0, 72, 100);
+    let data = synthesizer_util::render(&mut synthesizer, 4410);
+    assert!(frequency(&data[0..2205]) > 500_f32);
+}
+
+#[test]
+fn mono_mode() {
+    let mut synthesizer = synthesizer_util::create_synthesizer();
+    synthesizer.process_midi_message(0, 0xB0, 0x7E, 1);
+    synthesizer.note_on(0, 60, 100);
+    synthesizer_util::render(&mut synthesizer, 4410);
+
+    // The held note moves to the new key.
+    synthesizer.note_on(0, 64, 100);
+    let data = synthesizer_util::render(&mut synthesizer, 4410);
+    assert!((frequency(&data) - 329.6).abs() < 10_f32);
+
+    // The note-off of the previous key does not stop the note.
+    synthesizer.note_off(0, 60);
+    synthesizer_util::render(&mut synthesizer, 4410);
+    assert!(synthesizer_util::rms(&synthesizer_util::render(&mut synthesizer, 4410)) > 0.01);
+
+    synthesizer.note_off(0, 64);
+    synthesizer_util::render(&mut synthesizer, 4410);
+    assert!(synthesizer_util::rms(&synthesizer_util::render(&mut synthesizer, 4410)) < 1.0E-4);
+}
+
+#[test]
+fn mono_legato_does_not_restart_envelope() {
+    let mut expected = synthesizer_util::create_synthesizer();
+    expected.note_on(0, 60, 100);
+    expected.note_on(0, 64, 100);
+
+    // The voice keeps a single note with the envelope already in progress.
+    let mut actual = synthesizer_util::create_synthesizer();
+    actual.process_midi_message(0, 0xB0, 0x7E, 1);
+    actual.note_on(0, 60, 100);
+    actual.note_on(0, 64, 100);
+
+    let mut single = synthesizer_util::create_synthesizer();
+    single.note_on(0, 64, 100);
+
+    let actual = synthesizer_util::render(&mut actual, 4410);
+    synthesizer_util::assert_same(&actual, &synthesizer_util::render(&mut single, 4410));
+    assert!(
+        synthesizer_util::rms(&actual)
+            < synthesizer_util::rms(&synthesizer_util::render(&mut expected, 4410))
+    );
+}
+
+#[test]
+fn poly_mode() {
+    let mut expected = synthesizer_util::create_synthesizer();
+    expected.note_on(0, 60, 100);
+    expected.note_on(0, 64, 100);
+
+    let mut actual = synthesizer_util::create_synthesizer();
+    actual.process_midi_message(0, 0xB0, 0x7E, 1);
+    actual.process_midi_message(0, 0xB0, 0x7F, 0);
+    actual.note_on(0, 60, 100);
+    actual.note_on(0, 64, 100);
+
+    synthesizer_util::assert_same(
+        &synthesizer_util::render(&mut actual, 4410),
+        &synthesizer_util::render(&mut expected, 4410),
+    );
+}
+
+#[test]
+fn reset_all_controllers_releases_pedals() {
+    let mut synthesizer = synthesizer_util::create_synthesizer();
+    synthesizer.note_on(0, 60, 100);
+    synthesizer.process_midi_message(0, 0xB0, 0x42, 127);
+    synthesizer.note_off(0, 60);
+    synthesizer_util::render(&mut synthesizer, 4410);
+    assert!(synthesizer_util::rms(&synthesizer_util::render(&mut synthesizer, 4410)) > 0.01);
+
+    synthesizer.process_midi_message(0, 0xB0, 0x79, 0);
+    synthesizer_util::render(&mut synthesizer, 4410);
+    assert!(synthesizer_util::rms(&synthesizer_util::render(&mut synthesizer, 4410)) < 1.0E-4);
+}
@@ -0,0 +1,96 @@
+use rustysynth::SynthesizerHandle;
+use std::thread;
+
+use crate::synthesizer_util;
+
+fn assert_send_sync_clone<T: Send + Sync + Clone>() {}
+
+#[test]
+fn messages_from_threads() {
+    assert_send_sync_clone::<SynthesizerHandle>();
+
+    let mut synthesizer1 = synthesizer_util::create_synthesizer();
+    synthesizer1.note_on(0, 60, 100);
+    synthesizer1.note_on(0, 64, 100);
+
+    let mut synthesizer2 = synthesizer_util::create_synthesizer();
+    let handle = synthesizer2.create_handle();
+    let threads: Vec<_> = [60, 64]
+        .into_iter()
+        .map(|key| {
+            let handle = handle.clone();
+            thread::spawn(move || assert!(handle.process_midi_message(0, 0x90, key, 100)))
+        })
+        .collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+
+    // The events are processed at the beginning of the first block.
+    let block1 = synthesizer_util::render(&mut synthesizer1, 4410);
+    let block2 = synthesizer_util::render(&mut synthesizer2, 4410);
+    for (value1, value2) in block1.iter().zip(block2.iter()) {
+        assert!((value1 - value2).abs() < 1.0E-6);
+    }
+}
+
+#[test]
+fn timestamped_message() {
+    let mut synthesizer = synthesizer_util::create_synthesizer();
+    let handle = synthesizer.create_handle();
+    assert!(handle.process_midi_message_at(1000, 0, 0x90, 60, 100));
+
+    let block = synthesizer_util::render(&mut synthesizer, 4410);
+    let first = block.iter().position(|value| value.abs() > 1.0E-6).unwrap();
+    assert!((1000..1128).contains(&first));
+}
+
+#[test]
+fn full_queue() {
+    let mut synthesizer = synthesizer_util::create_synthesizer();
+    let handle = synthesizer.create_handle();
+
+    let accepted = (0..2000)
+        .filter(|_| handle.process_midi_message(0, 0xB0, 7, 100))
+        .count();
+    assert_eq!(accepted, 1024);
+    assert!(!handle.note_off_all(false));
+
+    // The queue is emptied by rendering.
+    synthesizer_util::render(&mut synthesizer, 64);
+    assert!(handle.note_off_all(false));
+}
+
+#[test]
+fn events_wait_for_scheduled_messages() {
+    let mut synthesizer = synthesizer_util::create_synthesizer();
+    let handle = synthesizer.create_handle();
+
+    // The messages given directly use up the space shared with the events.
+    for _ in 0..1024 {
+        synthesizer.process_midi_message_at(1000, 0, 0xB0, 7, 100);
+    }
+    assert!(handle.process_midi_message(0, 0x90, 60, 100));
+
+    // The event is not lost, and is processed by the block after the scheduled messages.
+    let block = synthesizer_util::render(&mut synthesizer, 4410);
+    let first = block.iter().position(|value| value.abs() > 1.0E-6).unwrap();
+    assert!(first < 1128);
+}
+
+#[test]
+fn sysex_and_parameters() {
+    let mut synthesizer = synthesizer_util::create_synthesizer();
+    let handle = synthesizer.create_handle();
+
+    assert!(handle.set_master_volume(0.25));
+    synthesizer_util::render(&mut synthesizer, 64);
+    assert_eq!(synthesizer.get_master_volume(), 0.25);
+
+    // Master Volume with the half of the maximum value.
+    assert!(handle.process_sysex(&[0xF0, 0x7F, 0x7F, 0x04, 0x01, 0x00, 0x40, 0xF7]));
+    synthesizer_util::render(&mut synthesizer, 64);
+    assert!((synthesizer.get_master_volume() - 0.25).abs() < 1.0E-3);
+
+    assert!(!handle.process_sysex(&[0; 100]));
+}
@@ -0,0 +1,147 @@
+use rustysynth::Synthesizer;
+
+use crate::synthesizer_util;
+
+fn differs(actual: &[f32], expected: &[f32]) -> bool {
+    actual
+        .iter()
+        .zip(expected.iter())
+        .any(|(a, e)| (a - e).abs() > 0.01)
+}
+
+// Sends the MPE configuration message.
+fn configure(synthesizer: &mut Synthesizer, manager_channel: i32, member_count: i32) {
+    synthesizer.process_midi_message(manager_channel, 0xB0, 0x65, 0);
+    synthesizer.process_midi_message(manager_channel, 0xB0, 0x64, 6);
+    synthesizer.process_midi_message(manager_channel, 0xB0, 0x06, member_count);
+}
+
+#[test]
+fn configuration_message() {
+    let mut synthesizer = synthesizer_util::create_synthesizer();
+    configure(&mut synthesizer, 0, 5);
+    assert_eq!(synthesizer.get_mpe_member_count(0), 5);
+    assert_eq!(synthesizer.get_mpe_member_count(15), 0);
+
+    // The lower zone shrinks for the upper zone.
+    configure(&mut synthesizer, 15, 12);
+    assert_eq!(synthesizer.get_mpe_member_count(0), 2);
+    assert_eq!(synthesizer.get_mpe_member_count(15), 12);
+
+    // The channels other than the managers cannot be configured.
+    configure(&mut synthesizer, 3, 4);
+    assert_eq!(synthesizer.get_mpe_member_count(3), 0);
+
+    configure(&mut synthesizer, 15, 0);
+    assert_eq!(synthesizer.get_mpe_member_count(15), 0);
+
+    // The zones are cleared by the reset.
+    synthesizer.reset();
+    assert_eq!(synthesizer.get_mpe_member_count(0), 0);
+}
+
+#[test]
+fn per_note_pitch_bend() {
+    // The pitch bend range of the members is 48 semitones, so a quarter of the range is 12 semitones.
+    let mut actual = synthesizer_util::create_synthesizer();
+    configure(&mut actual, 0, 3);
+    actual.process_midi_message(1, 0xE0, 0, 80);
+    actual.note_on(1, 60, 100);
+    actual.note_on(2, 64, 100);
+
+    let mut expected = synthesizer_util::create_synthesizer();
+    expected.note_on(1, 72, 100);
+    expected.note_on(2, 64, 100);
+
+    synthesizer_util::assert_same(
+        &synthesizer_util::render(&mut actual, 4410),
+        &synthesizer_util::render(&mut expected, 4410),
+    );
+}
+
+#[test]
+fn manager_pitch_bend() {
+    // The pitch bend range of the manager is 2 semitones, which is added to the members.
+    let mut actual = synthesizer_util::create_synthesizer();
+    configure(&mut actual, 15, 3);
+    actual.process_midi_message(15, 0xE0, 0, 96);
+    actual.process_midi_message(14, 0xE0, 0, 80);
+    actual.note_on(14, 60, 100);
+
+    let mut expected = synthesizer_util::create_synthesizer();
+    expected.note_on(1, 73, 100);
+
+    synthesizer_util::assert_same(
+        &synthesizer_util::render(&mut actual, 4410),
+        &synthesizer_util::render(&mut expected, 4410),
+    );
+}
+
+#[test]
+fn manager_controllers() {
+    let mut actual = synthesizer_util::create_synthesizer();
+    configure(&mut actual, 0, 3);
+    actual.process_midi_message(0, 0xB0, 0x07, 50);
+    actual.process_midi_message(0, 0xB0, 0x0A, 20);
+    actual.note_on(2, 60, 100);
+
+    // The channels out of the zone are not affected.
+    actual.note_on(5, 64, 100);
+
+    let mut expected = synthesizer_util::create_synthesizer();
+    expected.process_midi_message(2, 0xB0, 0x07, 50);
+    expected.process_midi_message(2, 0xB0, 0x0A, 20);
+    expected.note_on(2, 60, 100);
+    expected.note_on(5, 64, 100);
+
+    synthesizer_util::assert_same(
+        &synthesizer_util::render(&mut actual, 4410),
+        &synthesizer_util::render(&mut expected, 4410),
+    );
+}
+
+#[test]
+fn per_note_brightness_and_pressure() {
+    let mut plain = synthesizer_util::create_synthesizer();
+    plain.note_on(1, 60, 100);
+    let plain = synthesizer_util::render(&mut plain, 4410);
+
+    // The messages to the other member channel do not affect the note.
+    let mut other = synthesizer_util::create_synthesizer();
+    configure(&mut other, 0, 3);
+    other.process_midi_message(2, 0xB0, 0x4A, 0);
+    other.process_midi_message(2, 0xD0, 127, 0);
+    other.note_on(1, 60, 100);
+    synthesizer_util::assert_same(&synthesizer_util::render(&mut other, 4410), &plain);
+
+    // The brightness of 0 lowers the filter cutoff frequency.
+    let mut darker = synthesizer_util::create_synthesizer();
+    configure(&mut darker, 0, 3);
+    darker.process_midi_message(1, 0xB0, 0x4A, 0);
+    darker.note_on(1, 60, 100);
+    assert!(differs(
+        &synthesizer_util::render(&mut darker, 4410),
+        &plain
+    ));
+
+    // The pressure adds the vibrato.
+    let mut pressed = synthesizer_util::create_synthesizer();
+    configure(&mut pressed, 0, 3);
+    pressed.process_midi_message(1, 0xD0, 127, 0);
+    pressed.note_on(1, 60, 100);
+    assert!(differs(
+        &synthesizer_util::render(&mut pressed, 4410),
+        &plain
+    ));
+}
+
+#[test]
+fn manager_stops_zone_notes() {
+    let mut synthesizer = synthesizer_util::create_synthesizer();
+    configure(&mut synthesizer, 0, 3);
+    synthesizer.note_on(1, 60, 100);
+    synthesizer.note_on(3, 64, 100);
+    synthesizer.process_midi_message(0, 0xB0, 0x7B, 0);
+    synthesizer_util::render(&mut synthesizer, 4410);
+    assert!(synthesizer_util::rms(&synthesizer_util::render(&mut synthesizer, 4410)) < 1.0E-4);
+}
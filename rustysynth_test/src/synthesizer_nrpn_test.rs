@@ -0,0 +1,133 @@
+use rustysynth::Synthesizer;
+
+use crate::synthesizer_util;
+
+// The generator number of the initial attenuation in the SoundFont.
+const INITIAL_ATTENUATION: i32 = 48;
+
+// Sends the SoundFont 2.01 NRPN to offset a generator.
+fn set_generator_offset(synthesizer: &mut Synthesizer, generator: i32, offset: i32) {
+    let value = offset + 8192;
+    synthesizer.process_midi_message(0, 0xB0, 0x63, 120);
+    synthesizer.process_midi_message(0, 0xB0, 0x62, generator);
+    synthesizer.process_midi_message(0, 0xB0, 0x06, value >> 7);
+    synthesizer.process_midi_message(0, 0xB0, 0x26, value & 0x7F);
+}
+
+#[test]
+fn generator_offset_on_active_voice() {
+    let mut synthesizer = synthesizer_util::create_synthesizer();
+    synthesizer.note_on(0, 60, 100);
+    let before = synthesizer_util::rms(&synthesizer_util::render(&mut synthesizer, 4410));
+
+    // The attenuation of 24 dB is reduced to 40% as the note-on, which is about -9.6 dB.
+    set_generator_offset(&mut synthesizer, INITIAL_ATTENUATION, 240);
+    synthesizer_util::render(&mut synthesizer, 441);
+    let after = synthesizer_util::rms(&synthesizer_util::render(&mut synthesizer, 4410));
+    let ratio = after / before;
+    assert!((0.3..0.36).contains(&ratio));
+}
+
+#[test]
+fn generator_offset_on_new_voice() {
+    let mut expected = synthesizer_util::create_synthesizer();
+    expected.note_on(0, 60, 100);
+    set_generator_offset(&mut expected, INITIAL_ATTENUATION, 240);
+
+    let mut actual = synthesizer_util::create_synthesizer();
+    set_generator_offset(&mut actual, INITIAL_ATTENUATION, 240);
+    actual.note_on(0, 60, 100);
+
+    // The other channels are not affected.
+    actual.note_on(1, 64, 100);
+    let mut other = synthesizer_util::create_synthesizer();
+    other.note_on(1, 64, 100);
+
+    let expected: Vec<f32> = synthesizer_util::render(&mut expected, 4410)
+        .iter()
+        .zip(synthesizer_util::render(&mut other, 4410).iter())
+        .map(|(a, b)| a + b)
+        .collect();
+    synthesizer_util::assert_same(&synthesizer_util::render(&mut actual, 4410), &expected);
+}
+
+#[test]
+fn reset_all_controllers_clears_offsets() {
+    let mut expected = synthesizer_util::create_synthesizer();
+    expected.note_on(0, 60, 100);
+
+    let mut actual = synthesizer_util::create_synthesizer();
+    set_generator_offset(&mut actual, INITIAL_ATTENUATION, 240);
+    actual.process_midi_message(0, 0xB0, 0x79, 0);
+    actual.note_on(0, 60, 100);
+
+    synthesizer_util::assert_same(
+        &synthesizer_util::render(&mut actual, 4410),
+        &synthesizer_util::render(&mut expected, 4410),
+    );
+}
+
+#[test]
+fn data_increment_and_decrement() {
+    // The coarse tuning of +2 semitones is set by the data entry.
+    let mut expected = synthesizer_util::create_synthesizer();
+    expected.process_midi_message(0, 0xB0, 0x65, 0);
+    expected.process_midi_message(0, 0xB0, 0x64, 2);
+    expected.process_midi_message(0, 0xB0, 0x06, 66);
+    expected.note_on(0, 60, 100);
+
+    // The same tuning is set by incrementing three times and decrementing once.
+    let mut actual = synthesizer_util::create_synthesizer();
+    actual.process_midi_message(0, 0xB0, 0x65, 0);
+    actual.process_midi_message(0, 0xB0, 0x64, 2);
+    for _ in 0..3 {
+        actual.process_midi_message(0, 0xB0, 0x60, 0);
+    }
+    actual.process_midi_message(0, 0xB0, 0x61, 0);
+    actual.note_on(0, 60, 100);
+
+    synthesizer_util::assert_same(
+        &synthesizer_util::render(&mut actual, 4410),
+        &synthesizer_util::render(&mut expected, 4410),
+    );
+}
+
+#[test]
+fn nrpn_does_not_change_rpn() {
+    let mut expected = synthesizer_util::create_synthesizer();
+    expected.process_midi_message(0, 0xE0, 0, 127);
+    expected.note_on(0, 60, 100);
+
+    // After the NRPN is selected, the data entry does not change the pitch bend range.
+    let mut actual = synthesizer_util::create_synthesizer();
+    actual.process_midi_message(0, 0xB0, 0x65, 0);
+    actual.process_midi_message(0, 0xB0, 0x64, 0);
+    actual.process_midi_message(0, 0xB0, 0x63, 1);
+    actual.process_midi_message(0, 0xB0, 0x62, 8);
+    actual.process_midi_message(0, 0xB0, 0x06, 12);
+    actual.process_midi_message(0, 0xE0, 0, 127);
+    actual.note_on(0, 60, 100);
+
+    synthesizer_util::assert_same(
+        &synthesizer_util::render(&mut actual, 4410),
+        &synthesizer_util::render(&mut expected, 4410),
+    );
+}
+
+#[test]
+fn midi2_assignable_controller() {
+    let mut expected = synthesizer_util::create_synthesizer();
+    set_generator_offset(&mut expected, INITIAL_ATTENUATION, 240);
+    expected.note_on(0, 60, 100);
+
+    // The 32-bit data is mapped onto the 14-bit data of the NRPN.
+    let mut actual = synthesizer_util::create_synthesizer();
+    let value = ((240 + 8192) as u32) << 18;
+    actual.process_ump(&[0x4030_7800 | INITIAL_ATTENUATION as u32, value]);
+    actual.note_on(0, 60, 100);
+
+    synthesizer_util::assert_same(
+        &synthesizer_util::render(&mut actual, 4410),
+        &synthesizer_util::render(&mut expected, 4410),
+    );
+}
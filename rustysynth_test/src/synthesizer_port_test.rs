@@ -0,0 +1,149 @@
+use rustysynth::MidiFile;
+use rustysynth::MidiFileSequencer;
+use rustysynth::SequencerEvent;
+use rustysynth::Synthesizer;
+use rustysynth::SynthesizerError;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use crate::midifile_util;
+use crate::soundfont_util;
+use crate::synthesizer_util;
+
+fn create_synthesizer(channel_count: usize) -> Synthesizer {
+    let mut settings = synthesizer_util::create_settings();
+    settings.channel_count = channel_count;
+    synthesizer_util::create_synthesizer_with_settings(&settings)
+}
+
+#[test]
+fn channel_count() {
+    let synthesizer = create_synthesizer(48);
+    assert_eq!(synthesizer.get_channel_count(), 48);
+
+    for channel_count in [0, 241] {
+        let mut settings = synthesizer_util::create_settings();
+        settings.channel_count = channel_count;
+        let result = Synthesizer::new(&soundfont_util::create_sound_font(), &settings);
+        assert!(matches!(
+            result,
+            Err(SynthesizerError::ChannelCountOutOfRange(value)) if value == channel_count
+        ));
+    }
+}
+
+#[test]
+fn port_addressing() {
+    // The channel 0 of the port 2 is the channel 32.
+    let mut synthesizer = create_synthesizer(48);
+    synthesizer.process_port_midi_message(2, 0, 0x90, 60, 100);
+    let expected = synthesizer_util::render(&mut synthesizer, 4410);
+    assert!(synthesizer_util::peak(&expected) > 0.01);
+
+    let mut synthesizer = create_synthesizer(48);
+    synthesizer.process_midi_message(32, 0x90, 60, 100);
+    assert_eq!(synthesizer_util::render(&mut synthesizer, 4410), expected);
+
+    // The ports beyond the number of channels are ignored.
+    let mut synthesizer = create_synthesizer(16);
+    synthesizer.process_port_midi_message(1, 0, 0x90, 60, 100);
+    synthesizer.process_midi_message(16, 0x90, 60, 100);
+    assert!(synthesizer_util::peak(&synthesizer_util::render(&mut synthesizer, 4410)) < 1.0E-6);
+}
+
+#[test]
+fn ump_group_addressing() {
+    // The MIDI 1.0 and MIDI 2.0 note-ons to the channel 0 of the group 2 are sent to the channel 32.
+    // The MIDI 2.0 note-on is compared with the one sent to the channel 0 with the same volume.
+    let mut synthesizer = create_synthesizer(48);
+    synthesizer.process_midi_message(0, 0xB0, 7, 50);
+    synthesizer.process_midi_message(32, 0xB0, 7, 50);
+    synthesizer.process_midi_message(32, 0x90, 60, 100);
+    synthesizer.process_ump(&[0x4090_4000, 0xC800_0000]);
+    let expected = synthesizer_util::render(&mut synthesizer, 4410);
+
+    let mut synthesizer = create_synthesizer(48);
+    synthesizer.process_midi_message(32, 0xB0, 7, 50);
+    synthesizer.process_ump(&[0x2290_3C64]);
+    synthesizer.process_ump(&[0x4290_4000, 0xC800_0000]);
+    synthesizer_util::assert_same(&synthesizer_util::render(&mut synthesizer, 4410), &expected);
+
+    // The groups beyond the number of channels are ignored.
+    let mut synthesizer = create_synthesizer(32);
+    synthesizer.process_ump(&[0x2290_3C64]);
+    assert!(synthesizer_util::peak(&synthesizer_util::render(&mut synthesizer, 4410)) < 1.0E-6);
+
+    // With 16 channels, all the groups address the same channels.
+    let mut synthesizer = create_synthesizer(16);
+    synthesizer.process_midi_message(0, 0x90, 60, 100);
+    let expected = synthesizer_util::render(&mut synthesizer, 4410);
+
+    let mut synthesizer = create_synthesizer(16);
+    synthesizer.process_ump(&[0x2290_3C64]);
+    synthesizer_util::assert_same(&synthesizer_util::render(&mut synthesizer, 4410), &expected);
+}
+
+#[test]
+fn percussion_channels() {
+    let mut synthesizer = create_synthesizer(32);
+    assert!(synthesizer.is_percussion_channel(9));
+    assert!(synthesizer.is_percussion_channel(25));
+    assert!(!synthesizer.is_percussion_channel(16));
+    assert!(!synthesizer.is_percussion_channel(32));
+
+    // The setting is kept across the reset.
+    synthesizer.set_percussion_channel(25, false);
+    synthesizer.set_percussion_channel(26, true);
+    synthesizer.reset();
+    assert!(synthesizer.is_percussion_channel(9));
+    assert!(!synthesizer.is_percussion_channel(25));
+    assert!(synthesizer.is_percussion_channel(26));
+}
+
+// Gets the channels of the note-ons sent by the sequencer.
+fn get_note_on_channels(midi_file: &Arc<MidiFile>, channel_count: usize) -> Vec<i32> {
+    let mut sequencer = MidiFileSequencer::new(create_synthesizer(channel_count));
+    let channels = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&channels);
+    sequencer.set_event_callback(move |event, _| {
+        if let SequencerEvent::Message {
+            channel,
+            command: 0x90,
+            ..
+        } = event
+        {
+            sink.lock().unwrap().push(channel);
+        }
+    });
+    sequencer.play(midi_file, false);
+
+    let mut left = vec![0_f32; 4410];
+    let mut right = vec![0_f32; 4410];
+    sequencer.render(&mut left[..], &mut right[..]);
+
+    let channels = channels.lock().unwrap();
+    channels.clone()
+}
+
+#[test]
+fn port_meta_event() {
+    // The second track is sent to the port 1.
+    let track1 = midifile_util::track(&[
+        midifile_util::event(0, &[0x90, 60, 100]),
+        midifile_util::event(480, &[0x80, 60, 0]),
+        midifile_util::end_of_track(0),
+    ]);
+    let track2 = midifile_util::track(&[
+        midifile_util::meta_event(0, 0x21, &[1]),
+        midifile_util::event(0, &[0x92, 64, 100]),
+        midifile_util::event(480, &[0x82, 64, 0]),
+        midifile_util::end_of_track(0),
+    ]);
+    let data = midifile_util::midi_file(1, 480, &[track1, track2]);
+    let midi_file = Arc::new(MidiFile::new(&mut &data[..]).unwrap());
+    assert_eq!(get_note_on_channels(&midi_file, 32), vec![0, 18]);
+
+    // The channels of the ports can be remapped.
+    let remapped = midi_file.remap_channels(|channel| if channel == 18 { 40 } else { channel });
+    assert_eq!(get_note_on_channels(&Arc::new(remapped), 48), vec![0, 40]);
+}
@@ -0,0 +1,172 @@
+use rustysynth::PressureMapping;
+use rustysynth::SoundFont;
+use rustysynth::Synthesizer;
+use std::sync::Arc;
+
+use crate::soundfont_util;
+use crate::synthesizer_util;
+
+// The source of the channel pressure and the generator number of the initial attenuation.
+const CHANNEL_PRESSURE: u16 = 13;
+const INITIAL_ATTENUATION: u16 = 48;
+
+fn create_synthesizer(volume: f32) -> Synthesizer {
+    let mut synthesizer = synthesizer_util::create_synthesizer();
+
+    let mut mapping = PressureMapping::default();
+    mapping.vibrato_depth = 0_f32;
+    mapping.volume = volume;
+    synthesizer.set_pressure_mapping(mapping);
+    synthesizer
+}
+
+// Gets the ratio of the levels before and after the message.
+fn get_level_ratio(synthesizer: &mut Synthesizer, command: i32, data1: i32, data2: i32) -> f32 {
+    synthesizer.note_on(0, 60, 100);
+    let before = synthesizer_util::rms(&synthesizer_util::render(synthesizer, 4410));
+    synthesizer.process_midi_message(0, command, data1, data2);
+    synthesizer_util::render(synthesizer, 441);
+    let after = synthesizer_util::rms(&synthesizer_util::render(synthesizer, 4410));
+    after / before
+}
+
+#[test]
+fn channel_pressure() {
+    // The volume swell of 6 dB is about twice the amplitude.
+    let mut synthesizer = create_synthesizer(6_f32);
+    let ratio = get_level_ratio(&mut synthesizer, 0xD0, 127, 0);
+    assert!((1.9..2.05).contains(&ratio));
+}
+
+#[test]
+fn key_pressure() {
+    let mut synthesizer = create_synthesizer(6_f32);
+    let ratio = get_level_ratio(&mut synthesizer, 0xA0, 60, 127);
+    assert!((1.9..2.05).contains(&ratio));
+
+    // The pressure on the other keys does not affect the note.
+    let mut expected = create_synthesizer(6_f32);
+    expected.note_on(0, 60, 100);
+
+    let mut actual = create_synthesizer(6_f32);
+    actual.note_on(0, 60, 100);
+    actual.process_midi_message(0, 0xA0, 64, 127);
+
+    synthesizer_util::assert_same(
+        &synthesizer_util::render(&mut actual, 4410),
+        &synthesizer_util::render(&mut expected, 4410),
+    );
+}
+
+#[test]
+fn reset_all_controllers_clears_pressure() {
+    let mut expected = create_synthesizer(6_f32);
+    expected.note_on(0, 60, 100);
+
+    let mut actual = create_synthesizer(6_f32);
+    actual.process_midi_message(0, 0xD0, 127, 0);
+    actual.process_midi_message(0, 0xA0, 60, 127);
+    actual.process_midi_message(0, 0xB0, 0x79, 0);
+    actual.note_on(0, 60, 100);
+
+    synthesizer_util::assert_same(
+        &synthesizer_util::render(&mut actual, 4410),
+        &synthesizer_util::render(&mut expected, 4410),
+    );
+}
+
+#[test]
+fn default_vibrato() {
+    let mut expected = synthesizer_util::create_synthesizer();
+    expected.note_on(0, 60, 100);
+
+    let mut actual = synthesizer_util::create_synthesizer();
+    actual.process_midi_message(0, 0xD0, 127, 0);
+    actual.note_on(0, 60, 100);
+
+    let expected = synthesizer_util::render(&mut expected, 44100);
+    let actual = synthesizer_util::render(&mut actual, 44100);
+    assert!(actual
+        .iter()
+        .zip(expected.iter())
+        .any(|(a, e)| (a - e).abs() > 0.01));
+}
+
+#[test]
+fn soundfont_modulator() {
+    // The modulator lowers the attenuation by 24 dB, which is about -9.6 dB as the note-on,
+    // and takes precedence over the default mapping.
+    let data =
+        soundfont_util::create_sound_font_data_with_modulators(&[soundfont_util::modulator(
+            CHANNEL_PRESSURE,
+            INITIAL_ATTENUATION,
+            240,
+            0,
+        )]);
+    let sound_font = Arc::new(SoundFont::new(&mut &data[..]).unwrap());
+
+    let settings = synthesizer_util::create_settings();
+    let mut synthesizer = Synthesizer::new(&sound_font, &settings).unwrap();
+    let mut mapping = PressureMapping::default();
+    mapping.volume = 6_f32;
+    synthesizer.set_pressure_mapping(mapping);
+
+    let ratio = get_level_ratio(&mut synthesizer, 0xD0, 127, 0);
+    assert!((0.3..0.36).contains(&ratio));
+}
+
+#[test]
+fn modulator_list_without_terminator() {
+    // The last modulator is kept, since it is not the all-zero terminator.
+    let data = soundfont_util::create_sound_font_data_without_modulator_terminator(&[
+        soundfont_util::modulator(CHANNEL_PRESSURE, INITIAL_ATTENUATION, 240, 0),
+    ]);
+    let sound_font = Arc::new(SoundFont::new(&mut &data[..]).unwrap());
+
+    let settings = synthesizer_util::create_settings();
+    let mut synthesizer = Synthesizer::new(&sound_font, &settings).unwrap();
+    let mut mapping = PressureMapping::default();
+    mapping.volume = 6_f32;
+    synthesizer.set_pressure_mapping(mapping);
+
+    let ratio = get_level_ratio(&mut synthesizer, 0xD0, 127, 0);
+    assert!((0.3..0.36).contains(&ratio));
+}
+
+#[test]
+fn malformed_modulator_list() {
+    // The modulator list whose size is not a multiple of 10 is ignored.
+    let data = soundfont_util::create_sound_font_data_with_modulators(&[vec![0; 8]]);
+    let sound_font = Arc::new(SoundFont::new(&mut &data[..]).unwrap());
+
+    let settings = synthesizer_util::create_settings();
+    let mut actual = Synthesizer::new(&sound_font, &settings).unwrap();
+    actual.note_on(0, 60, 100);
+
+    let mut expected = synthesizer_util::create_synthesizer();
+    expected.note_on(0, 60, 100);
+
+    synthesizer_util::assert_same(
+        &synthesizer_util::render(&mut actual, 4410),
+        &synthesizer_util::render(&mut expected, 4410),
+    );
+}
+
+#[test]
+fn midi2_pressure() {
+    let mut expected = create_synthesizer(6_f32);
+    expected.note_on(0, 60, 100);
+    expected.process_midi_message(0, 0xD0, 100, 0);
+    expected.process_midi_message(0, 0xA0, 60, 50);
+
+    // The 32-bit values are mapped onto the 14-bit values.
+    let mut actual = create_synthesizer(6_f32);
+    actual.note_on(0, 60, 100);
+    actual.process_ump(&[0x40D0_0000, 100 << 25]);
+    actual.process_ump(&[0x40A0_3C00, 50 << 25]);
+
+    synthesizer_util::assert_same(
+        &synthesizer_util::render(&mut actual, 4410),
+        &synthesizer_util::render(&mut expected, 4410),
+    );
+}
@@ -0,0 +1,42 @@
+use rustysynth::Synthesizer;
+use rustysynth::SynthesizerSettings;
+
+use crate::soundfont_util;
+
+// The effects are disabled, so that the output only depends on the notes.
+pub fn create_settings() -> SynthesizerSettings {
+    let mut settings = SynthesizerSettings::new(44100);
+    settings.enable_reverb_and_chorus = false;
+    settings
+}
+
+pub fn create_synthesizer() -> Synthesizer {
+    create_synthesizer_with_settings(&create_settings())
+}
+
+pub fn create_synthesizer_with_settings(settings: &SynthesizerSettings) -> Synthesizer {
+    Synthesizer::new(&soundfont_util::create_sound_font(), settings).unwrap()
+}
+
+// Renders the waveform and returns the left channel.
+pub fn render(synthesizer: &mut Synthesizer, length: usize) -> Vec<f32> {
+    let mut left = vec![0_f32; length];
+    let mut right = vec![0_f32; length];
+    synthesizer.render(&mut left[..], &mut right[..]);
+    left
+}
+
+pub fn rms(data: &[f32]) -> f32 {
+    (data.iter().map(|value| value * value).sum::<f32>() / data.len() as f32).sqrt()
+}
+
+pub fn peak(data: &[f32]) -> f32 {
+    data.iter().fold(0_f32, |max, value| max.max(value.abs()))
+}
+
+pub fn assert_same(actual: &[f32], expected: &[f32]) {
+    assert_eq!(actual.len(), expected.len());
+    for (a, e) in actual.iter().zip(expected.iter()) {
+        assert!((a - e).abs() < 1.0E-4);
+    }
+}